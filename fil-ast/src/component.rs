@@ -1,9 +1,9 @@
 use std::path::PathBuf;
 
-use super::{Command, Id, Signature};
+use super::{Command, Id, Signature, Test};
 use fil_gen as gen;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 /// A external or generate definition in Filament
 pub struct Extern {
     pub path: String,
@@ -29,7 +29,7 @@ impl Extern {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 /// A component in Filament
 pub struct Component {
     // Signature of this component
@@ -44,6 +44,7 @@ impl Component {
     }
 }
 
+#[derive(Clone)]
 pub struct Namespace {
     /// Imported files
     pub imports: Vec<String>,
@@ -51,6 +52,8 @@ pub struct Namespace {
     pub externs: Vec<Extern>,
     /// Components defined in this file
     pub components: Vec<Component>,
+    /// Tests defined in this file
+    pub tests: Vec<Test>,
     /// Top-level component id
     pub toplevel: String,
 }
@@ -61,6 +64,7 @@ impl Namespace {
             imports: Vec::default(),
             externs: Vec::default(),
             components: Vec::default(),
+            tests: Vec::default(),
             toplevel,
         }
     }
@@ -79,6 +79,12 @@ where
     }
 }
 
+impl<T: std::fmt::Display> std::fmt::Display for OrderConstraint<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.op, self.right)
+    }
+}
+
 impl OrderConstraint<Expr> {
     pub fn resolve_expr(self, binding: &Binding<Expr>) -> Self {
         OrderConstraint {
@@ -159,6 +159,10 @@ pub struct Invoke {
     pub abstract_vars: Vec<Loc<Time>>,
     /// Assignment for the ports
     pub ports: Vec<Loc<Port>>,
+    /// Indices of the event bindings written as `_` that must be inferred by
+    /// the compiler. The corresponding entries in `abstract_vars` are
+    /// placeholders until the schedule is inferred.
+    pub holes: Vec<usize>,
 }
 
 impl Invoke {
@@ -173,6 +177,34 @@ impl Invoke {
             instance,
             abstract_vars,
             ports,
+            holes: Vec::default(),
+        }
+    }
+
+    /// Construct an invocation where some of the event bindings may be
+    /// inferred (represented using `None`).
+    pub fn with_holes(
+        name: Loc<Id>,
+        instance: Loc<Id>,
+        abstract_vars: Vec<Loc<Option<Time>>>,
+        ports: Vec<Loc<Port>>,
+    ) -> Self {
+        let mut holes = Vec::default();
+        let abstract_vars = abstract_vars
+            .into_iter()
+            .enumerate()
+            .map(|(idx, t)| {
+                let (t, pos) = t.split();
+                let t = t.unwrap_or_else(|| {
+                    holes.push(idx);
+                    Time::unit(Id::from("_"), 0)
+                });
+                Loc::new(t, pos)
+            })
+            .collect();
+        Self {
+            holes,
+            ..Self::new(name, instance, abstract_vars, ports)
         }
     }
 
@@ -1,5 +1,5 @@
 use super::{Binding, Id, Loc};
-use fil_utils::Error;
+use fil_utils::{BigInt, BigUint, BigUintExt, Error, One, ToPrimitive, Zero};
 use itertools::Itertools;
 
 /// Binary operation over expressions
@@ -50,29 +50,31 @@ impl std::fmt::Display for Fn {
 }
 
 impl Fn {
-    pub fn eval(self, args: Vec<u64>) -> u64 {
+    /// Evaluate the function on concrete arguments. Returns `None` if the
+    /// result is not defined or too large to represent.
+    pub fn eval(self, args: Vec<BigInt>) -> Option<BigInt> {
         match (self, &*args) {
-            (Fn::Pow2, &[n]) => 2u64.pow(n as u32),
-            (Fn::Log2, &[n]) => (n as f64).log2().ceil() as u64,
-            (Fn::SinB, &[num, den]) => {
-                ((2. * std::f64::consts::PI * (num as f64) / (den as f64)).sin()
-                    as f32)
-                    .to_bits() as u64
+            (Fn::Pow2, [n]) => n
+                .to_u64()
+                .filter(|n| *n <= Self::MAX_POW2)
+                .map(|n| BigUint::pow2(n).into()),
+            (Fn::Log2, [n]) => Some(n.to_biguint()?.log2_ceil().into()),
+            (Fn::SinB, [num, den]) => {
+                let v = (2. * std::f64::consts::PI * num.to_f64()?
+                    / den.to_f64()?)
+                .sin() as f32;
+                Some(u64::from(v.to_bits()).into())
             }
-            (Fn::CosB, &[num, den]) => {
-                ((2. * std::f64::consts::PI * (num as f64) / (den as f64)).cos()
-                    as f32)
-                    .to_bits() as u64
+            (Fn::CosB, [num, den]) => {
+                let v = (2. * std::f64::consts::PI * num.to_f64()?
+                    / den.to_f64()?)
+                .cos() as f32;
+                Some(u64::from(v.to_bits()).into())
             }
-            (Fn::BitRev, &[n, numbits]) => {
-                let mut n = n;
-                let mut rev = 0;
-                for _ in 0..numbits {
-                    rev <<= 1;
-                    rev |= n & 1;
-                    n >>= 1;
-                }
-                rev
+            (Fn::BitRev, [n, numbits]) => {
+                let width =
+                    numbits.to_u64().filter(|w| *w <= Self::MAX_POW2)?;
+                Some(n.to_biguint()?.bit_rev(width).into())
             }
             _ => unreachable!(
                 "Function {} did not expect {} arguments.",
@@ -81,12 +83,16 @@ impl Fn {
             ),
         }
     }
+
+    /// Largest exponent for which `pow2` is evaluated. Also bounds the width
+    /// given to `bit_rev` since its result can be as large.
+    const MAX_POW2: u64 = 1 << 16;
 }
 
 /// An expression containing integers and abstract variables
 #[derive(Clone, Hash, Debug)]
 pub enum Expr {
-    Concrete(u64),
+    Concrete(BigUint),
     Abstract(Loc<Id>),
     ParamAccess {
         inst: Loc<Id>,
@@ -105,7 +111,7 @@ pub enum Expr {
 
 impl Default for Expr {
     fn default() -> Self {
-        Expr::Concrete(0)
+        Expr::concrete(0)
     }
 }
 
@@ -122,7 +128,9 @@ impl TryFrom<&Expr> for u64 {
 
     fn try_from(value: &Expr) -> Result<Self, Self::Error> {
         match value {
-            Expr::Concrete(n) => Ok(*n),
+            Expr::Concrete(n) => n.to_u64().ok_or_else(|| {
+                Error::malformed(format!("`{n}' does not fit in 64 bits"))
+            }),
             n => Err(Error::malformed(format!("Cannot concretize `{n}'"))),
         }
     }
@@ -131,7 +139,12 @@ impl TryFrom<&Expr> for u64 {
 impl Expr {
     /// Construct a new expression from a concrete value
     pub fn concrete(n: u64) -> Self {
-        Expr::Concrete(n)
+        Expr::Concrete(n.into())
+    }
+
+    /// Is this expression the given constant?
+    pub fn is_const(&self, n: u64) -> bool {
+        matches!(self, Expr::Concrete(c) if *c == BigUint::from(n))
     }
 
     /// Construct a new expression from an abstract variable
@@ -191,7 +204,7 @@ impl std::ops::Add for Expr {
 
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Expr::Concrete(0), e) | (e, Expr::Concrete(0)) => e,
+            (Expr::Concrete(z), e) | (e, Expr::Concrete(z)) if z.is_zero() => e,
             (Expr::Concrete(l), Expr::Concrete(r)) => Expr::Concrete(l + r),
             (left, right) => Self::op_base(Op::Add, left, right),
         }
@@ -210,11 +223,10 @@ impl std::ops::Sub for Expr {
 
     fn sub(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (lhs, Expr::Concrete(0)) => lhs,
-            (Expr::Concrete(l), Expr::Concrete(r)) => match l.checked_sub(r) {
-                Some(n) => Expr::Concrete(n),
-                None => Self::op_base(Op::Sub, l.into(), r.into()),
-            },
+            (lhs, Expr::Concrete(z)) if z.is_zero() => lhs,
+            (Expr::Concrete(l), Expr::Concrete(r)) if l >= r => {
+                Expr::Concrete(l - r)
+            }
             (left, right) => Self::op_base(Op::Sub, left, right),
         }
     }
@@ -225,10 +237,10 @@ impl std::ops::Mul for Expr {
 
     fn mul(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Expr::Concrete(0), _) | (_, Expr::Concrete(0)) => {
-                Expr::Concrete(0)
+            (Expr::Concrete(z), _) | (_, Expr::Concrete(z)) if z.is_zero() => {
+                Expr::concrete(0)
             }
-            (Expr::Concrete(1), e) | (e, Expr::Concrete(1)) => e,
+            (Expr::Concrete(o), e) | (e, Expr::Concrete(o)) if o.is_one() => e,
             (Expr::Concrete(l), Expr::Concrete(r)) => Expr::Concrete(l * r),
             (left, right) => Self::op_base(Op::Mul, left, right),
         }
@@ -240,9 +252,11 @@ impl std::ops::Div for Expr {
 
     fn div(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Expr::Concrete(0), _) => Expr::Concrete(0),
-            (e, Expr::Concrete(1)) => e,
-            (Expr::Concrete(l), Expr::Concrete(r)) => Expr::Concrete(l / r),
+            (Expr::Concrete(z), _) if z.is_zero() => Expr::concrete(0),
+            (e, Expr::Concrete(o)) if o.is_one() => e,
+            (Expr::Concrete(l), Expr::Concrete(r)) if !r.is_zero() => {
+                Expr::Concrete(l / r)
+            }
             (left, right) => Self::op_base(Op::Div, left, right),
         }
     }
@@ -253,10 +267,11 @@ impl std::ops::Rem for Expr {
 
     fn rem(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Expr::Concrete(0), _) | (_, Expr::Concrete(1)) => {
-                Expr::Concrete(0)
+            (Expr::Concrete(z), _) if z.is_zero() => Expr::concrete(0),
+            (_, Expr::Concrete(o)) if o.is_one() => Expr::concrete(0),
+            (Expr::Concrete(l), Expr::Concrete(r)) if !r.is_zero() => {
+                Expr::Concrete(l % r)
             }
-            (Expr::Concrete(l), Expr::Concrete(r)) => Expr::Concrete(l % r),
             (left, right) => Self::op_base(Op::Mod, left, right),
         }
     }
@@ -280,6 +295,12 @@ impl From<u64> for Expr {
     }
 }
 
+impl From<BigUint> for Expr {
+    fn from(v: BigUint) -> Self {
+        Self::Concrete(v)
+    }
+}
+
 impl From<Id> for Expr {
     fn from(v: Id) -> Self {
         Self::Abstract(Loc::unknown(v))
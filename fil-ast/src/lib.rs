@@ -9,6 +9,7 @@ mod loc;
 mod parser;
 mod port;
 mod signature;
+mod test;
 mod time;
 
 pub use bind_map::Binding;
@@ -25,5 +26,6 @@ pub use interval::Range;
 pub use loc::Loc;
 pub use parser::FilamentParser;
 pub use port::{InterfaceDef, PortDef};
-pub use signature::{EventBind, ParamBind, SigBind, Signature};
+pub use signature::{EventBind, ParamBind, ParamRange, SigBind, Signature};
+pub use test::{Test, TestValue, Transaction};
 pub use time::{Time, TimeSub};
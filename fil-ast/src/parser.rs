@@ -4,7 +4,7 @@
 //! Parser for Filament programs.
 use crate::{self as ast, Loc, TimeSub};
 use fil_utils::{self as utils, FilamentResult};
-use fil_utils::{FileIdx, GPosIdx, GlobalPositionTable};
+use fil_utils::{BigUint, FileIdx, GPosIdx, GlobalPositionTable, Num};
 use itertools::Itertools;
 use pest::pratt_parser::{Assoc, Op, PrattParser};
 use pest_consume::{match_nodes, Error, Parser};
@@ -40,6 +40,7 @@ lazy_static::lazy_static! {
 pub enum BodyEl {
     Ext(ast::Extern),
     Comp(ast::Component),
+    Test(ast::Test),
 }
 
 #[derive(Clone)]
@@ -115,6 +116,17 @@ impl FilamentParser {
         GPosIdx(pos)
     }
 
+    /// Apply the refinements of a parameter's type to its binding
+    fn refine(
+        pb: ast::ParamBind,
+        refines: impl Iterator<Item = Option<Loc<ast::ParamRange>>>,
+    ) -> ast::ParamBind {
+        refines.fold(pb, |pb, r| match r {
+            None => pb.int(),
+            Some(r) => pb.with_range(r),
+        })
+    }
+
     #[allow(clippy::result_large_err)]
     fn expr_helper(
         ud: UserData,
@@ -212,7 +224,9 @@ impl FilamentParser {
         let out = match_nodes!(
             input.into_children();
             [param_var(param), expr(e)] => ast::ParamBind::new(param, Some(e.take())),
-            [param_var(param)] => ast::ParamBind::new(param, None)
+            [param_var(param), param_refine(rs).., expr(e)] => Self::refine(ast::ParamBind::new(param, Some(e.take())), rs),
+            [param_var(param)] => ast::ParamBind::new(param, None),
+            [param_var(param), param_refine(rs)..] => Self::refine(ast::ParamBind::new(param, None), rs)
         );
         Ok(Loc::new(out, sp))
     }
@@ -235,6 +249,13 @@ impl FilamentParser {
             .map_err(|_| input.error("Expected valid bitwidth"))
     }
 
+    fn number(input: Node) -> ParseResult<BigUint> {
+        input
+            .as_str()
+            .parse::<BigUint>()
+            .map_err(|_| input.error("Expected valid number"))
+    }
+
     // ================ Intervals =====================
     fn time(input: Node) -> ParseResult<Loc<ast::Time>> {
         let sp = Self::get_span(&input);
@@ -286,12 +307,47 @@ impl FilamentParser {
         ))
     }
 
+    fn int_param(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    /// A refinement of the type of a parameter. `None` stands for `int`.
+    fn param_refine(input: Node) -> ParseResult<Option<Loc<ast::ParamRange>>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [int_param(_)] => None,
+            [pow2_param(r)] => Some(r),
+            [range_param(r)] => Some(r),
+        ))
+    }
+
+    fn pow2_param(input: Node) -> ParseResult<Loc<ast::ParamRange>> {
+        let sp = Self::get_span(&input);
+        Ok(Loc::new(ast::ParamRange::Pow2, sp))
+    }
+
+    fn range_param(input: Node) -> ParseResult<Loc<ast::ParamRange>> {
+        let sp = Self::get_span(&input);
+        Ok(match_nodes!(
+            input.into_children();
+            [expr(lo), expr(hi)] => Loc::new(ast::ParamRange::Bounded { lo: lo.take(), hi: hi.take() }, sp),
+        ))
+    }
+
+    fn negation(input: Node) -> ParseResult<ast::Expr> {
+        Ok(match_nodes!(
+            input.into_children();
+            [expr_base(e)] => ast::Expr::concrete(0) - e,
+        ))
+    }
+
     fn expr_base(input: Node) -> ParseResult<ast::Expr> {
         Ok(match_nodes!(
             input.into_children();
             [identifier(inst), identifier(param)] => ast::Expr::ParamAccess{ inst, param },
             [param_var(id)] => ast::Expr::abs(id),
-            [bitwidth(c)] => c.into(),
+            [number(c)] => c.into(),
+            [negation(e)] => e,
             [r#fn(f), expr(exprs)..] => ast::Expr::func(f, exprs.into_iter().map(|e| e.take()).collect()),
             [expr(e)] => e.take(),
         ))
@@ -442,7 +498,7 @@ impl FilamentParser {
                     input.error("Generated Instance name conflicts with original name");
                 }
                 let instance = ast::Instance::new(iname.clone(), component, params, lives).into();
-                let invoke = ast::Invoke::new(name, iname, abstract_vars, ports).into();
+                let invoke = ast::Invoke::with_holes(name, iname, abstract_vars, ports).into();
                 vec![instance, invoke]
             },
         ))
@@ -485,17 +541,31 @@ impl FilamentParser {
         ))
     }
 
-    fn time_args(input: Node) -> ParseResult<Vec<Loc<ast::Time>>> {
+    fn sched_hole(input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    /// Returns `None` if the event binding must be inferred
+    fn time_arg(input: Node) -> ParseResult<Loc<Option<ast::Time>>> {
+        let sp = Self::get_span(&input);
+        Ok(match_nodes!(
+            input.into_children();
+            [sched_hole(_)] => Loc::new(None, sp),
+            [time(t)] => t.map(Some),
+        ))
+    }
+
+    fn time_args(input: Node) -> ParseResult<Vec<Loc<Option<ast::Time>>>> {
         Ok(match_nodes!(
             input.into_children();
-            [time(args)..] => args.collect(),
+            [time_arg(args)..] => args.collect(),
         ))
     }
 
     #[allow(clippy::type_complexity)]
     fn invoke_args(
         input: Node,
-    ) -> ParseResult<(Vec<Loc<ast::Time>>, Vec<Loc<ast::Port>>)> {
+    ) -> ParseResult<(Vec<Loc<Option<ast::Time>>>, Vec<Loc<ast::Port>>)> {
         Ok(match_nodes!(
             input.into_children();
             [time_args(time_args), arguments(args)] => (time_args, args),
@@ -509,7 +579,7 @@ impl FilamentParser {
                 identifier(bind),
                 identifier(comp),
                 invoke_args((abstract_vars, ports))
-            ] => ast::Invoke::new(bind, comp, abstract_vars, ports)
+            ] => ast::Invoke::with_holes(bind, comp, abstract_vars, ports)
         ))
     }
     fn gte(input: Node) -> ParseResult<()> {
@@ -707,7 +777,7 @@ impl FilamentParser {
                 let sizes = sizes.collect_vec();
                 // If no size is specified, treat this is as one dimensional bundle with size 1.
                 let (sizes, s_len) = if sizes.is_empty() {
-                    (vec![Loc::unknown(ast::Expr::Concrete(1))], 1)
+                    (vec![Loc::unknown(ast::Expr::concrete(1))], 1)
                 } else {
                     let s_len = sizes.len();
                     (sizes, s_len)
@@ -840,12 +910,73 @@ impl FilamentParser {
         ))
     }
 
+    // ================ Tests =====================
+    fn test_number(input: Node) -> ParseResult<BigUint> {
+        let s = input.as_str();
+        let (radix, digits) = match s.get(..2) {
+            Some("0x") => (16, &s[2..]),
+            Some("0o") => (8, &s[2..]),
+            Some("0b") => (2, &s[2..]),
+            _ => (10, s),
+        };
+        BigUint::from_str_radix(digits, radix)
+            .map_err(|_| input.error("Expected valid number"))
+    }
+
+    fn test_unknown(_input: Node) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn test_value(input: Node) -> ParseResult<ast::TestValue> {
+        Ok(match_nodes!(
+            input.into_children();
+            [test_number(n)] => Some(n),
+            [test_unknown(_)] => None,
+        ))
+    }
+
+    fn test_assign(input: Node) -> ParseResult<(Loc<ast::Id>, ast::TestValue)> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(port), test_value(v)] => (port, v),
+        ))
+    }
+
+    fn test_ports(
+        input: Node,
+    ) -> ParseResult<Vec<(Loc<ast::Id>, ast::TestValue)>> {
+        Ok(match_nodes!(
+            input.into_children();
+            [test_assign(ports)..] => ports.collect(),
+        ))
+    }
+
+    fn transaction(input: Node) -> ParseResult<Loc<ast::Transaction>> {
+        let sp = Self::get_span(&input);
+        let txn = match_nodes!(
+            input.into_children();
+            [test_ports(inputs)] => ast::Transaction { inputs, outputs: vec![] },
+            [test_ports(inputs), test_ports(outputs)] => ast::Transaction { inputs, outputs },
+        );
+        Ok(Loc::new(txn, sp))
+    }
+
+    fn test(input: Node) -> ParseResult<ast::Test> {
+        Ok(match_nodes!(
+            input.into_children();
+            [identifier(name), identifier(comp), conc_params(params), transaction(txns)..] => {
+                ast::Test { name, comp, params, txns: txns.collect() }
+            }
+        ))
+    }
+
     fn comp_or_ext(input: Node) -> ParseResult<BodyEl> {
         Ok(match_nodes!(
             input.into_children();
             [external(sig)] => BodyEl::Ext(sig),
             [generate(sig)] => BodyEl::Ext(sig),
             [component(comp)] => BodyEl::Comp(comp),
+            [test(test)] => BodyEl::Test(test),
         ))
     }
 
@@ -866,6 +997,7 @@ impl FilamentParser {
                     match m {
                         BodyEl::Ext(sig) => namespace.externs.push(sig),
                         BodyEl::Comp(comp) => namespace.components.push(comp),
+                        BodyEl::Test(test) => namespace.tests.push(test),
                     }
                 }
                 namespace
@@ -1,5 +1,5 @@
 use super::{
-    Binding, Expr, Id, InterfaceDef, Loc, OrderConstraint, PortDef, Time,
+    Binding, Expr, Fn, Id, InterfaceDef, Loc, OrderConstraint, PortDef, Time,
     TimeSub,
 };
 use fil_utils::GPosIdx;
@@ -44,16 +44,74 @@ impl EventBind {
     }
 }
 
+#[derive(Clone)]
+/// The range of values a signature parameter may take
+pub enum ParamRange {
+    /// The parameter is within `lo..=hi`
+    Bounded { lo: Expr, hi: Expr },
+    /// The parameter is a power of two
+    Pow2,
+}
+
+impl ParamRange {
+    /// Constraints on the parameter implied by this range
+    pub fn constraints(&self, param: Expr) -> Vec<OrderConstraint<Expr>> {
+        match self {
+            ParamRange::Bounded { lo, hi } => vec![
+                OrderConstraint::gte(param.clone(), lo.clone()),
+                OrderConstraint::gte(hi.clone(), param),
+            ],
+            ParamRange::Pow2 => {
+                let log = Expr::func(Fn::Log2, vec![param.clone()]);
+                vec![OrderConstraint::eq(
+                    param,
+                    Expr::func(Fn::Pow2, vec![log]),
+                )]
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ParamRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParamRange::Bounded { lo, hi } => write!(f, "{lo}..={hi}"),
+            ParamRange::Pow2 => write!(f, "pow2"),
+        }
+    }
+}
+
 #[derive(Clone)]
 /// A parameter bound in the signature
 pub struct ParamBind {
     pub param: Loc<Id>,
     pub default: Option<Expr>,
+    /// The parameter is an integer that may be negative
+    pub signed: bool,
+    /// Ranges the values of the parameter must lie within
+    pub ranges: Vec<Loc<ParamRange>>,
 }
 
 impl ParamBind {
     pub fn new(param: Loc<Id>, default: Option<Expr>) -> Self {
-        Self { param, default }
+        Self {
+            param,
+            default,
+            signed: false,
+            ranges: vec![],
+        }
+    }
+
+    /// Mark the parameter as an integer that may be negative
+    pub fn int(mut self) -> Self {
+        self.signed = true;
+        self
+    }
+
+    /// Restrict the values the parameter may take
+    pub fn with_range(mut self, range: Loc<ParamRange>) -> Self {
+        self.ranges.push(range);
+        self
     }
 
     pub fn name(&self) -> Id {
@@ -142,12 +200,20 @@ impl Signature {
         interface_signals: Vec<InterfaceDef>,
         mut inputs: Vec<Loc<PortDef>>,
         mut outputs: Vec<Loc<PortDef>>,
-        param_constraints: Vec<Loc<OrderConstraint<Expr>>>,
+        mut param_constraints: Vec<Loc<OrderConstraint<Expr>>>,
         event_constraints: Vec<Loc<OrderConstraint<Time>>>,
         sig_bindings: Vec<Loc<SigBind>>,
     ) -> Self {
         let outputs_idx = inputs.len();
         inputs.append(&mut outputs);
+        // Ranges on parameters are checked like any other constraint
+        param_constraints.extend(params.iter().flat_map(|pb| {
+            pb.ranges.iter().flat_map(|r| {
+                r.constraints(Expr::abs(pb.param.clone()))
+                    .into_iter()
+                    .map(|c| Loc::new(c, r.pos()))
+            })
+        }));
         Self {
             name,
             params,
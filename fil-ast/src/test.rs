@@ -0,0 +1,34 @@
+use super::{Expr, Id, Loc};
+use fil_utils::BigUint;
+
+/// A value in a test. Unknown values, written as `x`, are not driven on
+/// inputs and not checked on outputs.
+pub type TestValue = Option<BigUint>;
+
+#[derive(Clone)]
+/// A transaction of a test
+pub struct Transaction {
+    /// Values of the inputs
+    pub inputs: Vec<(Loc<Id>, TestValue)>,
+    /// Expected values of the outputs
+    pub outputs: Vec<(Loc<Id>, TestValue)>,
+}
+
+#[derive(Clone)]
+/// A test of a component with concrete parameters:
+/// ```text
+/// test add32: Add[32] {
+///    (left: 1, right: 2) -> (out: 3);
+///    (left: 0xff, right: 1) -> (out: 256);
+/// }
+/// ```
+pub struct Test {
+    /// Name of the test
+    pub name: Loc<Id>,
+    /// Component under test
+    pub comp: Loc<Id>,
+    /// Parameters of the component
+    pub params: Vec<Loc<Expr>>,
+    /// Transactions of the test in the order they start
+    pub txns: Vec<Loc<Transaction>>,
+}
@@ -1,12 +1,13 @@
 use super::{
-    AddCtx, Cmp, CmpOp, Command, Ctx, Event, EventIdx, Expr, ExprIdx, Fact,
-    IndexStore, Info, InfoIdx, InstIdx, Instance, InterfaceSrc, Interned,
-    InvIdx, Invoke, MutCtx, Param, ParamIdx, Port, PortIdx, Prop, PropIdx,
-    Time, TimeSub,
+    AddCtx, Cmp, CmpOp, Command, Ctx, DisplayCtx, Event, EventIdx, Expr,
+    ExprIdx, Fact, IndexStore, Info, InfoIdx, InstIdx, Instance, InterfaceSrc,
+    Interned, InvIdx, Invoke, MonoSrc, MutCtx, Param, ParamIdx, Port, PortIdx,
+    Prop, PropIdx, Time, TimeSub,
 };
 use crate::{utils::Idx, ParamOwner};
 use fil_ast as ast;
 use fil_derive::Ctx;
+use fil_utils::{BigInt, BigIntExt, Zero};
 use itertools::Itertools;
 
 #[derive(Default, PartialEq, Eq, Hash, Clone, Copy)]
@@ -75,6 +76,10 @@ pub struct Component {
     pub src_info: Option<InterfaceSrc>,
     /// unannotated ports associated with this component
     pub unannotated_ports: Box<Vec<(ast::Id, u64)>>,
+    /// Name of the source-level definition of this component
+    pub def_name: ast::Id,
+    /// Bindings this component was monomorphized with
+    pub mono_src: Option<MonoSrc>,
 
     // ============== Component structure ===============
     /// Commands in the component
@@ -112,6 +117,11 @@ impl Component {
 
     /// Add a number to the context and get handle to it.
     pub fn num(&mut self, n: u64) -> ExprIdx {
+        self.exprs.intern(Expr::Concrete(n.into()))
+    }
+
+    /// Add an arbitrary-precision number to the context and get handle to it.
+    pub fn big_num(&mut self, n: BigInt) -> ExprIdx {
         self.exprs.intern(Expr::Concrete(n))
     }
 
@@ -401,8 +411,8 @@ impl Component {
         match prop {
             Prop::Cmp(cmp) => {
                 let CmpOp { op, lhs, rhs } = cmp;
-                let lhs = lhs.as_concrete(self).unwrap();
-                let rhs = rhs.as_concrete(self).unwrap();
+                let lhs = lhs.as_big(self).unwrap().clone();
+                let rhs = rhs.as_big(self).unwrap().clone();
                 match op {
                     Cmp::Gt => {
                         if lhs > rhs {
@@ -508,8 +518,11 @@ impl Component {
             }
             Expr::Fn {op, args} => {
                 let args = args.iter().map(|arg| { let arg = self.get(*arg); self.func(arg.clone()) }).collect_vec();
-                let args = args.into_iter().map(|arg| arg.as_concrete(self).unwrap()).collect_vec();
-                self.add(Expr::Concrete(op.eval(args)))
+                let vals = args.iter().map(|arg| arg.as_big(self).unwrap().clone()).collect_vec();
+                let Some(v) = op.eval(vals) else {
+                    self.internal_error(format!("Cannot evaluate {op} on arguments {}", args.iter().map(|a| self.display(*a)).join(", ")))
+                };
+                self.add(Expr::Concrete(v))
             }
         }
     }
@@ -521,16 +534,15 @@ impl Component {
             Expr::Concrete(_) => self.add(expr),
             Expr::Bin { op, lhs, rhs } => {
                 let lhs = self.bin(self.get(lhs).clone());
-                let lhs = lhs.as_concrete(self).unwrap();
                 let rhs = self.bin(self.get(rhs).clone());
-                let rhs = rhs.as_concrete(self).unwrap();
-                match op {
-                    ast::Op::Add => self.add(Expr::Concrete(lhs + rhs)),
-                    ast::Op::Mul => self.add(Expr::Concrete(lhs * rhs)),
-                    ast::Op::Sub => self.add(Expr::Concrete(lhs - rhs)),
-                    ast::Op::Div => self.add(Expr::Concrete(lhs / rhs)),
-                    ast::Op::Mod => self.add(Expr::Concrete(lhs % rhs)),
+                // Adding the expression folds it when it is defined
+                let e = self.add(Expr::Bin { op, lhs, rhs });
+                if e.as_big(self).is_none() {
+                    self.internal_error(format!(
+                        "Cannot evaluate {}", self.display(e)
+                    ))
                 }
+                e
             }
             Expr::Param(pidx) => {
                 self.internal_error(format!(
@@ -547,46 +559,58 @@ impl AddCtx<Expr> for Component {
         match &val {
             Expr::Param(_) | Expr::Concrete(_) => self.exprs.intern(val),
             Expr::Bin { op, lhs, rhs } => {
-                let l = lhs.as_concrete(self);
-                let r = rhs.as_concrete(self);
-                let e = match (op, l, r) {
+                let l = lhs.as_big(self).cloned();
+                let r = rhs.as_big(self).cloned();
+                let is = |v: Option<&BigInt>, n: u64| {
+                    v.map_or(false, |v| *v == n.into())
+                };
+                let e = match (op, l.as_ref(), r.as_ref()) {
                     // 0+e == e
-                    (ast::Op::Add, Some(0), None) => return *rhs,
+                    (ast::Op::Add, l, None) if is(l, 0) => return *rhs,
                     // e+0 and e-0 == e
-                    (ast::Op::Add, None, Some(0))
-                    | (ast::Op::Sub, None, Some(0)) => return *lhs,
+                    (ast::Op::Add | ast::Op::Sub, None, r) if is(r, 0) => {
+                        return *lhs
+                    }
                     // e*0, 0*e, 0/e == 0
-                    (ast::Op::Mul, Some(0), None)
-                    | (ast::Op::Div, Some(0), None)
-                    | (ast::Op::Mul, None, Some(0)) => Expr::Concrete(0),
+                    (ast::Op::Mul | ast::Op::Div, l, None) if is(l, 0) => {
+                        Expr::Concrete(BigInt::zero())
+                    }
+                    (ast::Op::Mul, None, r) if is(r, 0) => {
+                        Expr::Concrete(BigInt::zero())
+                    }
                     // e*1 and e/1 == e
-                    (ast::Op::Mul, _, Some(1)) | (ast::Op::Div, _, Some(1)) => {
+                    (ast::Op::Mul | ast::Op::Div, _, r) if is(r, 1) => {
                         return *lhs
                     }
                     // 1*e == e
-                    (ast::Op::Mul, Some(1), _) => return *rhs,
+                    (ast::Op::Mul, l, _) if is(l, 1) => return *rhs,
                     (ast::Op::Add, Some(l), None)
                     | (ast::Op::Mul, Some(l), None) => Expr::Bin {
                         op: *op,
                         lhs: *rhs,
-                        rhs: self.exprs.intern(Expr::Concrete(l)),
+                        rhs: self.exprs.intern(Expr::Concrete(l.clone())),
+                    },
+                    // Division by zero is left unevaluated.
+                    (op, Some(l), Some(r)) => match op {
+                        ast::Op::Add => Expr::Concrete(l + r),
+                        ast::Op::Mul => Expr::Concrete(l * r),
+                        ast::Op::Sub => Expr::Concrete(l - r),
+                        ast::Op::Div => l
+                            .checked_div_rem_euclid(r)
+                            .map_or(val, |(q, _)| Expr::Concrete(q)),
+                        ast::Op::Mod => l
+                            .checked_div_rem_euclid(r)
+                            .map_or(val, |(_, r)| Expr::Concrete(r)),
                     },
-                    (op, Some(l), Some(r)) => Expr::Concrete(match op {
-                        ast::Op::Add => l + r,
-                        ast::Op::Sub => l - r,
-                        ast::Op::Mul => l * r,
-                        ast::Op::Div => l / r,
-                        ast::Op::Mod => l % r,
-                    }),
                     _ => val,
                 };
                 self.exprs.intern(e)
             }
             Expr::Fn { op, args } => self.exprs.intern(
                 args.iter()
-                    .map(|arg| arg.as_concrete(self))
+                    .map(|arg| arg.as_big(self).cloned())
                     .collect::<Option<Vec<_>>>()
-                    .map(|args| op.eval(args))
+                    .and_then(|args| op.eval(args))
                     .map_or(val, Expr::Concrete),
             ),
         }
@@ -652,22 +676,23 @@ impl AddCtx<Prop> for Component {
                     self.props.intern(Prop::Implies(l, r))
                 }
             }
-            Prop::Cmp(CmpOp { op, lhs, rhs }) => self.props.intern(
-                match (lhs.as_concrete(self), rhs.as_concrete(self)) {
-                    (Some(l), Some(r)) => {
-                        if match op {
-                            Cmp::Gt => l > r,
-                            Cmp::Gte => l >= r,
-                            Cmp::Eq => l == r,
-                        } {
-                            Prop::True
-                        } else {
-                            Prop::False
+            Prop::Cmp(CmpOp { op, lhs, rhs }) => {
+                self.props
+                    .intern(match (lhs.as_big(self), rhs.as_big(self)) {
+                        (Some(l), Some(r)) => {
+                            if match op {
+                                Cmp::Gt => l > r,
+                                Cmp::Gte => l >= r,
+                                Cmp::Eq => l == r,
+                            } {
+                                Prop::True
+                            } else {
+                                Prop::False
+                            }
                         }
-                    }
-                    _ => Prop::Cmp(CmpOp { op, lhs, rhs }),
-                },
-            ),
+                        _ => Prop::Cmp(CmpOp { op, lhs, rhs }),
+                    })
+            }
             Prop::TimeCmp(CmpOp { op, lhs, rhs }) => {
                 let l = self.get(lhs);
                 let r = self.get(rhs);
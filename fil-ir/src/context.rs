@@ -4,7 +4,7 @@ use super::{
 use fil_derive::Ctx;
 use std::collections::HashMap;
 
-#[derive(Default, Ctx)]
+#[derive(Default, Ctx, Clone)]
 pub struct Context {
     #[ctx(Component: Get, Add, Mut)]
     pub comps: IndexStore<Component>,
@@ -1,12 +1,13 @@
 use super::{AddCtx, Component, Ctx, ExprIdx, ParamIdx};
 use crate::construct_binop;
 use fil_ast as ast;
+use fil_utils::{BigInt, Signed, ToPrimitive};
 use std::fmt::Display;
 
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub enum Expr {
     Param(ParamIdx),
-    Concrete(u64),
+    Concrete(BigInt),
     Bin {
         op: ast::Op,
         lhs: ExprIdx,
@@ -38,11 +39,19 @@ impl Display for Expr {
 
 impl ExprIdx {
     #[inline]
-    /// Attempts to convert this expression into a concrete value.
+    /// Attempts to convert this expression into a concrete value that is not
+    /// negative and fits in 64 bits.
     /// If the coercion should panic on failure, use [Self::concrete] instead.
     pub fn as_concrete(&self, ctx: &impl Ctx<Expr>) -> Option<u64> {
+        self.as_big(ctx).and_then(BigInt::to_u64)
+    }
+
+    #[inline]
+    /// Attempts to convert this expression into an arbitrary-precision
+    /// concrete value.
+    pub fn as_big<'a>(&self, ctx: &'a impl Ctx<Expr>) -> Option<&'a BigInt> {
         if let Expr::Concrete(c) = ctx.get(*self) {
-            Some(*c)
+            Some(c)
         } else {
             None
         }
@@ -60,10 +69,15 @@ impl ExprIdx {
     /// Returns the concrete value represented by this expression or errors out.
     /// If an optional value is desired, use [Self::as_concrete] instead.
     pub fn concrete(self, comp: &Component) -> u64 {
-        let Some(c) = self.as_concrete(comp) else {
-            comp.internal_error(format!("{} is not a concrete number", self))
-        };
-        c
+        match self.as_big(comp) {
+            Some(c) => c.to_u64().unwrap_or_else(|| {
+                comp.internal_error(format!(
+                    "{c} is negative or does not fit in 64 bits"
+                ))
+            }),
+            None => comp
+                .internal_error(format!("{} is not a concrete number", self)),
+        }
     }
 
     /// Returns true if this expression is a constant.
@@ -104,6 +118,19 @@ impl ExprIdx {
 
 /// Queries over [ExprIdx]
 impl ExprIdx {
+    /// Returns true if the expression may be negative, i.e., it mentions a
+    /// signed parameter or a negative constant.
+    pub fn is_signed(&self, ctx: &Component) -> bool {
+        match ctx.get(*self) {
+            Expr::Param(p) => ctx.get(*p).signed,
+            Expr::Concrete(c) => c.is_negative(),
+            Expr::Bin { lhs, rhs, .. } => {
+                lhs.is_signed(ctx) || rhs.is_signed(ctx)
+            }
+            Expr::Fn { args, .. } => args.iter().any(|a| a.is_signed(ctx)),
+        }
+    }
+
     pub fn relevant_vars(&self, ctx: &impl Ctx<Expr>) -> Vec<ParamIdx> {
         let mut params = Vec::new();
         self.relevant_vars_acc(ctx, &mut params);
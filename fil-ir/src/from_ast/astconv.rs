@@ -8,7 +8,7 @@ use crate::{
     ParamIdx, PortIdx, PropIdx, TimeIdx,
 };
 use fil_ast as ast;
-use fil_utils::{Diagnostics, Error, GPosIdx};
+use fil_utils::{Diagnostics, Error, GPosIdx, Signed};
 use itertools::Itertools;
 use std::collections::HashMap;
 use std::{iter, rc::Rc};
@@ -215,7 +215,9 @@ impl<'prog> BuildCtx<'prog> {
                 // invocation definitions.
                 let bind = self.expr(expr.clone())?;
                 let owner = ir::ParamOwner::Let { bind };
-                self.param(name.clone(), owner);
+                let signed = bind.is_signed(self.comp());
+                let idx = self.param(name.clone(), owner);
+                self.comp().get_mut(idx).signed = signed;
                 Ok(())
             }
             ast::Command::Exists(_) => {
@@ -257,7 +259,7 @@ impl<'prog> BuildCtx<'prog> {
                 )?
             }
             ast::Expr::Concrete(n) => {
-                let e = ir::Expr::Concrete(n);
+                let e = ir::Expr::Concrete(n.into());
                 self.comp().add(e)
             }
             ast::Expr::Op { op, left, right } => {
@@ -604,10 +606,52 @@ impl<'prog> BuildCtx<'prog> {
         self.comp().param_args = sig
             .params
             .iter()
-            .map(|pb| self.param(pb.param.clone(), ir::ParamOwner::Sig))
+            .map(|pb| {
+                let idx = self.param(pb.param.clone(), ir::ParamOwner::Sig);
+                self.comp().get_mut(idx).signed = pb.signed;
+                idx
+            })
             .collect_vec()
             .into_boxed_slice();
 
+        // Ranges may mention other parameters so they are added after all the
+        // parameters are defined.
+        for (idx, pb) in self.comp().param_args.clone().iter().zip(&sig.params)
+        {
+            for range in &pb.ranges {
+                let range = match range.inner() {
+                    ast::ParamRange::Bounded { lo, hi } => {
+                        let lo = self.expr(lo.clone())?;
+                        // Natural parameters cannot be negative
+                        let neg = lo
+                            .as_big(self.comp())
+                            .filter(|v| v.is_negative() && !pb.signed);
+                        if let Some(v) = neg {
+                            let msg = format!(
+                                "range of natural parameter `{}' starts at {v}",
+                                pb.name()
+                            );
+                            let info = self.diag().add_info(
+                                "lower bound is negative",
+                                range.pos(),
+                            );
+                            let help = self.diag().add_message(
+                                "declare the parameter as `int' to allow negative values",
+                            );
+                            return self
+                                .fail(Error::malformed(msg), [info, help]);
+                        }
+                        ir::ParamRange::Bounded {
+                            lo,
+                            hi: self.expr(hi.clone())?,
+                        }
+                    }
+                    ast::ParamRange::Pow2 => ir::ParamRange::Pow2,
+                };
+                self.comp().get_mut(*idx).ranges.push(range);
+            }
+        }
+
         // Binding from let-defined parameters in the signature to their values
         conv_sig.sig_binding = sig
             .sig_bindings
@@ -760,7 +804,12 @@ impl<'prog> BuildCtx<'prog> {
             abstract_vars,
             ports,
             instance,
+            holes,
         } = inv;
+        assert!(
+            holes.is_empty(),
+            "schedule for invocation `{name}' must be inferred before conversion"
+        );
         let inv = self.get_inv(&name)?;
         let inst = inv.inst(self.comp());
         let (param_binding, comp) = self.inst_to_sig.get(inst).clone();
@@ -1108,6 +1157,7 @@ fn try_transform(ns: ast::Namespace) -> BuildRes<ir::Context> {
         .map(|(idx, (typ, ext_info, sig, body))| {
             let idx = ir::CompIdx::new(idx);
             let mut builder = BuildCtx::new(ir::Component::new(typ), &sig_map);
+            builder.comp().def_name = sig.name.copy();
 
             // enable source information saving if this is main
             if Some(idx) == ctx.entrypoint {
@@ -365,12 +365,17 @@ pub enum Reason {
         src_loc: GPosIdx,
         dst_liveness: Range,
         src_liveness: Range,
+        /// Names of the destination and source ports
+        dst_name: String,
+        src_name: String,
     },
     /// Bundle's delay must be less than the event's
     BundleDelay {
         event_delay_loc: GPosIdx,
         bundle_range_loc: GPosIdx,
         bundle_live: TimeSub,
+        /// Delay of the event the bundle's availability starts at
+        event_delay: TimeSub,
         param_info: Vec<(
             /*bind_loc=*/ GPosIdx,
             /*start=*/ ExprIdx,
@@ -440,6 +445,7 @@ impl Reason {
         event_delay_loc: GPosIdx,
         bundle_range_loc: GPosIdx,
         bundle_live: TimeSub,
+        event_delay: TimeSub,
         param_info: Vec<(
             /*bind_loc=*/ GPosIdx,
             /*start*/ ExprIdx,
@@ -450,6 +456,7 @@ impl Reason {
             event_delay_loc,
             bundle_range_loc,
             bundle_live,
+            event_delay,
             param_info,
         }
     }
@@ -459,12 +466,15 @@ impl Reason {
         src_loc: GPosIdx,
         dst_liveness: Range,
         src_liveness: Range,
+        (dst_name, src_name): (String, String),
     ) -> Self {
         Self::Liveness {
             dst_loc,
             src_loc,
             dst_liveness,
             src_liveness,
+            dst_name,
+            src_name,
         }
     }
 
@@ -740,6 +750,7 @@ impl Reason {
                 src_loc,
                 dst_liveness,
                 src_liveness,
+                ..
             } => {
                 let sl = src_loc.primary().with_message(format!(
                     "source is available for {}",
@@ -758,6 +769,7 @@ impl Reason {
                 bundle_range_loc,
                 bundle_live,
                 param_info,
+                ..
             } => {
                 let wire = bundle_range_loc.primary().with_message(format!(
                     "available for {} cycles",
@@ -30,10 +30,10 @@ pub use idxs::{
 };
 pub use info::Info;
 pub use printer::{DisplayCtx, Printer};
-pub use source_info::InterfaceSrc;
+pub use source_info::{InterfaceSrc, MonoSrc, RangeSrc};
 pub use structure::{
-    Access, Direction, Event, Liveness, Param, ParamOwner, Port, PortOwner,
-    Range,
+    Access, Direction, Event, Liveness, Param, ParamOwner, ParamRange, Port,
+    PortOwner, Range,
 };
 pub use time::{Time, TimeSub};
 pub use utils::{
@@ -161,7 +161,25 @@ impl<'a, 'b> Printer<'a, 'b> {
             .comp
             .param_args()
             .iter()
-            .map(|idx| self.comp.display(*idx))
+            .map(|idx| {
+                let param = self.comp.get(*idx);
+                let name = self.comp.display(*idx);
+                let int = param.signed.then(|| "int".to_string());
+                let ranges = param.ranges.iter().map(|r| match r {
+                    ir::ParamRange::Bounded { lo, hi } => format!(
+                        "{}..={}",
+                        self.comp.display(*lo),
+                        self.comp.display(*hi)
+                    ),
+                    ir::ParamRange::Pow2 => "pow2".to_string(),
+                });
+                let refines = int.into_iter().chain(ranges).join(" & ");
+                if refines.is_empty() {
+                    name
+                } else {
+                    format!("{name}: {refines}")
+                }
+            })
             .join(", ");
 
         let events = self
@@ -2,6 +2,7 @@ use crate::ParamIdx;
 
 use super::{utils::SparseInfoMap, Event, Param, Port};
 use fil_ast as ast;
+use fil_utils::BigInt;
 
 #[derive(Clone)]
 /// Externally facing interface name information for components.
@@ -49,3 +50,28 @@ impl InterfaceSrc {
         )
     }
 }
+
+#[derive(Clone, Default)]
+/// Source-level bindings of a component generated by monomorphization.
+pub struct MonoSrc {
+    /// Values of the parameters in the signature
+    pub params: Vec<(ast::Id, BigInt)>,
+    /// Values computed for the existentially quantified parameters
+    pub exists: Vec<(ast::Id, BigInt)>,
+    /// Declared ranges of the parameters in the signature, in the same order
+    /// as `params`
+    pub ranges: Vec<RangeSrc>,
+    /// Name of the generated component when it does not have a source-level
+    /// name
+    pub name: Option<ast::Id>,
+}
+
+#[derive(Clone, Default)]
+/// Declared range of a signature parameter. The bounds are evaluated using
+/// the values of the other parameters and are `None` if the parameter has no
+/// bounded range or if they cannot be evaluated.
+pub struct RangeSrc {
+    pub min: Option<BigInt>,
+    pub max: Option<BigInt>,
+    pub pow2: bool,
+}
@@ -211,8 +211,8 @@ impl Access {
     /// Construct an access on a simple port (i.e. not a bundle)
     /// The access only indexes into the first element of the port.
     pub fn port(port: PortIdx, ctx: &mut impl AddCtx<Expr>) -> Self {
-        let zero = ctx.add(Expr::Concrete(0));
-        let one = ctx.add(Expr::Concrete(1));
+        let zero = ctx.add(Expr::Concrete(0u64.into()));
+        let one = ctx.add(Expr::Concrete(1u64.into()));
         Self {
             port,
             ranges: vec![(zero, one)],
@@ -220,7 +220,7 @@ impl Access {
     }
 
     fn unit_range(start: ExprIdx, end: ExprIdx, ctx: &Component) -> bool {
-        let Some(one) = ctx.exprs().find(&Expr::Concrete(1)) else {
+        let Some(one) = ctx.exprs().find(&Expr::Concrete(1u64.into())) else {
             ctx.internal_error("Constant 1 not found in component")
         };
         match ctx.get(end) {
@@ -236,8 +236,8 @@ impl Access {
                 }
             }
             Expr::Concrete(e) => {
-                if let Some(s) = start.as_concrete(ctx) {
-                    if *e != s + 1 {
+                if let Some(s) = start.as_big(ctx) {
+                    if *e != s + 1u64 {
                         return false;
                     }
                 } else {
@@ -349,16 +349,36 @@ impl ParamOwner {
     }
 }
 
+#[derive(PartialEq, Eq, Hash, Clone)]
+/// The range of values a signature parameter may take. The signature also
+/// contains the constraints implied by the range.
+pub enum ParamRange {
+    /// The parameter is within `lo..=hi`
+    Bounded { lo: ExprIdx, hi: ExprIdx },
+    /// The parameter is a power of two
+    Pow2,
+}
+
 #[derive(PartialEq, Eq, Hash, Clone)]
 /// Parameters with an optional initial value
 pub struct Param {
     pub owner: ParamOwner,
     pub info: InfoIdx,
+    /// The parameter is an integer that may be negative. Parameters are
+    /// natural numbers by default.
+    pub signed: bool,
+    /// The declared ranges of a signature parameter
+    pub ranges: Vec<ParamRange>,
 }
 
 impl Param {
     pub fn new(owner: ParamOwner, info: InfoIdx) -> Self {
-        Self { owner, info }
+        Self {
+            owner,
+            info,
+            signed: false,
+            ranges: vec![],
+        }
     }
 
     pub fn is_sig_owned(&self) -> bool {
@@ -0,0 +1,113 @@
+//! Arbitrary-precision integers used to represent parameter values. The
+//! arithmetic is provided by [num_bigint]; this module only defines the
+//! operations of the constant evaluator that it does not provide.
+use num_traits::CheckedEuclid;
+
+pub use num_bigint::{BigInt, BigUint};
+pub use num_integer::Integer;
+pub use num_traits::{Num, One, Signed, ToPrimitive, Zero};
+
+/// Operations on unsigned values used by the functions of the language
+pub trait BigUintExt {
+    /// Computes `2^n`
+    fn pow2(n: u64) -> Self;
+
+    /// Computes `ceil(log2(self))`. The logarithm of zero is defined to be
+    /// zero.
+    fn log2_ceil(&self) -> u64;
+
+    /// Reverse the lowest `width` bits of this number
+    fn bit_rev(&self, width: u64) -> Self;
+}
+
+impl BigUintExt for BigUint {
+    fn pow2(n: u64) -> Self {
+        BigUint::one() << n
+    }
+
+    fn log2_ceil(&self) -> u64 {
+        if self.is_zero() {
+            0
+        } else {
+            (self - 1u32).bits()
+        }
+    }
+
+    fn bit_rev(&self, width: u64) -> Self {
+        let mut out = BigUint::zero();
+        // Bits above the most significant one are zero and do not need to be
+        // visited.
+        for i in 0..width.min(self.bits()) {
+            if self.bit(i) {
+                out.set_bit(width - 1 - i, true);
+            }
+        }
+        out
+    }
+}
+
+/// Operations on signed values used by the constant evaluator
+pub trait BigIntExt: Sized {
+    /// Computes the quotient and the remainder of the Euclidean division
+    /// `self / other`, i.e., the remainder is never negative. This matches
+    /// the semantics of `div` and `mod` over SMT integers.
+    /// Returns `None` if `other` is zero.
+    fn checked_div_rem_euclid(&self, other: &Self) -> Option<(Self, Self)>;
+}
+
+impl BigIntExt for BigInt {
+    fn checked_div_rem_euclid(&self, other: &Self) -> Option<(Self, Self)> {
+        Some((
+            self.checked_div_euclid(other)?,
+            self.checked_rem_euclid(other)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BigInt, BigIntExt, BigUint, BigUintExt, Zero};
+
+    fn int(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    fn uint(s: &str) -> BigUint {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn div_rem_euclid() {
+        let div_rem = |l: &str, r: &str| int(l).checked_div_rem_euclid(&int(r));
+        // The remainder is never negative
+        assert_eq!(div_rem("7", "2"), Some((int("3"), int("1"))));
+        assert_eq!(div_rem("-7", "2"), Some((int("-4"), int("1"))));
+        assert_eq!(div_rem("7", "-2"), Some((int("-3"), int("1"))));
+        assert_eq!(div_rem("-7", "-2"), Some((int("4"), int("1"))));
+        assert_eq!(div_rem("1", "0"), None);
+    }
+
+    #[test]
+    fn pow2() {
+        assert_eq!(BigUint::pow2(0), uint("1"));
+        assert_eq!(
+            BigUint::pow2(128),
+            uint("340282366920938463463374607431768211456")
+        );
+        assert_eq!(BigUint::zero().log2_ceil(), 0);
+        assert_eq!(uint("1").log2_ceil(), 0);
+        assert_eq!(BigUint::pow2(128).log2_ceil(), 128);
+        assert_eq!((BigUint::pow2(128) + 1u32).log2_ceil(), 129);
+    }
+
+    #[test]
+    fn bit_rev() {
+        assert_eq!(uint("1").bit_rev(4), uint("8"));
+        assert_eq!(uint("6").bit_rev(4), uint("6"));
+        // Bits above the width are dropped
+        assert_eq!(uint("17").bit_rev(4), uint("8"));
+        assert_eq!(uint("1").bit_rev(65), BigUint::pow2(64));
+        // Large widths only visit the bits that are set
+        assert_eq!(BigUint::zero().bit_rev(u64::MAX), BigUint::zero());
+    }
+}
@@ -1,3 +1,4 @@
+mod bigint;
 mod errors;
 mod global_sym;
 mod gsym;
@@ -6,9 +7,13 @@ mod math;
 mod position;
 mod reporter;
 
+pub use bigint::{
+    BigInt, BigIntExt, BigUint, BigUintExt, Integer, Num, One, Signed,
+    ToPrimitive, Zero,
+};
 pub use errors::{Error, FilamentResult};
 pub use gsym::GSym;
 pub use id::Id;
 pub use math::{all_indices, flat_idx, nd_idx};
 pub use position::{FileIdx, GPosIdx, GlobalPositionTable, PosData};
-pub use reporter::{Diagnostics, InfoIdx};
+pub use reporter::{report_notes, Diagnostics, InfoIdx, TimingDiagram};
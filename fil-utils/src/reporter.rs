@@ -4,7 +4,7 @@ use codespan_reporting::{
     diagnostic::{Diagnostic, Label, LabelStyle},
     term::{self, termcolor::StandardStream},
 };
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, fmt};
 
 #[derive(PartialOrd, Ord, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 /// Index for information associated with a [Diagnostic] instance.
@@ -33,6 +33,9 @@ pub struct Diagnostics {
     infos: Vec<Information>,
     /// Errors that have been reported.
     errors: Vec<Error>,
+    /// Errors that have already been rendered into diagnostics, such as the
+    /// ones generated from the reasons of failing obligations.
+    diagnostics: Vec<Diagnostic<usize>>,
 }
 
 impl Diagnostics {
@@ -79,6 +82,11 @@ impl Diagnostics {
         }
     }
 
+    /// Add an error that has already been rendered into a diagnostic
+    pub fn add_diagnostic(&mut self, diag: Diagnostic<usize>) {
+        self.diagnostics.push(diag);
+    }
+
     /// Report all errors and return the number of errors.
     /// Returns None if there are no errors.
     pub fn report_all(&mut self) -> Option<u64> {
@@ -88,7 +96,7 @@ impl Diagnostics {
         } else {
             ColorChoice::Never
         });
-        if self.errors.is_empty() {
+        if self.errors.is_empty() && self.diagnostics.is_empty() {
             return None;
         }
 
@@ -153,6 +161,17 @@ impl Diagnostics {
             .unwrap();
         }
 
+        for diag in self.diagnostics.drain(..) {
+            total += 1;
+            term::emit(
+                &mut writer.lock(),
+                &term::Config::default(),
+                table.files(),
+                &diag,
+            )
+            .unwrap();
+        }
+
         Some(total)
     }
 }
@@ -166,3 +185,98 @@ impl Drop for Diagnostics {
         }
     }
 }
+
+/// Report diagnostics that do not correspond to errors, such as notes
+/// describing transformations performed by the compiler.
+pub fn report_notes(notes: &[Diagnostic<usize>]) {
+    let is_tty = atty::is(atty::Stream::Stderr);
+    let writer = StandardStream::stderr(if is_tty {
+        ColorChoice::Always
+    } else {
+        ColorChoice::Never
+    });
+    let table = GlobalPositionTable::as_ref();
+    for note in notes {
+        term::emit(
+            &mut writer.lock(),
+            &term::Config::default(),
+            table.files(),
+            note,
+        )
+        .unwrap();
+    }
+}
+
+/// Maximum number of cycles rendered by a [TimingDiagram].
+const MAX_CYCLES: i64 = 32;
+
+/// An ASCII waveform comparing the cycles during which a value is required with
+/// the cycles during which it is provided. Cycles are relative to an event and
+/// intervals are half-open.
+pub struct TimingDiagram {
+    /// Name of the event the cycles are relative to
+    event: String,
+    required: (i64, i64),
+    provided: (i64, i64),
+}
+
+impl TimingDiagram {
+    /// Construct a diagram for the `required` and `provided` intervals.
+    /// Returns `None` if the diagram would be too wide to be useful.
+    pub fn new<S: ToString>(
+        event: S,
+        required: (i64, i64),
+        provided: (i64, i64),
+    ) -> Option<Self> {
+        let lo = required.0.min(provided.0);
+        let hi = required.1.max(provided.1);
+        if hi <= lo || hi - lo > MAX_CYCLES {
+            return None;
+        }
+        Some(Self {
+            event: event.to_string(),
+            required,
+            provided,
+        })
+    }
+}
+
+impl fmt::Display for TimingDiagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (req, prov) = (self.required, self.provided);
+        let cycles = req.0.min(prov.0)..req.1.max(prov.1);
+        let in_range = |(s, e): (i64, i64), c: i64| s <= c && c < e;
+        let width = cycles
+            .clone()
+            .map(|c| c.to_string().len())
+            .max()
+            .unwrap_or(1)
+            + 1;
+
+        writeln!(f, "timing relative to {}:", self.event)?;
+        write!(f, "cycle    |")?;
+        for c in cycles.clone() {
+            write!(f, "{c:>width$}")?;
+        }
+        writeln!(f)?;
+        for (name, (s, e)) in [("required", req), ("provided", prov)] {
+            write!(f, "{name:<9}|")?;
+            for c in cycles.clone() {
+                let mark = if in_range((s, e), c) { "#" } else { "." };
+                write!(f, "{mark:>width$}")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "gap      |")?;
+        let gap = cycles
+            .map(|c| {
+                if in_range(req, c) && !in_range(prov, c) {
+                    format!("{:>width$}", "^")
+                } else {
+                    " ".repeat(width)
+                }
+            })
+            .collect::<String>();
+        write!(f, "{}", gap.trim_end())
+    }
+}
@@ -20,6 +20,27 @@ def representable(n, width):
     return n >= 0 and n < (1 << width)
 
 
+def load_interface(f):
+    """
+    Load the interface of the toplevel component. Accepts both the versioned
+    format, which describes every component, and the older format, which only
+    describes the toplevel component.
+    The declared ranges of parameters are optional in both formats. Every
+    parameter gets `min`, `max` and `pow2` keys, which are `None`, `None` and
+    `False` when the parameter does not declare them.
+    """
+    interface = json.load(f)
+    if "components" in interface:
+        interface = next(
+            comp for comp in interface["components"] if comp["entrypoint"]
+        )
+    for param in interface.get("params", []):
+        param.setdefault("min", None)
+        param.setdefault("max", None)
+        param.setdefault("pow2", False)
+    return interface
+
+
 def validate_data(data):
     """
     Validate the data format provided to the file.
@@ -234,7 +255,7 @@ async def run_design(mod):
     max_cycles = int(os.environ.get("MAX_CYCLES") or MAX_CYCLES)
 
     with open(interface_file) as f:
-        interface = json.load(f)
+        interface = load_interface(f)
 
     with open(data_file) as f:
         data = json.load(f)
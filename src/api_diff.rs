@@ -0,0 +1,780 @@
+use crate::cmdline;
+use crate::ir_passes::Discharge;
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use easy_smt as smt;
+use fil_ast as ast;
+use fil_utils::{GPosIdx, Id, Zero};
+use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap};
+use std::iter;
+
+/// Names of one version of a signature along with the solver variables they
+/// are encoded as. Parameters and events are matched by position since users
+/// bind them positionally while bindings in the `with` section and bundle
+/// indices are matched by name.
+#[derive(Default, Clone)]
+struct Names(HashMap<Id, String>);
+
+impl Names {
+    fn new(sig: &ast::Signature) -> Self {
+        let mut names = HashMap::new();
+        for (i, p) in sig.params().enumerate() {
+            names.insert(p.take(), format!("param{i}"));
+        }
+        for (i, e) in sig.events().enumerate() {
+            names.insert(e.take(), format!("event{i}"));
+        }
+        for sb in &sig.sig_bindings {
+            let (ast::SigBind::Let { param, .. }
+            | ast::SigBind::Exists { param, .. }) = sb.inner();
+            names.insert(param.copy(), format!("with_{param}"));
+        }
+        Self(names)
+    }
+
+    /// Add the index variables of a bundle. Indices are named after the
+    /// bundle and their dimension.
+    fn with_bundle(&self, b: &ast::Bundle) -> Self {
+        let mut names = self.0.clone();
+        for (i, idx) in b.typ.idx.iter().enumerate() {
+            names.insert(idx.copy(), format!("{}_idx{i}", b.name.inner()));
+        }
+        Self(names)
+    }
+}
+
+/// Write a time the way it is written in the source
+fn time(t: &ast::Time) -> String {
+    if matches!(&t.offset, ast::Expr::Concrete(n) if n.is_zero()) {
+        format!("'{}", t.event)
+    } else {
+        format!("'{}+{}", t.event, t.offset)
+    }
+}
+
+fn range(r: &ast::Range) -> String {
+    format!("[{}, {}]", time(&r.start), time(&r.end))
+}
+
+/// Check whether the components in a new version of a library are safe
+/// replacements for the components in the old version.
+///
+/// A new signature is a safe replacement if every program that type checks
+/// against the old signature also type checks against the new one. Each
+/// requirement on users must be implied by the old signature and each
+/// guarantee to users must imply the old guarantee, assuming the constraints
+/// of the old signature. Requirements that are syntactically equal after
+/// matching up names are accepted without the solver.
+pub struct ApiDiff {
+    /// Only connected to a solver if some query could not be decided
+    /// syntactically in a first run without one.
+    sol: smt::Context,
+    sol_started: bool,
+    /// Set when a query cannot be decided without the solver
+    needs_solver: bool,
+    /// Set when the solver could not decide the last query
+    undecided: bool,
+    /// Variables mentioned by the current component
+    vars: BTreeSet<String>,
+    /// Breaking changes
+    diagnostics: Vec<Diagnostic<usize>>,
+}
+
+/// Encoding of signatures
+impl ApiDiff {
+    fn expr(&mut self, e: &ast::Expr, names: &Names) -> smt::SExpr {
+        match e {
+            ast::Expr::Concrete(n) => self.sol.atom(n.to_string()),
+            ast::Expr::Abstract(x) => self.var(x.inner(), names),
+            ast::Expr::ParamAccess { inst, param } => {
+                let name = format!("{}_{}", inst.inner(), param.inner());
+                self.vars.insert(name.clone());
+                self.sol.atom(name)
+            }
+            ast::Expr::App { func, args } => {
+                let f = self.sol.atom(func.to_string());
+                let args =
+                    args.iter().map(|a| self.expr(a, names)).collect_vec();
+                self.sol.list(iter::once(f).chain(args).collect())
+            }
+            ast::Expr::Op { op, left, right } => {
+                let l = self.expr(left, names);
+                let r = self.expr(right, names);
+                match op {
+                    ast::Op::Add => self.sol.plus(l, r),
+                    ast::Op::Sub => self.sol.sub(l, r),
+                    ast::Op::Mul => self.sol.times(l, r),
+                    ast::Op::Div => self.sol.div(l, r),
+                    ast::Op::Mod => self.sol.modulo(l, r),
+                }
+            }
+        }
+    }
+
+    fn var(&mut self, x: &Id, names: &Names) -> smt::SExpr {
+        // Names that are not bound by the signature are kept apart from the
+        // ones that are.
+        let name = names
+            .0
+            .get(x)
+            .cloned()
+            .unwrap_or_else(|| format!("free_{x}"));
+        self.vars.insert(name.clone());
+        self.sol.atom(name)
+    }
+
+    fn time(&mut self, t: &ast::Time, names: &Names) -> smt::SExpr {
+        let ev = self.var(&t.event, names);
+        if matches!(&t.offset, ast::Expr::Concrete(n) if n.is_zero()) {
+            ev
+        } else {
+            let offset = self.expr(&t.offset, names);
+            self.sol.plus(ev, offset)
+        }
+    }
+
+    fn time_sub(&mut self, ts: &ast::TimeSub, names: &Names) -> smt::SExpr {
+        match ts {
+            ast::TimeSub::Unit(e) => self.expr(e, names),
+            ast::TimeSub::Sym { l, r } => {
+                let l = self.time(l, names);
+                let r = self.time(r, names);
+                self.sol.sub(l, r)
+            }
+        }
+    }
+
+    /// Encode a comparison. Comparisons between syntactically equal terms
+    /// are simplified away.
+    fn cmp(
+        &self,
+        op: &ast::OrderOp,
+        l: smt::SExpr,
+        r: smt::SExpr,
+    ) -> smt::SExpr {
+        match op {
+            ast::OrderOp::Gte | ast::OrderOp::Eq if l == r => self.sol.true_(),
+            ast::OrderOp::Gt => self.sol.gt(l, r),
+            ast::OrderOp::Gte => self.sol.gte(l, r),
+            ast::OrderOp::Eq => self.sol.eq(l, r),
+        }
+    }
+
+    fn expr_cons(
+        &mut self,
+        c: &ast::OrderConstraint<ast::Expr>,
+        names: &Names,
+    ) -> smt::SExpr {
+        let l = self.expr(&c.left, names);
+        let r = self.expr(&c.right, names);
+        self.cmp(&c.op, l, r)
+    }
+
+    fn time_cons(
+        &mut self,
+        c: &ast::OrderConstraint<ast::Time>,
+        names: &Names,
+    ) -> smt::SExpr {
+        let l = self.time(&c.left, names);
+        let r = self.time(&c.right, names);
+        self.cmp(&c.op, l, r)
+    }
+
+    /// Facts that users of the old signature can rely on
+    fn assumptions(&mut self, old: &ast::Signature) -> Vec<smt::SExpr> {
+        let names = Names::new(old);
+        let mut facts = vec![];
+        let zero = self.sol.numeral(0);
+        for pb in &old.params {
+            if !pb.signed {
+                let p = self.var(pb.param.inner(), &names);
+                facts.push(self.sol.gte(p, zero));
+            }
+        }
+        for e in old.events() {
+            let e = self.var(e.inner(), &names);
+            facts.push(self.sol.gte(e, zero));
+        }
+        for c in &old.param_constraints {
+            facts.push(self.expr_cons(c, &names));
+        }
+        for c in &old.event_constraints {
+            facts.push(self.time_cons(c, &names));
+        }
+        for sb in &old.sig_bindings {
+            match sb.inner() {
+                ast::SigBind::Let { param, bind } => {
+                    let p = self.var(param.inner(), &names);
+                    let b = self.expr(bind, &names);
+                    facts.push(self.sol.eq(p, b));
+                }
+                ast::SigBind::Exists { cons, .. } => {
+                    for c in cons {
+                        facts.push(self.expr_cons(c, &names));
+                    }
+                }
+            }
+        }
+        facts
+    }
+}
+
+/// Deciding queries
+impl ApiDiff {
+    fn new(solver: Option<cmdline::Solver>) -> Self {
+        let mut sol = match solver {
+            Some(s) => Discharge::conf_solver(s, None),
+            None => smt::ContextBuilder::new().build().unwrap(),
+        };
+        if solver.is_some() {
+            let int = sol.int_sort();
+            for f in ["pow2", "log2"] {
+                sol.declare_fun(f, vec![int], int).unwrap();
+            }
+            for f in ["sin_bits", "cos_bits", "bit_rev"] {
+                sol.declare_fun(f, vec![int, int], int).unwrap();
+            }
+        }
+        Self {
+            sol,
+            sol_started: solver.is_some(),
+            needs_solver: false,
+            undecided: false,
+            vars: BTreeSet::new(),
+            diagnostics: vec![],
+        }
+    }
+
+    /// Is the goal valid under the assumptions? Without a solver, queries
+    /// that cannot be decided syntactically are assumed to be valid and the
+    /// diff has to be rerun with the solver. Queries the solver cannot
+    /// decide are treated as invalid and the breaking change that is
+    /// reported next says so.
+    fn valid(&mut self, assumes: &[smt::SExpr], goal: smt::SExpr) -> bool {
+        self.undecided = false;
+        if goal == self.sol.true_() || assumes.contains(&goal) {
+            return true;
+        }
+        if !self.sol_started {
+            self.needs_solver = true;
+            return true;
+        }
+        self.sol.push().unwrap();
+        let int = self.sol.int_sort();
+        for v in &self.vars {
+            self.sol.declare_const(v, int).unwrap();
+        }
+        for a in assumes {
+            self.sol.assert(*a).unwrap();
+        }
+        self.sol.assert(self.sol.not(goal)).unwrap();
+        let res = self.sol.check().unwrap();
+        self.sol.pop().unwrap();
+        match res {
+            smt::Response::Unsat => true,
+            smt::Response::Sat => false,
+            smt::Response::Unknown => {
+                self.undecided = true;
+                false
+            }
+        }
+    }
+}
+
+/// Comparing signatures
+impl ApiDiff {
+    fn breaking(&mut self, msg: String, new: GPosIdx, old: GPosIdx) {
+        let (msg, notes) = if std::mem::take(&mut self.undecided) {
+            (
+                "could not decide whether the new version is compatible"
+                    .to_string(),
+                vec![format!(
+                    "the solver returned unknown when checking: {msg}"
+                )],
+            )
+        } else {
+            (msg, vec![])
+        };
+        let labels = new
+            .into_option()
+            .map(|p| p.primary().with_message("in the new version"))
+            .into_iter()
+            .chain(
+                old.into_option()
+                    .map(|p| p.secondary().with_message("in the old version")),
+            )
+            .collect_vec();
+        self.diagnostics.push(
+            Diagnostic::error()
+                .with_message(msg)
+                .with_labels(labels)
+                .with_notes(notes),
+        );
+    }
+
+    fn removed(&mut self, msg: String, old: GPosIdx) {
+        let labels: Vec<Label<usize>> = old
+            .into_option()
+            .map(|p| p.primary().with_message("in the old version"))
+            .into_iter()
+            .collect();
+        self.diagnostics
+            .push(Diagnostic::error().with_message(msg).with_labels(labels));
+    }
+
+    /// Compare two versions of a component
+    fn signature(&mut self, old: &ast::Signature, new: &ast::Signature) {
+        let name = old.name.inner();
+        let (old_pos, new_pos) = (old.name.pos(), new.name.pos());
+
+        // Users bind parameters and events positionally so a different number
+        // of them breaks every instantiation.
+        let (on, nn) = (old.params.len(), new.params.len());
+        if on != nn {
+            let msg = format!(
+                "component `{name}' takes {nn} parameter(s) instead of {on}"
+            );
+            return self.breaking(msg, new_pos, old_pos);
+        }
+        let (on, nn) = (old.events.len(), new.events.len());
+        if on != nn {
+            let msg = format!(
+                "component `{name}' takes {nn} event(s) instead of {on}"
+            );
+            return self.breaking(msg, new_pos, old_pos);
+        }
+
+        self.vars.clear();
+        let assumes = self.assumptions(old);
+        let (on, nn) = (Names::new(old), Names::new(new));
+
+        for (op, np) in old.params.iter().zip(&new.params) {
+            let pos = (np.pos(), op.pos());
+            if op.signed && !np.signed {
+                let msg = format!(
+                    "parameter `{}' of `{name}' may no longer be negative",
+                    np.param
+                );
+                self.breaking(msg, pos.0, pos.1);
+            }
+            match (&op.default, &np.default) {
+                (Some(_), None) => {
+                    let msg = format!(
+                        "parameter `{}' of `{name}' no longer has a default value",
+                        np.param
+                    );
+                    self.breaking(msg, pos.0, pos.1);
+                }
+                (Some(od), Some(nd)) => {
+                    let (o, n) = (self.expr(od, &on), self.expr(nd, &nn));
+                    let eq = self.cmp(&ast::OrderOp::Eq, o, n);
+                    if !self.valid(&assumes, eq) {
+                        let msg = format!(
+                            "default value of parameter `{}' of `{name}' changed from `{od}' to `{nd}'",
+                            np.param
+                        );
+                        self.breaking(msg, pos.0, pos.1);
+                    }
+                }
+                _ => (),
+            }
+            // Users must pick values within the new ranges
+            let param = ast::Expr::abs(np.param.clone());
+            for r in &np.ranges {
+                let implied = r.constraints(param.clone()).iter().all(|c| {
+                    let goal = self.expr_cons(c, &nn);
+                    self.valid(&assumes, goal)
+                });
+                if !implied {
+                    let msg = format!(
+                        "range `{}' of parameter `{}' of `{name}' is not implied by the old signature",
+                        r.inner(),
+                        np.param
+                    );
+                    self.breaking(msg, r.pos(), pos.1);
+                }
+            }
+        }
+
+        // Users must satisfy the constraints of the new signature. The
+        // constraints generated from ranges have already been checked.
+        let ranges = new
+            .params
+            .iter()
+            .flat_map(|pb| pb.ranges.iter().map(|r| r.pos()))
+            .collect_vec();
+        for c in &new.param_constraints {
+            if ranges.contains(&c.pos()) {
+                continue;
+            }
+            let goal = self.expr_cons(c, &nn);
+            if !self.valid(&assumes, goal) {
+                let msg = format!(
+                    "constraint `{}' of `{name}' is not implied by the old constraints",
+                    c.inner()
+                );
+                self.breaking(msg, c.pos(), old_pos);
+            }
+        }
+        for c in &new.event_constraints {
+            let goal = self.time_cons(c, &nn);
+            if !self.valid(&assumes, goal) {
+                let msg = format!(
+                    "constraint `{} {} {}' of `{name}' is not implied by the old constraints",
+                    time(&c.left),
+                    c.op,
+                    time(&c.right)
+                );
+                self.breaking(msg, c.pos(), old_pos);
+            }
+        }
+
+        // Users can only reuse an instance as often as the old delays allow
+        for (oe, ne) in old.events.iter().zip(&new.events) {
+            let o = self.time_sub(&oe.delay, &on);
+            let n = self.time_sub(&ne.delay, &nn);
+            let goal = self.cmp(&ast::OrderOp::Gte, o, n);
+            if !self.valid(&assumes, goal) {
+                let msg = format!(
+                    "delay of event '{} of `{name}' may be larger than before",
+                    ne.event
+                );
+                self.breaking(msg, ne.delay.pos(), oe.delay.pos());
+            }
+        }
+
+        self.interface(old, new);
+        self.ports(old, new, &assumes, (&on, &nn));
+        self.bindings(old, new, &assumes, (&on, &nn));
+    }
+
+    /// Users drive the interface ports and unannotated ports of the component
+    fn interface(&mut self, old: &ast::Signature, new: &ast::Signature) {
+        let name = old.name.inner();
+        let ev_idx = |sig: &ast::Signature, ev: &Id| {
+            sig.events().position(|e| e.inner() == ev)
+        };
+        let old_intf = old
+            .interface_signals
+            .iter()
+            .map(|i| (ev_idx(old, &i.event), i))
+            .collect_vec();
+        for ni in &new.interface_signals {
+            let idx = ev_idx(new, &ni.event);
+            if !old_intf.iter().any(|(i, _)| *i == idx) {
+                let msg = format!(
+                    "event '{} of `{name}' has a new interface port `{}'",
+                    ni.event, ni.name
+                );
+                self.breaking(msg, ni.name.pos(), old.name.pos());
+            }
+        }
+        for (idx, oi) in old_intf {
+            let Some(ni) = new
+                .interface_signals
+                .iter()
+                .find(|ni| ev_idx(new, &ni.event) == idx)
+            else {
+                let msg = format!(
+                    "interface port `{}' of `{name}' was removed",
+                    oi.name
+                );
+                self.removed(msg, oi.name.pos());
+                continue;
+            };
+            if ni.name.inner() != oi.name.inner() {
+                let msg = format!(
+                    "interface port `{}' of `{name}' was renamed to `{}'",
+                    oi.name, ni.name
+                );
+                self.breaking(msg, ni.name.pos(), oi.name.pos());
+            }
+        }
+        for (port, width) in &old.unannotated_ports {
+            match new.unannotated_ports.iter().find(|(p, _)| p == port) {
+                None => {
+                    let msg = format!(
+                        "unannotated port `{port}' of `{name}' was removed"
+                    );
+                    self.removed(msg, old.name.pos());
+                }
+                Some((_, w)) if w != width => {
+                    let msg = format!(
+                        "unannotated port `{port}' of `{name}' is {w} bits wide instead of {width}"
+                    );
+                    self.breaking(msg, new.name.pos(), old.name.pos());
+                }
+                _ => (),
+            }
+        }
+        for (port, _) in &new.unannotated_ports {
+            if !old.unannotated_ports.iter().any(|(p, _)| p == port) {
+                let msg = format!(
+                    "component `{name}' has a new unannotated port `{port}'"
+                );
+                self.breaking(msg, new.name.pos(), old.name.pos());
+            }
+        }
+    }
+
+    /// Compare the ports of the two versions. Inputs may require their values
+    /// for a shorter time while outputs may provide them for longer.
+    fn ports(
+        &mut self,
+        old: &ast::Signature,
+        new: &ast::Signature,
+        assumes: &[smt::SExpr],
+        names: (&Names, &Names),
+    ) {
+        let name = old.name.inner();
+        for (is_input, olds, news) in [
+            (true, old.inputs().collect_vec(), new.inputs().collect_vec()),
+            (
+                false,
+                old.outputs().collect_vec(),
+                new.outputs().collect_vec(),
+            ),
+        ] {
+            let kind = if is_input { "input" } else { "output" };
+            for op in &olds {
+                let pname = op.name().inner();
+                let Some(np) = news.iter().find(|p| p.name().inner() == pname)
+                else {
+                    let msg = format!(
+                        "{kind} port `{pname}' of `{name}' was removed"
+                    );
+                    self.removed(msg, op.name().pos());
+                    continue;
+                };
+                self.port(op, np, is_input, assumes, names);
+            }
+            if is_input {
+                for np in &news {
+                    let pname = np.name().inner();
+                    if !olds.iter().any(|p| p.name().inner() == pname) {
+                        let msg = format!(
+                            "component `{name}' has a new input port `{pname}'"
+                        );
+                        self.breaking(msg, np.name().pos(), old.name.pos());
+                    }
+                }
+            }
+        }
+    }
+
+    fn port(
+        &mut self,
+        old: &ast::PortDef,
+        new: &ast::PortDef,
+        is_input: bool,
+        assumes: &[smt::SExpr],
+        (on, nn): (&Names, &Names),
+    ) {
+        let pname = old.name().inner();
+        let (old_pos, new_pos) = (old.name().pos(), new.name().pos());
+        // Constrain the indices of bundles to be within bounds
+        let mut assumes = assumes.to_vec();
+        let (on, nn, (olive, owidth), (nlive, nwidth)) = match (old, new) {
+            (
+                ast::PortDef::Port {
+                    liveness: ol,
+                    bitwidth: ow,
+                    ..
+                },
+                ast::PortDef::Port {
+                    liveness: nl,
+                    bitwidth: nw,
+                    ..
+                },
+            ) => (on.clone(), nn.clone(), (ol, ow), (nl, nw)),
+            (ast::PortDef::Bundle(ob), ast::PortDef::Bundle(nb)) => {
+                let (ot, nt) = (&ob.typ, &nb.typ);
+                if ot.len.len() != nt.len.len() {
+                    let msg = format!(
+                        "bundle `{pname}' has {} dimension(s) instead of {}",
+                        nt.len.len(),
+                        ot.len.len()
+                    );
+                    return self.breaking(msg, new_pos, old_pos);
+                }
+                let (on, nn) = (on.with_bundle(ob), nn.with_bundle(nb));
+                for (i, (ol, nl)) in ot.len.iter().zip(&nt.len).enumerate() {
+                    let (o, n) = (self.expr(ol, &on), self.expr(nl, &nn));
+                    let eq = self.cmp(&ast::OrderOp::Eq, o, n);
+                    if !self.valid(&assumes, eq) {
+                        let msg = format!(
+                            "dimension {i} of bundle `{pname}' changed length from `{}' to `{}'",
+                            ol.inner(),
+                            nl.inner()
+                        );
+                        self.breaking(msg, nl.pos(), ol.pos());
+                    }
+                    let idx = self.var(ot.idx[i].inner(), &on);
+                    let zero = self.sol.numeral(0);
+                    assumes.push(self.sol.gte(idx, zero));
+                    assumes.push(self.sol.gt(o, idx));
+                }
+                (
+                    on,
+                    nn,
+                    (&ot.liveness, &ot.bitwidth),
+                    (&nt.liveness, &nt.bitwidth),
+                )
+            }
+            _ => {
+                let msg = format!(
+                    "port `{pname}' changed between a port and a bundle"
+                );
+                return self.breaking(msg, new_pos, old_pos);
+            }
+        };
+
+        let (o, n) = (self.expr(owidth, &on), self.expr(nwidth, &nn));
+        let eq = self.cmp(&ast::OrderOp::Eq, o, n);
+        if !self.valid(&assumes, eq) {
+            let msg = format!(
+                "port `{pname}' changed width from `{}' to `{}'",
+                owidth.inner(),
+                nwidth.inner()
+            );
+            self.breaking(msg, nwidth.pos(), owidth.pos());
+        }
+
+        // The interval that has to contain the other one
+        let (os, oe) =
+            (self.time(&olive.start, &on), self.time(&olive.end, &on));
+        let (ns, ne) =
+            (self.time(&nlive.start, &nn), self.time(&nlive.end, &nn));
+        let (outer, inner) = if is_input {
+            ((os, oe), (ns, ne))
+        } else {
+            ((ns, ne), (os, oe))
+        };
+        let start = self.cmp(&ast::OrderOp::Gte, inner.0, outer.0);
+        let end = self.cmp(&ast::OrderOp::Gte, outer.1, inner.1);
+        if !self.valid(&assumes, start) || !self.valid(&assumes, end) {
+            let msg = if is_input {
+                format!(
+                    "input port `{pname}' requires its value during {} instead of {}",
+                    range(nlive),
+                    range(olive)
+                )
+            } else {
+                format!(
+                    "output port `{pname}' provides its value during {} instead of {}",
+                    range(nlive),
+                    range(olive)
+                )
+            };
+            self.breaking(msg, nlive.pos(), olive.pos());
+        }
+    }
+
+    /// Parameters bound in the `with` section can be accessed by users
+    fn bindings(
+        &mut self,
+        old: &ast::Signature,
+        new: &ast::Signature,
+        assumes: &[smt::SExpr],
+        (on, nn): (&Names, &Names),
+    ) {
+        let name = old.name.inner();
+        let param = |sb: &ast::SigBind| match sb {
+            ast::SigBind::Let { param, .. }
+            | ast::SigBind::Exists { param, .. } => param.clone(),
+        };
+        for osb in &old.sig_bindings {
+            let op = param(osb.inner());
+            let Some(nsb) = new
+                .sig_bindings
+                .iter()
+                .find(|sb| param(sb.inner()).inner() == op.inner())
+            else {
+                let msg = format!("parameter `{op}' of `{name}' was removed");
+                self.removed(msg, op.pos());
+                continue;
+            };
+            let np = param(nsb.inner());
+            // The new binding has to guarantee everything the old one did
+            let mut new_facts = vec![];
+            match nsb.inner() {
+                ast::SigBind::Let { bind, .. } => {
+                    let p = self.var(np.inner(), nn);
+                    let b = self.expr(bind, nn);
+                    new_facts.push(self.sol.eq(p, b));
+                }
+                ast::SigBind::Exists { cons, .. } => {
+                    for c in cons {
+                        new_facts.push(self.expr_cons(c, nn));
+                    }
+                }
+            }
+            let mut old_facts = vec![];
+            match osb.inner() {
+                ast::SigBind::Let { bind, .. } => {
+                    let p = self.var(op.inner(), on);
+                    let b = self.expr(bind, on);
+                    old_facts.push(self.sol.eq(p, b));
+                }
+                ast::SigBind::Exists { cons, .. } => {
+                    for c in cons {
+                        old_facts.push(self.expr_cons(c, on));
+                    }
+                }
+            }
+            // The old facts about the binding are not assumed
+            let base = assumes
+                .iter()
+                .filter(|a| !old_facts.contains(a))
+                .copied()
+                .chain(new_facts)
+                .collect_vec();
+            if !old_facts.into_iter().all(|f| self.valid(&base, f)) {
+                let msg = format!(
+                    "parameter `{np}' of `{name}' no longer guarantees what it did"
+                );
+                self.breaking(msg, np.pos(), op.pos());
+            }
+        }
+    }
+
+    /// All signatures defined in a namespace
+    fn signatures(ns: &ast::Namespace) -> Vec<&ast::Signature> {
+        ns.components
+            .iter()
+            .map(|c| &c.sig)
+            .chain(ns.externs.iter().flat_map(|e| &e.comps))
+            .collect()
+    }
+
+    /// Compare the components defined in two versions of a library and
+    /// report the breaking changes. Returns the number of breaking changes.
+    pub fn diff(
+        opts: &cmdline::ApiDiffOpts,
+        old: &ast::Namespace,
+        new: &ast::Namespace,
+    ) -> u64 {
+        let mut diff = Self::new(None);
+        diff.compare(old, new);
+        if diff.needs_solver {
+            diff = Self::new(Some(opts.solver));
+            diff.compare(old, new);
+        }
+        fil_utils::report_notes(&diff.diagnostics);
+        diff.diagnostics.len() as u64
+    }
+
+    fn compare(&mut self, old: &ast::Namespace, new: &ast::Namespace) {
+        let news = Self::signatures(new);
+        for old in Self::signatures(old) {
+            let name = old.name.inner();
+            match news.iter().find(|s| s.name.inner() == name) {
+                Some(new) => self.signature(old, new),
+                None => {
+                    let msg = format!("component `{name}' was removed");
+                    self.removed(msg, old.name.pos());
+                }
+            }
+        }
+    }
+}
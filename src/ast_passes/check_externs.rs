@@ -0,0 +1,391 @@
+use crate::verilog::{self, Direction, Parser};
+use fil_ast as ast;
+use fil_utils::{BigInt, Diagnostics, Error, GPosIdx, Id};
+use itertools::Itertools;
+use std::{collections::HashMap, path::Path};
+
+/// Values given to parameters without defaults when comparing widths
+const SAMPLES: [u64; 2] = [8, 32];
+
+/// A port that the signature expects the Verilog module to define
+struct Expected {
+    name: Id,
+    pos: GPosIdx,
+    dir: Direction,
+    width: ast::Expr,
+}
+
+/// Checks the signatures of external components against the headers of the
+/// Verilog modules that implement them.
+///
+/// For every component in an `extern` block, the module with the same name
+/// must be defined in the referenced file and must have:
+/// - a parameter for each parameter of the component,
+/// - a port for each port of the component, including the unannotated and
+///   interface ports, with the same direction,
+/// - the same widths for these ports when the parameters are assigned their
+///   defaults or sample values, and
+/// - no input ports that the component does not declare.
+pub struct CheckExterns {
+    diag: Diagnostics,
+}
+
+impl CheckExterns {
+    pub fn check(ns: &ast::Namespace) -> Result<(), u64> {
+        let mut check = CheckExterns {
+            diag: Diagnostics::default(),
+        };
+        // Files are parsed once even when they are referenced by several
+        // extern blocks.
+        let mut files: HashMap<&str, Vec<verilog::Module>> = HashMap::new();
+        for ext in &ns.externs {
+            // Generated modules do not exist until the generator runs
+            if ext.gen.is_some() {
+                continue;
+            }
+            if !files.contains_key(ext.path.as_str()) {
+                match Parser::parse_file(Path::new(&ext.path), &mut check.diag)
+                {
+                    Ok(modules) => {
+                        files.insert(&ext.path, modules);
+                    }
+                    Err(err) => {
+                        check.diag.add_error(err);
+                        continue;
+                    }
+                }
+            }
+            let modules = &files[ext.path.as_str()];
+            for sig in &ext.comps {
+                match modules
+                    .iter()
+                    .find(|m| m.name.inner() == sig.name.inner())
+                {
+                    Some(m) => check.comp(sig, m),
+                    None => {
+                        let err = Error::malformed(format!(
+                            "module `{}' is not defined in `{}'",
+                            sig.name, ext.path
+                        ))
+                        .add_note(
+                            check.diag.add_info(
+                                "external component declared here",
+                                sig.name.pos(),
+                            ),
+                        );
+                        check.diag.add_error(err);
+                    }
+                }
+            }
+        }
+        match check.diag.report_all() {
+            Some(errs) => Err(errs),
+            None => Ok(()),
+        }
+    }
+
+    /// Report an error with notes at the signature and the module
+    fn error(
+        &mut self,
+        msg: String,
+        fil: (&str, GPosIdx),
+        sv: (&str, GPosIdx),
+    ) {
+        let err = Error::malformed(msg)
+            .add_note(self.diag.add_info(fil.0, fil.1))
+            .add_note(self.diag.add_info(sv.0, sv.1));
+        self.diag.add_error(err);
+    }
+
+    fn comp(&mut self, sig: &ast::Signature, m: &verilog::Module) {
+        for p in &sig.params {
+            match m
+                .params
+                .iter()
+                .find(|vp| vp.name.inner() == p.param.inner())
+            {
+                Some(vp) if vp.local => self.error(
+                    format!(
+                        "parameter `{}' of module `{}' is a localparam",
+                        p.param, m.name
+                    ),
+                    ("parameter declared here", p.param.pos()),
+                    ("localparam defined here", vp.name.pos()),
+                ),
+                Some(_) => (),
+                None => self.error(
+                    format!(
+                        "module `{}' does not have a parameter named `{}'",
+                        m.name, p.param
+                    ),
+                    ("parameter declared here", p.param.pos()),
+                    ("module defined here", m.name.pos()),
+                ),
+            }
+        }
+        for vp in &m.params {
+            let declared = sig
+                .params
+                .iter()
+                .any(|p| p.param.inner() == vp.name.inner());
+            if !declared && !vp.local && vp.default.is_none() {
+                self.error(
+                    format!(
+                        "parameter `{}' of module `{}' has no default and is not a parameter of the component",
+                        vp.name, m.name
+                    ),
+                    ("component declared here", sig.name.pos()),
+                    ("parameter defined here", vp.name.pos()),
+                );
+            }
+        }
+
+        let expected = Self::expected(sig);
+        let samples = Self::samples(sig, m);
+        for exp in &expected {
+            let Some(port) = m.port(exp.name.as_ref()) else {
+                self.error(
+                    format!(
+                        "module `{}' does not have a port named `{}'",
+                        m.name, exp.name
+                    ),
+                    ("port declared here", exp.pos),
+                    ("module defined here", m.name.pos()),
+                );
+                continue;
+            };
+            if port.dir != exp.dir {
+                self.error(
+                    format!(
+                        "port `{}' is an {} in the signature but an {} in module `{}'",
+                        exp.name, exp.dir, port.dir, m.name
+                    ),
+                    ("port declared here", exp.pos),
+                    ("port defined here", port.name.pos()),
+                );
+                continue;
+            }
+            self.width(sig, exp, port, &samples);
+        }
+
+        // Bundles are not checked and may be split into one port per element
+        let bundles = sig
+            .ports()
+            .iter()
+            .filter_map(|p| match p.inner() {
+                ast::PortDef::Bundle(b) if !Self::is_port(b) => {
+                    Some(b.name.to_string())
+                }
+                ast::PortDef::Bundle(_) | ast::PortDef::Port { .. } => None,
+            })
+            .collect_vec();
+        for port in &m.ports {
+            let name = port.name.as_ref();
+            let declared = expected.iter().any(|e| e.name == name)
+                || bundles.iter().any(|b| {
+                    name.strip_prefix(b.as_str()).map_or(false, |s| {
+                        s.split('_').skip(1).all(|i| i.parse::<u64>().is_ok())
+                            && (s.is_empty() || s.starts_with('_'))
+                    })
+                });
+            if port.dir == Direction::Input && !declared {
+                self.error(
+                    format!(
+                        "input `{}' of module `{}' is not a port of the component",
+                        port.name, m.name
+                    ),
+                    ("component declared here", sig.name.pos()),
+                    ("port defined here", port.name.pos()),
+                );
+            }
+        }
+    }
+
+    /// The ports that the Verilog module should define
+    fn expected(sig: &ast::Signature) -> Vec<Expected> {
+        // Unannotated ports do not track their positions
+        let unannotated =
+            sig.unannotated_ports.iter().map(|(name, width)| Expected {
+                name: *name,
+                pos: sig.name.pos(),
+                dir: Direction::Input,
+                width: ast::Expr::concrete(*width),
+            });
+        let interfaces = sig.interface_signals.iter().map(|i| Expected {
+            name: *i.name.inner(),
+            pos: i.name.pos(),
+            dir: Direction::Input,
+            width: ast::Expr::concrete(1),
+        });
+        let port = |dir| {
+            move |p: &ast::Loc<ast::PortDef>| match p.inner() {
+                ast::PortDef::Port { name, bitwidth, .. } => Some(Expected {
+                    name: *name.inner(),
+                    pos: name.pos(),
+                    dir,
+                    width: bitwidth.inner().clone(),
+                }),
+                // Ports are parsed as bundles with a single element
+                ast::PortDef::Bundle(b) if Self::is_port(b) => Some(Expected {
+                    name: *b.name.inner(),
+                    pos: b.name.pos(),
+                    dir,
+                    width: b.typ.bitwidth.inner().clone(),
+                }),
+                ast::PortDef::Bundle(_) => None,
+            }
+        };
+        unannotated
+            .chain(interfaces)
+            .chain(sig.inputs().filter_map(port(Direction::Input)))
+            .chain(sig.outputs().filter_map(port(Direction::Output)))
+            .collect()
+    }
+
+    /// Is this bundle a single port?
+    fn is_port(b: &ast::Bundle) -> bool {
+        matches!(&b.typ.len[..], [l] if l.is_const(1))
+    }
+
+    /// Assignments to the parameters of the component and of the module used
+    /// to compare widths. Parameters with defaults are assigned their defaults
+    /// and the others are assigned values from [SAMPLES]. Assignments that
+    /// violate the constraints of the component are skipped.
+    fn samples(
+        sig: &ast::Signature,
+        m: &verilog::Module,
+    ) -> Vec<(ast::Binding<ast::Expr>, ast::Binding<ast::Expr>)> {
+        let mut samples = vec![];
+        for sample in SAMPLES {
+            let mut fil = ast::Binding::default();
+            for p in &sig.params {
+                let v = p
+                    .default
+                    .as_ref()
+                    .and_then(|d| eval(d, &fil))
+                    .unwrap_or(sample);
+                fil.insert(*p.param.inner(), ast::Expr::concrete(v));
+            }
+            for sb in &sig.sig_bindings {
+                if let ast::SigBind::Let { param, bind } = sb.inner() {
+                    if let Some(v) = eval(bind, &fil) {
+                        fil.insert(*param.inner(), ast::Expr::concrete(v));
+                    }
+                }
+            }
+            let violated = sig.param_constraints.iter().any(|c| {
+                let (Some(l), Some(r)) =
+                    (eval(&c.left, &fil), eval(&c.right, &fil))
+                else {
+                    return false;
+                };
+                match c.op {
+                    ast::OrderOp::Gt => l <= r,
+                    ast::OrderOp::Gte => l < r,
+                    ast::OrderOp::Eq => l != r,
+                }
+            });
+            if violated {
+                continue;
+            }
+
+            // Parameters of the module not set by the component use their
+            // defaults.
+            let mut sv = ast::Binding::default();
+            for p in &m.params {
+                let declared = sig
+                    .params
+                    .iter()
+                    .any(|fp| fp.param.inner() == p.name.inner());
+                let v = if declared && !p.local {
+                    fil.find(p.name.inner()).cloned()
+                } else {
+                    p.default
+                        .as_ref()
+                        .and_then(|d| eval(d, &sv))
+                        .map(ast::Expr::concrete)
+                };
+                if let Some(v) = v {
+                    sv.insert(*p.name.inner(), v);
+                }
+            }
+            samples.push((fil, sv));
+        }
+        samples
+    }
+
+    /// Compare the width of a port under each sample
+    fn width(
+        &mut self,
+        sig: &ast::Signature,
+        exp: &Expected,
+        port: &verilog::Port,
+        samples: &[(ast::Binding<ast::Expr>, ast::Binding<ast::Expr>)],
+    ) {
+        let Some(sv_width) = &port.width else {
+            return;
+        };
+        for (fil, sv) in samples {
+            let (Some(fw), Some(vw)) =
+                (eval(&exp.width, fil), eval(sv_width, sv))
+            else {
+                continue;
+            };
+            if fw == vw {
+                continue;
+            }
+            let params = if sig.params.is_empty() {
+                String::new()
+            } else {
+                let params = sig
+                    .params
+                    .iter()
+                    .map(|p| format!("{}={}", p.param, fil[p.param.inner()]))
+                    .join(", ");
+                format!(" when {params}")
+            };
+            self.error(
+                format!(
+                    "port `{}' has width {fw} in the signature but {vw} in the module{params}",
+                    exp.name
+                ),
+                (&format!("width is {fw}"), exp.pos),
+                (&format!("width is {vw}"), port.typ.pos()),
+            );
+            return;
+        }
+    }
+}
+
+/// Evaluate an expression under a binding of its parameters to constants
+pub(super) fn eval(
+    e: &ast::Expr,
+    bind: &ast::Binding<ast::Expr>,
+) -> Option<u64> {
+    fn fold(e: ast::Expr) -> ast::Expr {
+        match e {
+            ast::Expr::App { func, args } => {
+                let args = args.into_iter().map(fold).collect_vec();
+                let vals = args
+                    .iter()
+                    .map(|a| match a {
+                        ast::Expr::Concrete(n) => Some(BigInt::from(n.clone())),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match vals.and_then(|v| func.eval(v)) {
+                    Some(v) => match v.to_biguint() {
+                        Some(n) => ast::Expr::Concrete(n.clone()),
+                        None => ast::Expr::App { func, args },
+                    },
+                    None => ast::Expr::App { func, args },
+                }
+            }
+            ast::Expr::Op { op, left, right } => {
+                ast::Expr::op(op, fold(*left), fold(*right))
+            }
+            e => e,
+        }
+    }
+    u64::try_from(fold(e.clone().resolve(bind))).ok()
+}
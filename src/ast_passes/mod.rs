@@ -0,0 +1,7 @@
+mod check_externs;
+mod schedule;
+mod test_params;
+
+pub use check_externs::CheckExterns;
+pub use schedule::InferSchedule;
+pub use test_params::TestParams;
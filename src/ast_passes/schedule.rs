@@ -0,0 +1,719 @@
+use codespan_reporting::diagnostic::Diagnostic;
+use fil_ast as ast;
+use fil_utils::{Diagnostics, Error, GPosIdx, Id};
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// Display a time expression that is a concrete offset from an event.
+fn fmt_time(event: Id, offset: u64) -> String {
+    if offset == 0 {
+        format!("'{event}")
+    } else {
+        format!("'{event}+{offset}")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+/// An interval whose start and end are concrete offsets from the same event.
+struct Interval {
+    event: Id,
+    start: u64,
+    end: u64,
+}
+
+impl Interval {
+    /// Returns `None` if the range is not a concrete offset from a single event.
+    fn concrete(range: &ast::Range) -> Option<Self> {
+        let ast::Range { start, end } = range;
+        if start.event != end.event {
+            return None;
+        }
+        Some(Self {
+            event: start.event,
+            start: start.offset().try_into().ok()?,
+            end: end.offset().try_into().ok()?,
+        })
+    }
+
+    /// Is this interval contained within `other`?
+    fn within(&self, other: &Interval) -> bool {
+        self.event == other.event
+            && other.start <= self.start
+            && self.end <= other.end
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}, {}]",
+            fmt_time(self.event, self.start),
+            fmt_time(self.event, self.end)
+        )
+    }
+}
+
+/// What part of the invoked component a [Requirement] constrains.
+enum Bound {
+    /// The input port must only require its value within the interval
+    Input(Id),
+    /// The output port must provide a value for the entire interval
+    Output(Id),
+    /// The event (and its delay) must fit within the instance's liveness
+    Event(usize),
+}
+
+/// A requirement on the schedule of an invocation derived from its context.
+struct Requirement {
+    bound: Bound,
+    /// The interval provided or required by the context
+    ctx: Interval,
+    /// Location that generated this requirement
+    pos: GPosIdx,
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.bound {
+            Bound::Input(p) => {
+                write!(f, "input `{p}' is provided a value in {}", self.ctx)
+            }
+            Bound::Output(p) => {
+                write!(f, "output `{p}' must provide a value in {}", self.ctx)
+            }
+            Bound::Event(_) => {
+                write!(f, "instance is live in {}", self.ctx)
+            }
+        }
+    }
+}
+
+/// An instance in the component being scheduled
+struct InstInfo<'a> {
+    sig: &'a ast::Signature,
+    params: ast::Binding<ast::Expr>,
+    lives: &'a [ast::Loc<ast::Range>],
+}
+
+/// Construct the parameter binding for an instance of `sig`.
+/// Returns `None` if the arguments are malformed; astconv reports the error.
+fn param_binding(
+    sig: &ast::Signature,
+    args: &[ast::Loc<ast::Expr>],
+) -> Option<ast::Binding<ast::Expr>> {
+    if args.len() > sig.params.len() {
+        return None;
+    }
+    let mut binding = ast::Binding::new(
+        sig.params
+            .iter()
+            .map(|p| p.name())
+            .zip(args.iter().map(|a| a.inner().clone())),
+    );
+    for p in sig.params.iter().skip(args.len()) {
+        let bind = p.default.clone()?.resolve(&binding);
+        binding.insert(p.name(), bind);
+    }
+    for sb in &sig.sig_bindings {
+        if let ast::SigBind::Let { param, bind } = sb.inner() {
+            let bind = bind.clone().resolve(&binding);
+            binding.insert(param.copy(), bind);
+        }
+    }
+    Some(binding)
+}
+
+/// Construct the event binding for an invocation of `sig`.
+/// Returns `None` if the arguments are malformed; astconv reports the error.
+fn event_binding(
+    sig: &ast::Signature,
+    args: Vec<ast::Time>,
+) -> Option<ast::Binding<ast::Time>> {
+    if args.len() > sig.events.len() {
+        return None;
+    }
+    let mut binding = ast::Binding::new(
+        sig.events
+            .iter()
+            .map(|eb| eb.event.copy())
+            .zip(args.iter().cloned()),
+    );
+    for eb in sig.events.iter().skip(args.len()) {
+        let bind = eb.default.clone()?.resolve_event(&binding);
+        binding.insert(eb.event.copy(), bind);
+    }
+    Some(binding)
+}
+
+/// Liveness of a port definition that carries a single value.
+fn port_liveness(pd: &ast::PortDef) -> Option<&ast::Range> {
+    match pd {
+        ast::PortDef::Port { liveness, .. } => Some(liveness.inner()),
+        ast::PortDef::Bundle(ast::Bundle { typ, .. }) => {
+            let single = typ.len.iter().all(|l| l.inner().is_const(1));
+            single.then(|| typ.liveness.inner())
+        }
+    }
+}
+
+/// The liveness of `port` on an invocation of `sig` with the given bindings.
+fn port_interval(
+    sig: &ast::Signature,
+    params: &ast::Binding<ast::Expr>,
+    events: &ast::Binding<ast::Time>,
+    port: Id,
+) -> Option<Interval> {
+    let pd = sig.ports().iter().find(|p| *p.name().inner() == port)?;
+    let range = port_liveness(pd.inner())?
+        .clone()
+        .resolve_exprs(params)
+        .resolve_event(events);
+    Interval::concrete(&range)
+}
+
+/// Infers event bindings written as `_` in invocations.
+///
+/// For an invocation `m := M<_>(...)`, the pass picks the earliest offset `k`
+/// from the containing component's event such that binding the event to
+/// `'G+k` satisfies the liveness requirements of the invocation's inputs, the
+/// uses of its outputs, and the liveness of the instance. The requirements
+/// are computed from the producers' output intervals and must be concrete.
+/// The inferred schedule is reported and later checked by the type checker.
+///
+/// Only one event binding per invocation may be inferred, and invocations
+/// whose schedules are inferred must not read from each other in a cycle.
+pub struct InferSchedule<'a> {
+    /// Signatures of all the components in the namespace
+    sigs: HashMap<Id, &'a ast::Signature>,
+    /// Signature of the component being scheduled
+    sig: &'a ast::Signature,
+    /// Instances in the component being scheduled
+    insts: HashMap<Id, InstInfo<'a>>,
+    /// Invocations in the component being scheduled
+    invs: Vec<&'a ast::Invoke>,
+    /// Connections in the component being scheduled
+    connects: Vec<&'a ast::Connect>,
+    /// Event bindings of invocations with a known schedule
+    scheduled: HashMap<Id, ast::Binding<ast::Time>>,
+    /// Inferred bindings: (invocation name, event index, binding)
+    inferred: Vec<(Id, usize, ast::Time)>,
+    diag: Diagnostics,
+    notes: Vec<Diagnostic<usize>>,
+}
+
+impl<'a> InferSchedule<'a> {
+    /// Infer the schedules of all invocations in the namespace that use `_`.
+    /// Returns the number of errors if a schedule cannot be inferred.
+    pub fn infer(ns: &mut ast::Namespace) -> Result<(), u64> {
+        let needs_inference = |c: &ast::Component| Self::has_holes(&c.body);
+        if !ns.components.iter().any(needs_inference) {
+            return Ok(());
+        }
+
+        let mut solutions = Vec::with_capacity(ns.components.len());
+        let mut diag = Diagnostics::default();
+        let mut notes = vec![];
+        {
+            let sigs: HashMap<_, _> = ns
+                .components
+                .iter()
+                .map(|c| (*c.sig.name.inner(), &c.sig))
+                .chain(ns.externals())
+                .collect();
+            for comp in &ns.components {
+                if !Self::has_holes(&comp.body) {
+                    solutions.push(vec![]);
+                    continue;
+                }
+                let mut sched = InferSchedule {
+                    sigs: sigs.clone(),
+                    sig: &comp.sig,
+                    insts: HashMap::default(),
+                    invs: vec![],
+                    connects: vec![],
+                    scheduled: HashMap::default(),
+                    inferred: vec![],
+                    diag: std::mem::take(&mut diag),
+                    notes: vec![],
+                };
+                sched.collect(&comp.body);
+                sched.schedule();
+                diag = std::mem::take(&mut sched.diag);
+                notes.append(&mut sched.notes);
+                solutions.push(sched.inferred);
+            }
+        }
+
+        if let Some(errs) = diag.report_all() {
+            return Err(errs);
+        }
+
+        for (comp, sol) in ns.components.iter_mut().zip(solutions) {
+            Self::apply(&mut comp.body, &sol);
+        }
+
+        // Report the inferred schedules
+        fil_utils::report_notes(&notes);
+        Ok(())
+    }
+
+    fn has_holes(cmds: &[ast::Command]) -> bool {
+        cmds.iter().any(|cmd| match cmd {
+            ast::Command::Invoke(inv) => !inv.holes.is_empty(),
+            ast::Command::ForLoop(l) => Self::has_holes(&l.body),
+            ast::Command::If(i) => {
+                Self::has_holes(&i.then) || Self::has_holes(&i.alt)
+            }
+            _ => false,
+        })
+    }
+
+    /// Replace the holes in the invocations with the inferred bindings.
+    fn apply(cmds: &mut [ast::Command], sol: &[(Id, usize, ast::Time)]) {
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Invoke(inv) => {
+                    for (name, idx, time) in sol {
+                        if name == inv.name.inner() {
+                            let pos = inv.abstract_vars[*idx].pos();
+                            inv.abstract_vars[*idx] =
+                                ast::Loc::new(time.clone(), pos);
+                            inv.holes.retain(|h| h != idx);
+                        }
+                    }
+                }
+                ast::Command::ForLoop(l) => Self::apply(&mut l.body, sol),
+                ast::Command::If(i) => {
+                    Self::apply(&mut i.then, sol);
+                    Self::apply(&mut i.alt, sol);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Collect the instances, invocations, and connections in the component.
+    fn collect(&mut self, cmds: &'a [ast::Command]) {
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Instance(inst) => {
+                    let Some(&sig) = self.sigs.get(inst.component.inner())
+                    else {
+                        continue;
+                    };
+                    let Some(params) = param_binding(sig, &inst.params) else {
+                        continue;
+                    };
+                    self.insts.insert(
+                        inst.name.copy(),
+                        InstInfo {
+                            sig,
+                            params,
+                            lives: &inst.lives,
+                        },
+                    );
+                }
+                ast::Command::Invoke(inv) => self.invs.push(inv),
+                ast::Command::Connect(con) => self.connects.push(con),
+                ast::Command::ForLoop(l) => self.collect(&l.body),
+                ast::Command::If(i) => {
+                    self.collect(&i.then);
+                    self.collect(&i.alt);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Interval during which a port in this component is available.
+    fn available(&self, port: &ast::Port) -> Option<Interval> {
+        match port {
+            ast::Port::This(name) => {
+                let pd = self.sig.inputs().find(|p| p.name() == name)?;
+                Interval::concrete(port_liveness(pd.inner())?)
+            }
+            ast::Port::InvPort { invoke, name } => {
+                let events = self.scheduled.get(invoke.inner())?;
+                let inv = self.invs.iter().find(|i| i.name == *invoke)?;
+                let inst = self.insts.get(inv.instance.inner())?;
+                port_interval(inst.sig, &inst.params, events, name.copy())
+            }
+            ast::Port::Bundle { .. } | ast::Port::InvBundle { .. } => None,
+        }
+    }
+
+    /// Requirements on the uses of the outputs of invocation `inv`.
+    fn uses(&self, inv: Id) -> Vec<Requirement> {
+        let mut reqs = vec![];
+        let is_inv = |p: &ast::Port| match p {
+            ast::Port::InvPort { invoke, name } if *invoke.inner() == inv => {
+                Some(name.copy())
+            }
+            _ => None,
+        };
+
+        // Connections to the outputs of the component
+        for con in &self.connects {
+            let Some(port) = is_inv(con.src.inner()) else {
+                continue;
+            };
+            let ast::Port::This(dst) = con.dst.inner() else {
+                continue;
+            };
+            let Some(pd) = self.sig.outputs().find(|p| p.name() == dst) else {
+                continue;
+            };
+            let Some(liveness) = port_liveness(pd.inner()) else {
+                continue;
+            };
+            if let Some(ctx) = Interval::concrete(liveness) {
+                reqs.push(Requirement {
+                    bound: Bound::Output(port),
+                    ctx,
+                    pos: con.dst.pos(),
+                });
+            }
+        }
+
+        // Arguments to invocations with a known schedule
+        for user in &self.invs {
+            let Some(events) = self.scheduled.get(user.name.inner()) else {
+                continue;
+            };
+            let Some(inst) = self.insts.get(user.instance.inner()) else {
+                continue;
+            };
+            for (pd, arg) in inst.sig.inputs().zip(&user.ports) {
+                let Some(port) = is_inv(arg.inner()) else {
+                    continue;
+                };
+                let ctx = port_interval(
+                    inst.sig,
+                    &inst.params,
+                    events,
+                    pd.name().copy(),
+                );
+                if let Some(ctx) = ctx {
+                    reqs.push(Requirement {
+                        bound: Bound::Output(port),
+                        ctx,
+                        pos: arg.pos(),
+                    });
+                }
+            }
+        }
+        reqs
+    }
+
+    /// Check if the requirement holds when the event `hole` of `inv` is bound
+    /// to `time`. Returns `None` if the requirement cannot be evaluated
+    /// concretely.
+    fn holds(
+        &self,
+        req: &Requirement,
+        inv: &ast::Invoke,
+        inst: &InstInfo,
+        hole: usize,
+        time: ast::Time,
+    ) -> Option<bool> {
+        let mut args = inv
+            .abstract_vars
+            .iter()
+            .map(|t| t.inner().clone())
+            .collect_vec();
+        args[hole] = time;
+        let events = event_binding(inst.sig, args)?;
+        match &req.bound {
+            Bound::Input(p) => {
+                port_interval(inst.sig, &inst.params, &events, *p)
+                    .map(|i| i.within(&req.ctx))
+            }
+            Bound::Output(p) => {
+                port_interval(inst.sig, &inst.params, &events, *p)
+                    .map(|i| req.ctx.within(&i))
+            }
+            Bound::Event(idx) => {
+                let eb = &inst.sig.events[*idx];
+                let start = events.get(eb.event.inner()).clone();
+                let delay = match eb
+                    .delay
+                    .inner()
+                    .clone()
+                    .resolve_expr(&inst.params)
+                    .resolve_event(&events)
+                {
+                    ast::TimeSub::Unit(e) => u64::try_from(&e).ok()?,
+                    ast::TimeSub::Sym { l, r } => {
+                        let l = Interval::concrete(&ast::Range::new(l, r))?;
+                        l.start.checked_sub(l.end)?
+                    }
+                };
+                let start_off = u64::try_from(start.offset()).ok()?;
+                let used = Interval {
+                    event: start.event,
+                    start: start_off,
+                    end: start_off + delay,
+                };
+                Some(used.within(&req.ctx))
+            }
+        }
+    }
+
+    /// Schedule all invocations with holes. Invocations are scheduled once
+    /// all the invocations they read from have a known schedule.
+    fn schedule(&mut self) {
+        let mut pending = vec![];
+        for inv in &self.invs {
+            if !inv.holes.is_empty() {
+                pending.push(*inv);
+                continue;
+            }
+            let Some(inst) = self.insts.get(inv.instance.inner()) else {
+                continue;
+            };
+            let args = inv
+                .abstract_vars
+                .iter()
+                .map(|t| t.inner().clone())
+                .collect();
+            if let Some(events) = event_binding(inst.sig, args) {
+                self.scheduled.insert(inv.name.copy(), events);
+            }
+        }
+
+        // Invocations whose schedule could not be inferred. Their errors
+        // have already been reported.
+        let mut failed = HashSet::new();
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<_>, Vec<_>) =
+                pending.into_iter().partition(|inv| {
+                    self.inferred_deps(inv).iter().all(|dep| {
+                        self.scheduled.contains_key(dep) || failed.contains(dep)
+                    })
+                });
+            if ready.is_empty() {
+                self.report_cycle(&waiting);
+                return;
+            }
+            for inv in ready {
+                if self.inferred_deps(inv).iter().any(|d| failed.contains(d)) {
+                    failed.insert(inv.name.copy());
+                    continue;
+                }
+                self.schedule_inv(inv);
+                if !self.scheduled.contains_key(inv.name.inner()) {
+                    failed.insert(inv.name.copy());
+                }
+            }
+            pending = waiting;
+        }
+    }
+
+    /// Invocations with holes that `inv` reads from.
+    fn inferred_deps(&self, inv: &ast::Invoke) -> Vec<Id> {
+        inv.ports
+            .iter()
+            .filter_map(|p| match p.inner() {
+                ast::Port::InvPort { invoke, .. }
+                | ast::Port::InvBundle { invoke, .. } => Some(invoke.copy()),
+                _ => None,
+            })
+            .filter(|dep| {
+                self.invs
+                    .iter()
+                    .any(|i| *i.name.inner() == *dep && !i.holes.is_empty())
+            })
+            .unique()
+            .collect()
+    }
+
+    /// Report invocations whose schedules cannot be inferred because they
+    /// read from each other. Invocations that only read from such a cycle
+    /// are not reported.
+    fn report_cycle(&mut self, waiting: &[&'a ast::Invoke]) {
+        let deps: HashMap<_, _> = waiting
+            .iter()
+            .map(|i| (i.name.copy(), self.inferred_deps(i)))
+            .collect();
+        // Is `to` reachable from `from` by following the dependencies?
+        let reaches = |from: Id, to: Id| {
+            let mut seen = HashSet::new();
+            let mut stack = vec![from];
+            while let Some(cur) = stack.pop() {
+                if cur == to {
+                    return true;
+                }
+                if seen.insert(cur) {
+                    stack.extend(deps.get(&cur).into_iter().flatten());
+                }
+            }
+            false
+        };
+        for inv in waiting {
+            let name = inv.name.copy();
+            let Some(&dep) = deps[&name].iter().find(|d| reaches(**d, name))
+            else {
+                continue;
+            };
+            let msg = format!(
+                "cannot infer schedule for invocation `{name}': it reads from `{dep}', whose schedule is also inferred and depends on `{name}'"
+            );
+            let err = Error::misc(msg)
+                .add_note(self.diag.add_info(
+                    "schedules that depend on each other cannot be inferred",
+                    inv.name.pos(),
+                ))
+                .add_note(self.diag.add_message(
+                    "bind the events of one of these invocations explicitly",
+                ));
+            self.diag.add_error(err);
+        }
+    }
+
+    fn schedule_inv(&mut self, inv: &'a ast::Invoke) {
+        if inv.holes.len() > 1 {
+            let msg = format!(
+                "cannot infer schedule for invocation `{}': only one event binding per invocation can be written as `_'",
+                inv.name
+            );
+            let mut err = Error::malformed(msg);
+            for hole in &inv.holes {
+                err = err.add_note(self.diag.add_info(
+                    "event binding to be inferred",
+                    inv.abstract_vars[*hole].pos(),
+                ));
+            }
+            let err = err
+                .add_note(self.diag.add_message(
+                    "bind all but one of these events explicitly",
+                ));
+            self.diag.add_error(err);
+            return;
+        }
+        let hole = inv.holes[0];
+        let hole_pos = inv.abstract_vars[hole].pos();
+        // Malformed instances are reported by astconv
+        let Some(inst) = self.insts.get(inv.instance.inner()) else {
+            return;
+        };
+        if hole >= inst.sig.events.len() {
+            return;
+        }
+
+        let mut reqs = inst
+            .sig
+            .inputs()
+            .zip(&inv.ports)
+            .filter_map(|(pd, arg)| {
+                self.available(arg.inner()).map(|ctx| Requirement {
+                    bound: Bound::Input(pd.name().copy()),
+                    ctx,
+                    pos: arg.pos(),
+                })
+            })
+            .collect_vec();
+        reqs.extend(self.uses(inv.name.copy()));
+        if let Some(live) = inst.lives.get(hole) {
+            if let Some(ctx) = Interval::concrete(live.inner()) {
+                reqs.push(Requirement {
+                    bound: Bound::Event(hole),
+                    ctx,
+                    pos: live.pos(),
+                });
+            }
+        }
+
+        let Some(event) = reqs.first().map(|r| r.ctx.event) else {
+            let msg = format!(
+                "cannot infer schedule for invocation `{}': none of its ports are connected to values with a concrete schedule",
+                inv.name
+            );
+            let err = Error::misc(msg)
+                .add_note(self.diag.add_info("cannot infer binding", hole_pos));
+            self.diag.add_error(err);
+            return;
+        };
+        let at = |k: u64| ast::Time::new(event, ast::Expr::concrete(k));
+
+        // Requirements that cannot be evaluated concretely are left to the
+        // type checker.
+        reqs.retain(|r| self.holds(r, inv, inst, hole, at(0)).is_some());
+
+        // Beyond the last cycle mentioned by a requirement, inputs are no
+        // longer available and outputs are produced too late.
+        let limit = reqs.iter().map(|r| r.ctx.end).max().unwrap_or(0);
+        let feasible = reqs
+            .iter()
+            .map(|r| {
+                (0..=limit)
+                    .filter(|k| {
+                        self.holds(r, inv, inst, hole, at(*k)).unwrap_or(false)
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let best = (0..=limit).find(|k| feasible.iter().all(|f| f.contains(k)));
+
+        let Some(k) = best else {
+            let msg = format!(
+                "no schedule for invocation `{}' satisfies its timing requirements",
+                inv.name
+            );
+            let mut err = Error::misc(msg).add_note(
+                self.diag.add_info("cannot infer event binding", hole_pos),
+            );
+            for (req, f) in reqs.iter().zip(&feasible) {
+                let note = match (f.first(), f.last()) {
+                    (Some(lo), Some(hi)) if lo == hi => format!(
+                        "{req}: requires the binding {}",
+                        fmt_time(event, *lo)
+                    ),
+                    (Some(lo), Some(hi)) => format!(
+                        "{req}: requires a binding between {} and {}",
+                        fmt_time(event, *lo),
+                        fmt_time(event, *hi)
+                    ),
+                    _ => format!("{req}: not satisfied by any binding"),
+                };
+                err = err.add_note(self.diag.add_info(note, req.pos));
+            }
+            self.diag.add_error(err);
+            return;
+        };
+
+        let time = at(k);
+        let mut args = inv
+            .abstract_vars
+            .iter()
+            .map(|t| t.inner().clone())
+            .collect_vec();
+        args[hole] = time.clone();
+        if let Some(events) = event_binding(inst.sig, args.clone()) {
+            self.scheduled.insert(inv.name.copy(), events);
+        }
+        let sched = args
+            .iter()
+            .map(|t| {
+                let off = u64::try_from(t.offset()).ok();
+                off.map_or_else(|| t.to_string(), |o| fmt_time(t.event, o))
+            })
+            .join(", ");
+        let note = Diagnostic::note().with_message(format!(
+            "inferred schedule for invocation `{}': {}<{sched}>",
+            inv.name, inv.instance
+        ));
+        let note = if let Some(pos) = hole_pos.into_option() {
+            note.with_labels(vec![pos
+                .primary()
+                .with_message(format!("inferred as {}", fmt_time(event, k)))])
+        } else {
+            note
+        };
+        self.notes.push(note);
+        self.inferred.push((inv.name.copy(), hole, time));
+    }
+}
@@ -0,0 +1,149 @@
+use super::check_externs::eval;
+use fil_ast as ast;
+use fil_utils::{BigInt, Diagnostics, Error};
+
+/// Computes the parameters that the component named by a `test` block is
+/// monomorphized with.
+///
+/// Parameters without arguments are assigned their defaults and the result
+/// must satisfy the range and the constraints of every parameter, since the
+/// component is not instantiated anywhere that would check them.
+pub struct TestParams {
+    diag: Diagnostics,
+}
+
+impl TestParams {
+    pub fn params(
+        ns: &ast::Namespace,
+        test: &ast::Test,
+    ) -> Result<Vec<BigInt>, u64> {
+        let mut tp = TestParams {
+            diag: Diagnostics::default(),
+        };
+        let params = tp.resolve(ns, test);
+        match tp.diag.report_all() {
+            Some(errs) => Err(errs),
+            None => Ok(params.unwrap()),
+        }
+    }
+
+    fn error(&mut self, msg: String, note: &str, pos: fil_utils::GPosIdx) {
+        let err = Error::malformed(msg).add_note(self.diag.add_info(note, pos));
+        self.diag.add_error(err);
+    }
+
+    fn resolve(
+        &mut self,
+        ns: &ast::Namespace,
+        test: &ast::Test,
+    ) -> Option<Vec<BigInt>> {
+        let Some(comp) = ns
+            .components
+            .iter()
+            .find(|c| c.sig.name.inner() == test.comp.inner())
+        else {
+            let msg = if ns.externals().any(|(n, _)| n == *test.comp.inner()) {
+                format!(
+                    "test `{}' uses external component `{}': only components defined in Filament can be tested",
+                    test.name, test.comp
+                )
+            } else {
+                format!(
+                    "test `{}' uses undefined component `{}'",
+                    test.name, test.comp
+                )
+            };
+            self.error(msg, "component used here", test.comp.pos());
+            return None;
+        };
+        let sig = &comp.sig;
+        let required =
+            sig.params.iter().filter(|p| p.default.is_none()).count();
+        if test.params.len() < required || test.params.len() > sig.params.len()
+        {
+            self.error(
+                format!(
+                    "`{}' requires {} parameters but {} were provided",
+                    sig.name,
+                    if required == sig.params.len() {
+                        required.to_string()
+                    } else {
+                        format!("{required} to {}", sig.params.len())
+                    },
+                    test.params.len()
+                ),
+                "parameters provided here",
+                test.comp.pos(),
+            );
+            return None;
+        }
+
+        let mut binding = ast::Binding::default();
+        let mut params = Vec::with_capacity(sig.params.len());
+        for (i, p) in sig.params.iter().enumerate() {
+            let (e, pos) = match test.params.get(i) {
+                Some(arg) => (arg.inner(), arg.pos()),
+                None => (p.default.as_ref().unwrap(), p.pos()),
+            };
+            let Some(v) = eval(e, &binding) else {
+                self.error(
+                    format!(
+                        "value of parameter `{}' of `{}' is not a constant: {e}",
+                        p.param, sig.name
+                    ),
+                    "parameter defined here",
+                    pos,
+                );
+                return None;
+            };
+            binding.insert(*p.param.inner(), ast::Expr::concrete(v));
+            params.push(BigInt::from(v));
+        }
+        // Let-bound parameters may appear in the constraints
+        for sb in &sig.sig_bindings {
+            if let ast::SigBind::Let { param, bind } = sb.inner() {
+                if let Some(v) = eval(bind, &binding) {
+                    binding.insert(*param.inner(), ast::Expr::concrete(v));
+                }
+            }
+        }
+
+        let ranges = sig.params.iter().flat_map(|p| {
+            p.ranges.iter().flat_map(|r| {
+                r.inner()
+                    .constraints(ast::Expr::abs(p.param.clone()))
+                    .into_iter()
+                    .map(|c| ast::Loc::new(c, r.pos()))
+            })
+        });
+        for c in ranges.chain(sig.param_constraints.iter().cloned()) {
+            let (Some(l), Some(r)) =
+                (eval(&c.left, &binding), eval(&c.right, &binding))
+            else {
+                continue;
+            };
+            let holds = match c.op {
+                ast::OrderOp::Gt => l > r,
+                ast::OrderOp::Gte => l >= r,
+                ast::OrderOp::Eq => l == r,
+            };
+            if !holds {
+                let err = Error::malformed(format!(
+                    "parameters of test `{}' violate constraint `{}' of `{}'",
+                    test.name,
+                    c.inner(),
+                    sig.name
+                ))
+                .add_note(
+                    self.diag.add_info("constraint defined here", c.pos()),
+                )
+                .add_note(
+                    self.diag
+                        .add_info("parameters provided here", test.comp.pos()),
+                );
+                self.diag.add_error(err);
+            }
+        }
+        Some(params)
+    }
+}
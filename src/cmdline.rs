@@ -2,10 +2,10 @@ use argh::FromArgs;
 use std::{path::PathBuf, str::FromStr};
 
 #[derive(Debug, Default, Clone, Copy)]
-/// Solver to use in the pass
+/// Solver to use in the pass. The default is shared by all subcommands.
 pub enum Solver {
-    #[default]
     CVC5,
+    #[default]
     Z3,
     Boolector,
     Bitwuzla,
@@ -26,6 +26,49 @@ impl FromStr for Solver {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+/// Width of the bitvector encoding used by the solver
+pub enum BvWidth {
+    /// Use the given width
+    Fixed(u8),
+    /// Derive the width for each component from its constants and the
+    /// ranges of its parameters.
+    Auto,
+}
+
+impl FromStr for BvWidth {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(BvWidth::Auto),
+            _ => s.parse().map(BvWidth::Fixed).map_err(|_| {
+                format!(
+                    "invalid bitvector width: {s}. Expected a number or `auto'"
+                )
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Description of the toplevel component to emit instead of compiling it
+pub enum Emit {
+    /// IP-XACT (IEEE 1685-2014) component description
+    IpXact,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ip-xact" => Ok(Emit::IpXact),
+            _ => {
+                Err(format!("unknown format: {s}. Known formats are: ip-xact"))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub enum Backend {
     #[default]
@@ -46,6 +89,33 @@ impl FromStr for Backend {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Scheme used to name the components generated by monomorphization
+pub enum Mangle {
+    /// Number the components: `comp3`
+    #[default]
+    Sequential,
+    /// Append the parameter values to the name of the definition: `Foo_8_2`
+    Readable,
+    /// Append a hash of the parameter values to the name of the definition:
+    /// `Foo_3f2a9c1d`
+    Hashed,
+}
+
+impl FromStr for Mangle {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(Mangle::Sequential),
+            "readable" => Ok(Mangle::Readable),
+            "hashed" => Ok(Mangle::Hashed),
+            _ => Err(format!(
+                "unknown mangling scheme: {s}. Known schemes are: sequential, readable, hashed"
+            )),
+        }
+    }
+}
+
 #[derive(FromArgs, Debug)]
 /// The Filament pipeline verifier
 pub struct Opts {
@@ -61,6 +131,10 @@ pub struct Opts {
     #[argh(switch, long = "show-models")]
     pub show_models: bool,
 
+    /// interactively explore the obligations that could not be proved
+    #[argh(switch, long = "debug-proofs")]
+    pub debug_proofs: bool,
+
     /// path to search for imports
     #[argh(option, long = "library", short = 'l', default = "\".\".into()")]
     pub library: PathBuf,
@@ -73,6 +147,25 @@ pub struct Opts {
     #[argh(switch, long = "dump-interface")]
     pub dump_interface: bool,
 
+    /// component whose interface is dumped: the name of a source or
+    /// generated component, or `all' (default: the toplevel component)
+    #[argh(option, long = "interface-comp")]
+    pub interface_comp: Option<String>,
+
+    /// emit a description of the toplevel component instead of compiling
+    /// it: ip-xact
+    #[argh(option, long = "emit")]
+    pub emit: Option<Emit>,
+
+    /// emit a self-checking SystemVerilog testbench for the toplevel
+    /// component that reads its stimulus from the given JSON file
+    #[argh(option, long = "emit-testbench")]
+    pub emit_testbench: Option<PathBuf>,
+
+    /// JSON file with the expected outputs checked by the testbench
+    #[argh(option, long = "testbench-expect")]
+    pub testbench_expect: Option<PathBuf>,
+
     /// set log level
     #[argh(option, long = "log", default = "log::LevelFilter::Warn")]
     pub log_level: log::LevelFilter,
@@ -81,12 +174,32 @@ pub struct Opts {
     #[argh(option, long = "toplevel", default = "\"main\".into()")]
     pub toplevel: String,
 
+    /// insert registers to repair connections whose source is live too early
+    #[argh(switch, long = "auto-balance")]
+    pub auto_balance: bool,
+
+    /// search for the smallest delay of the given event in the toplevel
+    /// component under which the component checks
+    #[argh(option, long = "min-delay")]
+    pub min_delay: Option<String>,
+
+    /// check the signatures of external components against the headers of
+    /// the Verilog modules that implement them
+    #[argh(switch, long = "check-externs")]
+    pub check_externs: bool,
+
     /// skip the discharge pass (unsafe)
     #[argh(switch, long = "unsafe-skip-discharge")]
     pub unsafe_skip_discharge: bool,
 
+    /// re-check the obligations of the program after monomorphization by
+    /// evaluating them without a solver
+    #[argh(switch, long = "recheck-concrete")]
+    pub recheck_concrete: bool,
+
     // `gen` options
-    /// the output directory to store files generated from external tools
+    /// the output directory to store files generated from external tools and
+    /// the harnesses generated by `filament test'
     #[argh(option, long = "out-dir")]
     pub out_dir: Option<PathBuf>,
 
@@ -100,10 +213,31 @@ pub struct Opts {
     /// preserves original port names during compilation.
     #[argh(switch, long = "preserve-names")]
     pub preserve_names: bool,
+    /// add simulation checks that report when the environment of the
+    /// toplevel component violates its timing contract. Bundles and ports
+    /// whose interval spans several events are not checked
+    #[argh(switch, long = "timing-checks")]
+    pub timing_checks: bool,
+    /// write a JSON map from the identifiers of the generated Verilog to
+    /// their source positions to the given file
+    #[argh(option, long = "emit-source-map")]
+    pub emit_source_map: Option<PathBuf>,
+    /// write each generated module to its own file in the given directory
+    /// along with a file list instead of printing the design
+    #[argh(option, long = "split-verilog")]
+    pub split_verilog: Option<PathBuf>,
+    /// scheme used to name monomorphized components (default: sequential):
+    /// sequential, readable, hashed
+    #[argh(option, long = "mangle", default = "Mangle::Sequential")]
+    pub mangle: Mangle,
+    /// write a JSON table from the names of monomorphized components to the
+    /// component and parameters they were generated from to the given file
+    #[argh(option, long = "mangle-map")]
+    pub mangle_map: Option<PathBuf>,
 
     // Solver specific configuration
-    /// solver to use (default: cvc5): cvc5, z3
-    #[argh(option, long = "solver", default = "Solver::Z3")]
+    /// solver to use (default: z3): cvc5, z3
+    #[argh(option, long = "solver", default = "Solver::default()")]
     pub solver: Solver,
     /// solve assertions separately rather than all at once
     #[argh(switch, long = "discharge-separate")]
@@ -111,7 +245,45 @@ pub struct Opts {
     /// dump interactions with the solver in the given file
     #[argh(option, long = "dump-solver-log")]
     pub solver_replay_file: Option<String>,
-    /// use bitvector encoding for proofs
+    /// write each obligation checked by the solver to the given directory
+    /// as a standalone SMT-LIB query
+    #[argh(option, long = "emit-obligations")]
+    pub emit_obligations: Option<PathBuf>,
+    /// use bitvector encoding for proofs with the given width or `auto' to
+    /// pick the width for each component
     #[argh(option, long = "solver-bv")]
-    pub solver_bv: Option<u8>,
+    pub solver_bv: Option<BvWidth>,
+}
+
+#[derive(FromArgs, Debug)]
+/// Check whether the components of a new version of a Filament library can
+/// replace the ones in the old version
+pub struct ApiDiffOpts {
+    /// the old version of the library
+    #[argh(positional)]
+    pub old: PathBuf,
+
+    /// the new version of the library
+    #[argh(positional)]
+    pub new: PathBuf,
+
+    /// set log level
+    #[argh(option, long = "log", default = "log::LevelFilter::Warn")]
+    pub log_level: log::LevelFilter,
+
+    /// solver to use (default: z3): cvc5, z3
+    #[argh(option, long = "solver", default = "Solver::default()")]
+    pub solver: Solver,
+}
+
+#[derive(FromArgs, Debug)]
+/// Generate skeleton extern blocks for the modules defined in Verilog files
+pub struct ImportSvOpts {
+    /// the Verilog files to import
+    #[argh(positional)]
+    pub files: Vec<PathBuf>,
+
+    /// only import the module with this name. Can be repeated.
+    #[argh(option, long = "module")]
+    pub modules: Vec<String>,
 }
@@ -1,6 +1,6 @@
 use crate::ir_visitor::{Action, Visitor, VisitorData};
 use fil_ast as ast;
-use fil_ir::{self as ir, AddCtx, Ctx, ExprIdx, PropIdx};
+use fil_ir::{self as ir, Ctx, ExprIdx, PropIdx};
 
 /// Generates default assumptions to the Filament program for assumptions using custom functions
 #[derive(Default)]
@@ -15,9 +15,9 @@ impl Assume {
         rhs: ExprIdx,
     ) -> Vec<PropIdx> {
         // Define constant expressions used
-        let zero = ctx.add(ir::Expr::Concrete(0));
-        let one = ctx.add(ir::Expr::Concrete(1));
-        let two = ctx.add(ir::Expr::Concrete(2));
+        let zero = ctx.num(0);
+        let one = ctx.num(1);
+        let two = ctx.num(2);
 
         match f {
             ast::Fn::Pow2 => vec![
@@ -0,0 +1,501 @@
+use crate::{
+    cmdline,
+    ir_visitor::{Action, Construct, Visitor, VisitorData},
+};
+use codespan_reporting::diagnostic::Diagnostic;
+use fil_ir::{self as ir, AddCtx, Ctx, DisplayCtx, MutCtx};
+use fil_utils::{GPosIdx, Id};
+use itertools::Itertools;
+use std::collections::HashSet;
+
+/// A time `'E+offset` relative to an event `'E` of a register primitive.
+type SigTime = (ir::EventIdx, u64);
+
+/// A port of a register primitive with a liveness of `[start, end]`.
+struct RegPort {
+    idx: ir::PortIdx,
+    name: Id,
+    start: SigTime,
+    end: SigTime,
+}
+
+/// The delay of an event of a register primitive.
+enum RegDelay {
+    /// A concrete number of cycles
+    Unit(u64),
+    /// The difference `l - r` between two times
+    Sym(SigTime, SigTime),
+}
+
+/// The signature of a register primitive from `primitives/state.fil`:
+/// ```text
+/// comp Delay[WIDTH]<'G: 1>(in: ['G, 'G+1] WIDTH) -> (out: ['G+1, 'G+2] WIDTH);
+/// comp Register[WIDTH]<'G: 'L-('G+1), 'L: 1>(
+///    write_en: interface['G], in: ['G, 'G+1] WIDTH,
+/// ) -> (out: ['G+1, 'L] WIDTH) where 'L > 'G+1;
+/// ```
+struct RegSig {
+    comp: ir::CompIdx,
+    events: Vec<(ir::EventIdx, RegDelay)>,
+    input: RegPort,
+    output: RegPort,
+}
+
+impl RegSig {
+    /// Find the primitive `name` in the context and check that it has a
+    /// single width parameter and `events` events, and that all its timing
+    /// is concrete. Its assertions are checked by the type checker like
+    /// those of any other invocation.
+    fn find(ctx: &ir::Context, name: &str, events: usize) -> Option<Self> {
+        let (idx, comp) = ctx.iter().find(|(_, c)| {
+            c.is_ext() && c.source_name().map_or(false, |n| n.as_ref() == name)
+        })?;
+
+        let &[width] = comp.param_args() else {
+            return None;
+        };
+        if comp.event_args().len() != events
+            || !comp.get_param_asserts().is_empty()
+        {
+            return None;
+        }
+        let time = |t: ir::TimeIdx| -> Option<SigTime> {
+            let ir::Time { event, offset } = comp.get(t);
+            Some((*event, offset.as_concrete(comp)?))
+        };
+        let events = comp
+            .event_args()
+            .iter()
+            .map(|&ev| {
+                let delay = match &comp.get(ev).delay {
+                    ir::TimeSub::Unit(e) => {
+                        RegDelay::Unit(e.as_concrete(comp)?)
+                    }
+                    ir::TimeSub::Sym { l, r } => {
+                        RegDelay::Sym(time(*l)?, time(*r)?)
+                    }
+                };
+                Some((ev, delay))
+            })
+            .collect::<Option<_>>()?;
+
+        let port = |(idx, p): (ir::PortIdx, &ir::Port)| -> Option<RegPort> {
+            let ir::Liveness { lens, range, .. } = &p.live;
+            if !p.width.is_param(comp, width)
+                || lens.len() != 1
+                || !lens[0].is_const(comp, 1)
+            {
+                return None;
+            }
+            Some(RegPort {
+                idx,
+                name: p.info.get_name(comp)?.into(),
+                start: time(range.start)?,
+                end: time(range.end)?,
+            })
+        };
+
+        let mut inputs = comp.inputs();
+        let mut outputs = comp.outputs();
+        let (Some(input), None, Some(output), None) =
+            (inputs.next(), inputs.next(), outputs.next(), outputs.next())
+        else {
+            return None;
+        };
+
+        Some(RegSig {
+            comp: idx,
+            events,
+            input: port(input)?,
+            output: port(output)?,
+        })
+    }
+
+    /// The offset from the scheduling event of a time in the signature when
+    /// the events of the primitive are bound to `times`.
+    fn resolve(&self, (ev, off): SigTime, times: &[u64]) -> u64 {
+        let idx = self.events.iter().position(|(e, _)| *e == ev).unwrap();
+        times[idx] + off
+    }
+
+    /// The delays of the events of the primitive when they are bound to
+    /// `times`. Returns `None` if a delay would be negative.
+    fn delays(&self, times: &[u64]) -> Option<Vec<u64>> {
+        self.events
+            .iter()
+            .map(|(_, delay)| match delay {
+                RegDelay::Unit(d) => Some(*d),
+                RegDelay::Sym(l, r) => {
+                    self.resolve(*l, times).checked_sub(self.resolve(*r, times))
+                }
+            })
+            .collect()
+    }
+
+    /// The liveness of a port when the events are bound to `times`.
+    fn live(&self, port: &RegPort, times: &[u64]) -> (u64, u64) {
+        (
+            self.resolve(port.start, times),
+            self.resolve(port.end, times),
+        )
+    }
+}
+
+/// Repairs connections that fail only because the source is live too early by
+/// inserting a chain of registers between the source and the destination.
+/// A connection is repaired when:
+/// * Both sides are simple ports whose liveness is concrete and relative to the
+///   same event.
+/// * The source becomes live no later than the destination requires it but
+///   stops being live before it.
+///
+/// The value is sampled in the last cycle the source is live so that the chain
+/// uses the fewest possible registers. The chain is made of `Delay`s, which
+/// provide their output for one cycle. If the destination requires the value
+/// for more than one cycle, the last stage is a `Register` that holds the
+/// value until the end of the destination's liveness.
+/// The inserted invocations are checked by the subsequent passes like any
+/// other invocation in the program.
+pub struct AutoBalance {
+    /// The signature of the `Delay` primitive
+    delay: Option<RegSig>,
+    /// The signature of the `Register` primitive
+    register: Option<RegSig>,
+    /// Names of the instances and invocations in the current component
+    names: HashSet<Id>,
+    /// Total number of registers inserted
+    registers: u64,
+    /// Total number of bits of state inserted. `None` if any of the inserted
+    /// registers has a width that is not concrete.
+    bits: Option<u64>,
+    /// Notes describing the inserted registers
+    notes: Vec<Diagnostic<usize>>,
+}
+
+impl Construct for AutoBalance {
+    fn from(_: &cmdline::Opts, ctx: &mut ir::Context) -> Self {
+        let delay = RegSig::find(ctx, "Delay", 1);
+        if delay.is_none() {
+            log::warn!(concat!(
+                "--auto-balance requires the `Delay' primitive from ",
+                "`primitives/state.fil'. No registers will be inserted."
+            ));
+        }
+        AutoBalance {
+            delay,
+            register: RegSig::find(ctx, "Register", 2),
+            names: HashSet::new(),
+            registers: 0,
+            bits: Some(0),
+            notes: vec![],
+        }
+    }
+
+    fn clear_data(&mut self) {
+        /* Inserted registers are reported after the traversal */
+        self.names.clear();
+    }
+}
+
+impl AutoBalance {
+    /// Concrete liveness of an access as `(event, start, end)`.
+    fn concrete_live(
+        access: &ir::Access,
+        comp: &mut ir::Component,
+    ) -> Option<(ir::EventIdx, u64, u64)> {
+        let ir::Range { start, end } = access.bundle_typ(comp).range;
+        let start = comp.get(start).clone();
+        let end = comp.get(end).clone();
+        if start.event != end.event {
+            return None;
+        }
+        Some((
+            start.event,
+            start.offset.as_concrete(comp)?,
+            end.offset.as_concrete(comp)?,
+        ))
+    }
+
+    /// A name for an inserted register that is not used by any instance or
+    /// invocation in the component.
+    fn fresh_name(&mut self) -> Id {
+        let name = (self.registers..)
+            .map(|i| Id::new(format!("balance{i}")))
+            .find(|n| !self.names.contains(n))
+            .unwrap();
+        self.names.insert(name);
+        name
+    }
+
+    /// Define a port on the invocation of a register whose events are bound
+    /// to `'G+times`.
+    fn port(
+        comp: &mut ir::Component,
+        owner: ir::PortOwner,
+        sig: &RegSig,
+        port: &RegPort,
+        width: ir::ExprIdx,
+        (event, times): (ir::EventIdx, &[u64]),
+        pos: GPosIdx,
+    ) -> ir::PortIdx {
+        let info = comp.add(ir::Info::port(port.name, pos, pos, pos));
+        let p_info = comp.add(ir::Info::param(Id::new("_"), pos));
+        // Updated after the port is constructed
+        let idx = comp.add(ir::Param::new(
+            ir::ParamOwner::bundle(ir::PortIdx::UNKNOWN),
+            p_info,
+        ));
+        let mut time_at = |t: SigTime| {
+            let offset = comp.num(sig.resolve(t, times));
+            comp.add(ir::Time { event, offset })
+        };
+        let range = ir::Range {
+            start: time_at(port.start),
+            end: time_at(port.end),
+        };
+        let live = ir::Liveness {
+            idxs: vec![idx],
+            lens: vec![comp.num(1)],
+            range,
+        };
+        let pidx = comp.add(ir::Port {
+            owner,
+            width,
+            live,
+            info,
+        });
+        comp.get_mut(idx).owner = ir::ParamOwner::bundle(pidx);
+        pidx
+    }
+
+    /// Instantiate and invoke a register with its events bound to
+    /// `'G+times`. Returns the commands that define it along with its input
+    /// and output ports.
+    fn register(
+        sig: &RegSig,
+        name: Id,
+        comp: &mut ir::Component,
+        width: ir::ExprIdx,
+        (event, times): (ir::EventIdx, &[u64]),
+        pos: GPosIdx,
+    ) -> (Vec<ir::Command>, ir::PortIdx, ir::PortIdx) {
+        // Like an instance written without a liveness, the register may be
+        // invoked at any time and is not checked for resource conflicts.
+        // Each inserted register is invoked exactly once.
+        let info = comp.add(ir::Info::instance(name, pos, pos, vec![]));
+        let inst = comp.add(ir::Instance {
+            comp: sig.comp,
+            args: vec![width].into_boxed_slice(),
+            lives: vec![],
+            params: vec![],
+            info,
+        });
+
+        let binds = vec![pos; sig.events.len()];
+        let info = comp.add(ir::Info::invoke(name, pos, pos, binds));
+        let inv = comp.add(ir::Invoke {
+            inst,
+            events: vec![],
+            ports: vec![],
+            info,
+        });
+
+        let base = ir::Foreign::new(sig.output.idx, sig.comp);
+        let out = Self::port(
+            comp,
+            ir::PortOwner::inv_out(inv, base),
+            sig,
+            &sig.output,
+            width,
+            (event, times),
+            pos,
+        );
+        let base = ir::Foreign::new(sig.input.idx, sig.comp);
+        let input = Self::port(
+            comp,
+            ir::PortOwner::inv_in(inv, base),
+            sig,
+            &sig.input,
+            width,
+            (event, times),
+            pos,
+        );
+
+        // The delays are checked by the caller
+        let delays = sig.delays(times).unwrap();
+        let mut events = Vec::with_capacity(sig.events.len());
+        for (((ev, _), time), delay) in sig.events.iter().zip(times).zip(delays)
+        {
+            let delay = ir::TimeSub::Unit(comp.num(delay));
+            let offset = comp.num(*time);
+            let arg = comp.add(ir::Time { event, offset });
+            let info = comp.add(ir::Info::event_bind(pos, pos));
+            let base = ir::Foreign::new(*ev, sig.comp);
+            events.push(ir::EventBind::new(delay, arg, info, base));
+        }
+        let invoke = comp.get_mut(inv);
+        invoke.ports.extend([out, input]);
+        invoke.events = events;
+
+        (vec![inst.into(), inv.into()], input, out)
+    }
+}
+
+impl Visitor for AutoBalance {
+    fn name() -> &'static str {
+        "auto-balance"
+    }
+
+    fn start(&mut self, data: &mut VisitorData) -> Action {
+        let comp = &data.comp;
+        if self.delay.is_none() || comp.is_ext() {
+            return Action::Stop;
+        }
+        let insts = comp.instances().iter().map(|(_, i)| i.info);
+        let invs = comp.invocations().iter().map(|(_, i)| i.info);
+        self.names = insts
+            .chain(invs)
+            .filter_map(|info| info.get_name(comp))
+            .map(Id::new)
+            .collect();
+        Action::Continue
+    }
+
+    fn connect(
+        &mut self,
+        con: &mut ir::Connect,
+        data: &mut VisitorData,
+    ) -> Action {
+        let Some(delay) = &self.delay else {
+            return Action::Continue;
+        };
+        let comp = &mut data.comp;
+        if !con.src.is_port(comp) || !con.dst.is_port(comp) {
+            return Action::Continue;
+        }
+        let (Some((event, start, end)), Some((dst_event, dst_start, dst_end))) = (
+            Self::concrete_live(&con.src, comp),
+            Self::concrete_live(&con.dst, comp),
+        ) else {
+            return Action::Continue;
+        };
+        // The source must stop being live before the destination requires it
+        if event != dst_event
+            || start >= end
+            || start > dst_start
+            || end > dst_start
+            || dst_end <= dst_start
+        {
+            return Action::Continue;
+        }
+
+        // Sample the value in the last cycle it is live. The register
+        // scheduled at `'G+t` provides its output at `'G+t+1`. If the
+        // destination requires the value for more than one cycle, the last
+        // stage holds it until `dst_end`.
+        let sample = end - 1;
+        let count = dst_start - sample;
+        let hold = dst_end > dst_start + 1;
+        let stages = (0..count)
+            .map(|i| {
+                if hold && i == count - 1 {
+                    (true, vec![sample + i, dst_end])
+                } else {
+                    (false, vec![sample + i])
+                }
+            })
+            .collect_vec();
+        let sig_of = |is_reg: bool| {
+            if is_reg {
+                self.register.as_ref()
+            } else {
+                Some(delay)
+            }
+        };
+        // Check that each stage reads its input while the previous one is
+        // live and that the last one provides the value the destination
+        // requires.
+        let mut live = (start, end);
+        for (is_reg, times) in &stages {
+            let Some(sig) = sig_of(*is_reg) else {
+                return Action::Continue;
+            };
+            let (in_start, in_end) = sig.live(&sig.input, times);
+            if sig.delays(times).is_none()
+                || in_start < live.0
+                || live.1 < in_end
+            {
+                return Action::Continue;
+            }
+            live = sig.live(&sig.output, times);
+        }
+        if dst_start < live.0 || live.1 < dst_end {
+            return Action::Continue;
+        }
+
+        let width = comp.get(con.src.port).width;
+        let &ir::info::Connect { src_loc, .. } = comp.get(con.info).into();
+        let src_range = con.src.bundle_typ(comp).range;
+        let src_live = comp.display(&src_range);
+        let dst_range = con.dst.bundle_typ(comp).range;
+        let dst_live = comp.display(&dst_range);
+
+        let names = stages.iter().map(|_| self.fresh_name()).collect_vec();
+        let mut cmds = Vec::with_capacity(3 * count as usize + 1);
+        let mut src = con.src.clone();
+        for ((is_reg, times), name) in stages.iter().zip(names) {
+            let sig = if *is_reg {
+                self.register.as_ref().unwrap()
+            } else {
+                self.delay.as_ref().unwrap()
+            };
+            let (defs, input, out) =
+                Self::register(sig, name, comp, width, (event, times), src_loc);
+            cmds.extend(defs);
+            let info = comp.add(ir::Info::connect(src_loc, src_loc));
+            let dst = ir::Access::port(input, comp);
+            cmds.push(ir::Connect { src, dst, info }.into());
+            src = ir::Access::port(out, comp);
+        }
+        con.src = src;
+        cmds.push(con.clone().into());
+
+        // Track the cost of the inserted registers
+        self.registers += count;
+        self.bits = self
+            .bits
+            .zip(width.as_concrete(comp))
+            .map(|(bits, w)| bits + w * count);
+        let msg = format!(
+            "inserted {count} register(s) of width {} to delay {src_live} to {dst_live}",
+            comp.display(width),
+        );
+        let note = Diagnostic::note().with_message(msg);
+        self.notes.push(if let Some(pos) = src_loc.into_option() {
+            note.with_labels(vec![pos
+                .primary()
+                .with_message(format!("delayed by {count} cycle(s)"))])
+        } else {
+            note
+        });
+
+        Action::Change(cmds)
+    }
+
+    fn after_traversal(&mut self) -> Option<u64> {
+        if self.registers == 0 {
+            return None;
+        }
+        let bits = self
+            .bits
+            .map(|b| format!(" ({b} bits of state)"))
+            .unwrap_or_default();
+        self.notes.push(Diagnostic::note().with_message(format!(
+            "auto-balance inserted {} register(s){bits}",
+            self.registers
+        )));
+
+        fil_utils::report_notes(&self.notes);
+        None
+    }
+}
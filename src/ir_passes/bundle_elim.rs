@@ -4,7 +4,7 @@ use crate::{
 };
 use fil_ir::{
     self as ir, Access, AddCtx, Bind, Command, Component, Connect, Ctx,
-    DenseIndexInfo, DisplayCtx, Expr, Foreign, Info, InvIdx, Invoke, Liveness,
+    DenseIndexInfo, DisplayCtx, Foreign, Info, InvIdx, Invoke, Liveness,
     MutCtx, Port, PortIdx, PortOwner, Range, Subst, Time,
 };
 use fil_utils as utils;
@@ -63,7 +63,7 @@ impl BundleElim {
 
     /// Compiles a port by breaking it into multiple len-1 ports.
     fn port(&self, pidx: PortIdx, comp: &mut Component) -> PortInfo {
-        let one = comp.add(Expr::Concrete(1));
+        let one = comp.num(1);
         let Port {
             owner,
             width,
@@ -98,9 +98,10 @@ impl BundleElim {
         let ports = (0..len)
             .map(|i| {
                 let binding = Bind::new(
-                    utils::nd_idx(i, &lens).into_iter().zip_eq(&idxs).map(
-                        |(v, idx)| (*idx, comp.add(Expr::Concrete(v as u64))),
-                    ),
+                    utils::nd_idx(i, &lens)
+                        .into_iter()
+                        .zip_eq(&idxs)
+                        .map(|(v, idx)| (*idx, comp.num(v as u64))),
                 );
 
                 // calculates the offsets based on this binding and generates new start and end times.
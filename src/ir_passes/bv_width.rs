@@ -0,0 +1,206 @@
+use fil_ast as ast;
+use fil_ir::{self as ir, Ctx, DisplayCtx};
+use fil_utils::{BigUint, BigUintExt, ToPrimitive, Zero};
+use std::collections::HashMap;
+
+/// Largest exponent for which the bound of `pow2` is computed
+const MAX_POW2: u64 = 1 << 10;
+
+/// Computes upper bounds on the magnitude of every expression in a component
+/// using its concrete constants and the declared ranges of its parameters.
+/// Used to pick the smallest bitvector width that can represent all the
+/// values in a component.
+pub(super) struct Bounds<'a> {
+    comp: &'a ir::Component,
+    /// Expressions bounding parameters defined by commands in the component
+    defs: HashMap<ir::ParamIdx, Vec<ir::ExprIdx>>,
+    /// Bounds of expressions that have already been computed
+    memo: HashMap<ir::ExprIdx, BigUint>,
+}
+
+impl<'a> Bounds<'a> {
+    pub fn new(comp: &'a ir::Component) -> Self {
+        let mut bounds = Self {
+            comp,
+            defs: HashMap::new(),
+            memo: HashMap::new(),
+        };
+        bounds.cmds(&comp.cmds);
+        bounds
+    }
+
+    /// Record the expressions defining parameters bound by the commands
+    fn cmds(&mut self, cmds: &[ir::Command]) {
+        for cmd in cmds {
+            match cmd {
+                ir::Command::Let(ir::Let { param, expr }) => {
+                    self.defs.entry(*param).or_default().push(*expr);
+                }
+                ir::Command::Exists(ir::Exists { param, expr }) => {
+                    self.defs.entry(*param).or_default().push(*expr);
+                }
+                ir::Command::ForLoop(ir::Loop {
+                    index,
+                    start,
+                    end,
+                    body,
+                }) => {
+                    self.defs.entry(*index).or_default().extend([*start, *end]);
+                    self.cmds(body);
+                }
+                ir::Command::If(ir::If { then, alt, .. }) => {
+                    self.cmds(then);
+                    self.cmds(alt);
+                }
+                ir::Command::Instance(_)
+                | ir::Command::Invoke(_)
+                | ir::Command::BundleDef(_)
+                | ir::Command::Connect(_)
+                | ir::Command::Fact(_) => (),
+            }
+        }
+    }
+
+    /// Number of bits required to represent every expression in the
+    /// component. If `signed` is true, an extra bit is reserved for the sign.
+    /// Returns a description of the first value that cannot be bounded
+    /// otherwise.
+    pub fn width(&mut self, signed: bool) -> Result<u32, String> {
+        let comp = self.comp;
+        let mut max = BigUint::zero();
+        for (idx, _) in comp.exprs().iter() {
+            max = max.max(self.expr(idx)?);
+        }
+
+        // Events are unbounded but obligations only relate them through the
+        // offsets of times and the delays of events. Any assignment to the
+        // events can therefore be shifted so that every event lies within
+        // the sum of those values, and each time within that sum plus its
+        // own offset.
+        let mut span = BigUint::zero();
+        for (_, ir::Time { offset, .. }) in comp.times().iter() {
+            span = &span + &self.expr(*offset)?;
+        }
+        for (_, ev) in comp.events().iter() {
+            if let ir::TimeSub::Unit(delay) = &ev.delay {
+                span = &span + &self.expr(*delay)?;
+            }
+        }
+        for (_, ir::Time { offset, .. }) in comp.times().iter() {
+            max = max.max(&span + &self.expr(*offset)?);
+        }
+        Ok((max.bits() as u32).max(1) + signed as u32)
+    }
+
+    /// Number of bits required to represent the declared ranges of the
+    /// signature parameters. Ranges that cannot be bounded are ignored.
+    pub fn range_width(&mut self, signed: bool) -> u32 {
+        let comp = self.comp;
+        comp.param_args()
+            .iter()
+            .filter(|p| !comp.get(**p).ranges.is_empty())
+            .filter_map(|p| self.param(*p).ok())
+            .map(|b| b.bits() as u32 + signed as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn param(&mut self, p: ir::ParamIdx) -> Result<BigUint, String> {
+        let comp = self.comp;
+        let param = comp.get(p);
+        let unbounded =
+            || format!("parameter `{}' has no upper bound", comp.display(p));
+        match &param.owner {
+            ir::ParamOwner::Sig => {
+                // The parameter lies within all of its declared ranges
+                let mut bound: Option<BigUint> = None;
+                for range in &param.ranges {
+                    let ir::ParamRange::Bounded { lo, hi } = range else {
+                        continue;
+                    };
+                    let (Ok(lo), Ok(hi)) = (self.expr(*lo), self.expr(*hi))
+                    else {
+                        continue;
+                    };
+                    let b = lo.max(hi);
+                    bound = Some(bound.map_or(b.clone(), |c| c.min(b)));
+                }
+                bound.ok_or_else(|| {
+                    if param.ranges.contains(&ir::ParamRange::Pow2) {
+                        format!(
+                            "parameter `{}' is a power of two without an upper bound",
+                            comp.display(p)
+                        )
+                    } else {
+                        unbounded()
+                    }
+                })
+            }
+            ir::ParamOwner::Bundle(port) => {
+                // Bundle indices are smaller than the length of the bundle
+                let live = &comp.get(*port).live;
+                let Some(pos) = live.idxs.iter().position(|i| *i == p) else {
+                    return Err(unbounded());
+                };
+                self.expr(live.lens[pos])
+            }
+            ir::ParamOwner::Let { .. }
+            | ir::ParamOwner::Loop
+            | ir::ParamOwner::Exists { .. } => {
+                let Some(defs) = self.defs.get(&p).cloned() else {
+                    return Err(unbounded());
+                };
+                let mut max = BigUint::zero();
+                for e in defs {
+                    max = max.max(self.expr(e)?);
+                }
+                Ok(max)
+            }
+            ir::ParamOwner::Instance { .. } => Err(unbounded()),
+        }
+    }
+
+    fn expr(&mut self, e: ir::ExprIdx) -> Result<BigUint, String> {
+        if let Some(b) = self.memo.get(&e) {
+            return Ok(b.clone());
+        }
+        let bound = match self.comp.get(e) {
+            ir::Expr::Param(p) => self.param(*p)?,
+            ir::Expr::Concrete(n) => n.magnitude().clone(),
+            ir::Expr::Bin { op, lhs, rhs } => {
+                let l = self.expr(*lhs)?;
+                let r = self.expr(*rhs)?;
+                match op {
+                    // Bounds the magnitude of a difference. Negative values
+                    // use the sign bit reserved for components that subtract.
+                    ast::Op::Add | ast::Op::Sub => &l + &r,
+                    ast::Op::Mul => &l * &r,
+                    ast::Op::Div => l,
+                    // The remainder is smaller than the divisor. The bound of
+                    // the dividend is kept so that it holds however negative
+                    // operands are rounded.
+                    ast::Op::Mod => l.max(r),
+                }
+            }
+            ir::Expr::Fn { op, args } => {
+                let args = args
+                    .iter()
+                    .map(|a| self.expr(*a))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let exp = |n: &BigUint| {
+                    n.to_u64().filter(|n| *n <= MAX_POW2).map(BigUint::pow2)
+                };
+                let bound = match (op, &*args) {
+                    (ast::Fn::Pow2, [n]) | (ast::Fn::BitRev, [_, n]) => exp(n),
+                    (ast::Fn::Log2, [n]) => Some(n.bits().into()),
+                    _ => None,
+                };
+                bound.ok_or_else(|| {
+                    format!("`{}' cannot be bounded", self.comp.display(e))
+                })?
+            }
+        };
+        self.memo.insert(e, bound.clone());
+        Ok(bound)
+    }
+}
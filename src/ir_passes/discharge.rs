@@ -1,18 +1,28 @@
+use super::bv_width::Bounds;
+use super::linear::Linear;
 use crate::cmdline;
 use crate::ir_visitor::{Action, Construct, Visitor, VisitorData};
 use crate::log_time;
 use crate::utils::HoistFacts;
 use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::files::Files;
 use codespan_reporting::{diagnostic as cr, term};
 use easy_smt as smt;
 use fil_ast as ast;
 use fil_ir::{self as ir, Ctx, DisplayCtx};
-use fil_utils::GlobalPositionTable;
+use fil_utils::{
+    BigInt, BigUint, BigUintExt, Diagnostics, Error, GlobalPositionTable,
+    Signed, TimingDiagram, Zero,
+};
 use itertools::Itertools;
 use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::{fs, iter};
 use term::termcolor::{ColorChoice, StandardStream};
 
+mod repl;
+
 #[derive(Default)]
 struct Assign(Vec<(ir::ParamIdx, String)>);
 
@@ -35,20 +45,63 @@ impl Assign {
     }
 }
 
+#[derive(Default)]
+/// Counterexample for a failing proposition. It is shared by all the facts
+/// that check the same proposition.
+struct Model {
+    /// Bindings for the parameters mentioned in the proposition
+    assign: Assign,
+    /// Values of the events, times, and expressions of the component that are
+    /// natural numbers
+    values: HashMap<smt::SExpr, u64>,
+}
+
+impl Model {
+    /// Values of the given expressions in the model.
+    /// Returns `None` if any of them is not a natural number.
+    fn values(&self, sexps: Vec<smt::SExpr>) -> Option<Vec<u64>> {
+        sexps.iter().map(|s| self.values.get(s).copied()).collect()
+    }
+}
+
 /// Pass to discharge top-level `assert` statements in the IR and turn them into
 /// `assume` if they are true. Any assertions within the body are left as-is.
 /// Run [super::HoistFacts] before this pass to ensure that all facts are
 /// top-level.
 pub struct Discharge {
+    /// The solver is only started once an obligation cannot be proved by
+    /// the built-in decision procedure. Until then, this context is not
+    /// connected to a solver.
     sol: smt::Context,
-    /// Are we using a bitvector encoding
-    bv_size: Option<u8>,
+    sol_started: bool,
+    /// File to log solver interactions to
+    sol_replay: Option<String>,
+    /// Directory to write each obligation to as a standalone query
+    emit_dir: Option<PathBuf>,
+    /// Commands defining the encoding of the current component. Only
+    /// recorded when emitting obligations.
+    preamble: Vec<smt::SExpr>,
+    /// Obligations of the current component to be emitted, including the
+    /// ones proved without the solver
+    to_emit: Vec<ir::Fact>,
+    /// Bitvector width requested on the command line
+    bv_opt: Option<cmdline::BvWidth>,
+    /// Bitvector width used for the current component, if we are using a
+    /// bitvector encoding
+    bv_size: Option<u32>,
+    /// Does the current component use signed parameters or negative
+    /// constants. Under the bitvector encoding, such components use signed
+    /// bitvector operations.
+    signed: bool,
     /// Which solver are we using
     sol_base: cmdline::Solver,
     /// Defined global functions
     func_map: HashMap<ast::Fn, smt::SExpr>,
     /// Defined functions for `some` parameters on components
     comp_param_map: HashMap<ir::Foreign<ir::Param, ir::Component>, smt::SExpr>,
+    /// Non-opaque `some` parameters along with the number of parameters of
+    /// their component
+    exist_params: Vec<(ir::Foreign<ir::Param, ir::Component>, usize)>,
 
     // Defined names
     param_map: ir::DenseIndexInfo<ir::Param, smt::SExpr>,
@@ -58,27 +111,51 @@ pub struct Discharge {
     time_map: ir::DenseIndexInfo<ir::Time, smt::SExpr>,
     // Propositions
     prop_map: ir::DenseIndexInfo<ir::Prop, smt::SExpr>,
-    // Propositions that have already been checked
-    checked: HashMap<ir::PropIdx, Option<Assign>>,
+    // Propositions that have already been checked along with the
+    // counterexample computed when they failed.
+    checked: HashMap<ir::PropIdx, Option<Model>>,
 
     // counter for activation literals generated
     act_lit_count: u32,
 
     /// Report the unsatisfied constraint and generate a model
     show_models: bool,
+    /// Explore the failing obligations interactively
+    debug_proofs: bool,
+    /// Obligations of the current component that could not be proved
+    failed: Vec<ir::Fact>,
 
     to_prove: Vec<ir::Fact>,
 
     // Diagnostics to be reported
     diagnostics: Vec<cr::Diagnostic<usize>>,
+    /// Encodings picked for each component with `--solver-bv auto`
+    encodings: Vec<cr::Diagnostic<usize>>,
+    /// Count failing assertions without reporting them
+    quiet: bool,
     /// Number of errors encountered
     error_count: u64,
+    /// Number of obligations proved without the solver
+    builtin_count: u64,
 }
 
 impl Discharge {
+    /// Only count the failing assertions instead of reporting them.
+    /// Obligations are not emitted or debugged either since a quiet run is
+    /// only used to probe whether the program checks.
+    pub(super) fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self.emit_dir = None;
+        self.debug_proofs = false;
+        self
+    }
+
     /// Configure solver to use in this pass
-    fn conf_solver(opts: &cmdline::Opts) -> smt::Context {
-        let (name, s_opts) = match opts.solver {
+    pub(crate) fn conf_solver(
+        solver: cmdline::Solver,
+        replay: Option<&String>,
+    ) -> smt::Context {
+        let (name, s_opts) = match solver {
             cmdline::Solver::Z3 => {
                 log::debug!("Using z3 solver");
                 ("z3", vec!["-smt2", "-in"])
@@ -97,16 +174,88 @@ impl Discharge {
             }
         };
         smt::ContextBuilder::new()
-            .replay_file(
-                opts.solver_replay_file
-                    .as_ref()
-                    .map(|s| fs::File::create(s).unwrap()),
-            )
+            .replay_file(replay.map(|s| fs::File::create(s).unwrap()))
             .solver(name, s_opts)
             .build()
             .unwrap()
     }
 
+    /// Start the solver if it is not running yet
+    fn start_solver(&mut self) {
+        if self.sol_started {
+            return;
+        }
+        self.sol = Self::conf_solver(self.sol_base, self.sol_replay.as_ref());
+        self.sol.push_many(1).unwrap();
+        self.sol_started = true;
+    }
+
+    /// Record a command that is part of the encoding of the current
+    /// component if we are emitting obligations.
+    fn record(&mut self, cmd: Vec<smt::SExpr>) {
+        if self.emit_dir.is_some() {
+            let cmd = self.sol.list(cmd);
+            self.preamble.push(cmd);
+        }
+    }
+
+    /// Declare a function. The declaration is only sent to the solver if it
+    /// is running.
+    fn declare(
+        &mut self,
+        name: String,
+        args: Vec<smt::SExpr>,
+        out: smt::SExpr,
+    ) -> smt::SExpr {
+        let atom = self.sol.atom(&name);
+        let cmd = vec![
+            self.sol.atom("declare-fun"),
+            atom,
+            self.sol.list(args.clone()),
+            out,
+        ];
+        self.record(cmd);
+        if self.sol_started {
+            self.sol.declare_fun(name, args, out).unwrap()
+        } else {
+            atom
+        }
+    }
+
+    /// Define a constant. The definition is only sent to the solver if it is
+    /// running.
+    fn define(
+        &mut self,
+        name: String,
+        out: smt::SExpr,
+        body: smt::SExpr,
+    ) -> smt::SExpr {
+        let atom = self.sol.atom(&name);
+        let cmd = vec![
+            self.sol.atom("define-fun"),
+            atom,
+            self.sol.list(vec![]),
+            out,
+            body,
+        ];
+        self.record(cmd);
+        if self.sol_started {
+            self.sol.define_const(name, out, body).unwrap()
+        } else {
+            atom
+        }
+    }
+
+    /// Assume that the proposition holds in the current component. The
+    /// assertion is only sent to the solver if it is running.
+    fn assume(&mut self, e: smt::SExpr) {
+        let cmd = vec![self.sol.atom("assert"), e];
+        self.record(cmd);
+        if self.sol_started {
+            self.sol.assert(e).unwrap();
+        }
+    }
+
     fn app(&mut self, f: smt::SExpr, args: Vec<smt::SExpr>) -> smt::SExpr {
         if args.is_empty() {
             f
@@ -119,7 +268,7 @@ impl Discharge {
     /// When using bv encoding, we return twice the number of bits provided on
     /// the command line so that we can encode overflow checks.
     #[inline]
-    fn bv_size(&self) -> Option<u8> {
+    fn bv_size(&self) -> Option<u32> {
         self.bv_size.map(|v| v * 2)
     }
 
@@ -130,11 +279,21 @@ impl Discharge {
             self.sol.int_sort()
         }
     }
-    fn num(&self, n: u64) -> smt::SExpr {
+    fn num(&self, n: &BigInt) -> smt::SExpr {
+        let mag = n.magnitude();
         if let Some(v) = self.bv_size() {
-            self.sol.binary(v as usize, n)
+            let width = v as usize;
+            // Negative numbers use their two's complement representation
+            let bits = if n.is_negative() {
+                BigUint::pow2(v as u64) - mag
+            } else {
+                mag.clone()
+            };
+            self.sol.atom(format!("#b{bits:0>width$b}"))
+        } else if n.is_negative() {
+            self.sol.negate(self.sol.atom(mag.to_string()))
         } else {
-            self.sol.numeral(n)
+            self.sol.atom(mag.to_string())
         }
     }
     fn plus(&self, l: smt::SExpr, r: smt::SExpr) -> smt::SExpr {
@@ -158,29 +317,60 @@ impl Discharge {
             self.sol.times(l, r)
         }
     }
+    /// Is the truncated remainder of `l / r` negative? `bvsdiv` and `bvsrem`
+    /// round towards zero while the integer encoding and the evaluator use
+    /// Euclidean division, whose remainder is never negative. The results
+    /// are corrected when the truncated remainder is negative.
+    fn neg_rem(&self, l: smt::SExpr, r: smt::SExpr) -> smt::SExpr {
+        let zero = self.num(&BigInt::zero());
+        self.sol.bvslt(self.sol.bvsrem(l, r), zero)
+    }
     fn div(&self, l: smt::SExpr, r: smt::SExpr) -> smt::SExpr {
-        if self.bv_size.is_some() {
+        if self.bv_size.is_some() && self.signed {
+            let q = self.sol.bvsdiv(l, r);
+            let one = self.num(&1u64.into());
+            let zero = self.num(&BigInt::zero());
+            let fix = self.sol.ite(
+                self.sol.bvsgt(r, zero),
+                self.sol.bvsub(q, one),
+                self.sol.bvadd(q, one),
+            );
+            self.sol.ite(self.neg_rem(l, r), fix, q)
+        } else if self.bv_size.is_some() {
             self.sol.bvudiv(l, r)
         } else {
             self.sol.div(l, r)
         }
     }
     fn modulo(&self, l: smt::SExpr, r: smt::SExpr) -> smt::SExpr {
-        if self.bv_size.is_some() {
+        if self.bv_size.is_some() && self.signed {
+            let rem = self.sol.bvsrem(l, r);
+            let zero = self.num(&BigInt::zero());
+            let fix = self.sol.ite(
+                self.sol.bvsgt(r, zero),
+                self.sol.bvadd(rem, r),
+                self.sol.bvsub(rem, r),
+            );
+            self.sol.ite(self.neg_rem(l, r), fix, rem)
+        } else if self.bv_size.is_some() {
             self.sol.bvurem(l, r)
         } else {
             self.sol.modulo(l, r)
         }
     }
     fn gt(&self, l: smt::SExpr, r: smt::SExpr) -> smt::SExpr {
-        if self.bv_size.is_some() {
+        if self.bv_size.is_some() && self.signed {
+            self.sol.bvsgt(l, r)
+        } else if self.bv_size.is_some() {
             self.sol.bvugt(l, r)
         } else {
             self.sol.gt(l, r)
         }
     }
     fn gte(&self, l: smt::SExpr, r: smt::SExpr) -> smt::SExpr {
-        if self.bv_size.is_some() {
+        if self.bv_size.is_some() && self.signed {
+            self.sol.bvsge(l, r)
+        } else if self.bv_size.is_some() {
             self.sol.bvuge(l, r)
         } else {
             self.sol.gte(l, r)
@@ -191,29 +381,58 @@ impl Discharge {
     }
     /// Assert that the expression is not overflowing
     /// e >= 0 && e < 2^bvsize
+    /// For signed components: e >= -2^(bvsize-1) && e < 2^(bvsize-1)
     fn overflow_assert(&mut self, e: smt::SExpr) {
         let Some(v) = self.bv_size else {
             return;
         };
-        let max = self.num((1 << v) - 1);
-        let zero = self.num(0);
-        let ge_zero = self.gte(e, zero);
+        let (min, max) = if self.signed {
+            let half: BigInt = BigUint::pow2(v as u64 - 1).into();
+            (-half.clone(), half)
+        } else {
+            (BigInt::zero(), BigUint::pow2(v as u64).into())
+        };
+        let max = self.num(&(max - 1u64));
+        let min = self.num(&min);
+        let ge_min = self.gte(e, min);
         let lt_max = self.gt(max, e);
-        let and = self.sol.and(ge_zero, lt_max);
-        self.sol.assert(and).unwrap();
+        let and = self.sol.and(ge_min, lt_max);
+        self.assume(and);
+    }
+
+    /// Assert that the expression is not negative. Only required for signed
+    /// components since the unsigned encoding cannot represent negative
+    /// values.
+    fn natural_assert(&mut self, e: smt::SExpr) {
+        if self.bv_size.is_none() || !self.signed {
+            return;
+        }
+        let zero = self.num(&BigInt::zero());
+        let ge_zero = self.gte(e, zero);
+        self.assume(ge_zero);
     }
 }
 
 impl Construct for Discharge {
     fn from(opts: &cmdline::Opts, ctx: &mut ir::Context) -> Self {
         let mut out = Self {
-            bv_size: opts.solver_bv,
-            sol: Self::conf_solver(opts),
+            bv_opt: opts.solver_bv,
+            bv_size: None,
+            signed: false,
+            sol: smt::ContextBuilder::new().build().unwrap(),
+            sol_started: false,
+            sol_replay: opts.solver_replay_file.clone(),
+            emit_dir: opts.emit_obligations.clone(),
+            preamble: vec![],
+            to_emit: vec![],
             sol_base: opts.solver,
             error_count: 0,
+            builtin_count: 0,
             act_lit_count: 0,
             to_prove: vec![],
             show_models: opts.show_models,
+            debug_proofs: opts.debug_proofs,
+            failed: vec![],
             func_map: Default::default(),
             param_map: Default::default(),
             prop_map: Default::default(),
@@ -222,13 +441,12 @@ impl Construct for Discharge {
             expr_map: Default::default(),
             checked: Default::default(),
             diagnostics: Default::default(),
+            encodings: Default::default(),
+            quiet: false,
             comp_param_map: Default::default(),
+            exist_params: vec![],
         };
 
-        out.define_funcs();
-
-        // For each `some` parameter of a component, define function from the
-        // input parameters of the component to the `some` parameter.
         for (comp_idx, comp) in ctx.comps.iter() {
             let num_args = comp.param_args().len();
             for some_param in comp.exist_params() {
@@ -240,24 +458,11 @@ impl Construct for Discharge {
                     // If this is an opaque parameter, then we don't define the function
                     continue;
                 }
-                let func = out
-                    .sol
-                    .declare_fun(
-                        format!(
-                            "comp{}_param{}",
-                            comp_idx.get(),
-                            some_param.get()
-                        ),
-                        (0..num_args).map(|_| out.sort()).collect_vec(),
-                        out.sort(),
-                    )
-                    .unwrap();
                 let f = ir::Foreign::new(some_param, comp_idx);
-                out.comp_param_map.insert(f, func);
+                out.exist_params.push((f, num_args));
             }
         }
 
-        out.sol.push_many(1).unwrap();
         out
     }
 
@@ -271,10 +476,15 @@ impl Construct for Discharge {
         self.diagnostics.clear();
         self.act_lit_count = 0;
         self.to_prove.clear();
+        self.preamble.clear();
+        self.to_emit.clear();
+        self.failed.clear();
 
         // Create a new solver context
-        self.sol.pop_many(1).unwrap();
-        self.sol.push_many(1).unwrap();
+        if self.sol_started {
+            self.sol.pop_many(1).unwrap();
+            self.sol.push_many(1).unwrap();
+        }
     }
 }
 
@@ -322,16 +532,33 @@ impl Discharge {
             .unwrap()
     }
 
+    /// Declare the functions used by the encoding of the current component.
+    /// They are redefined for every component since the sort depends on the
+    /// component.
+    fn declare_funcs(&mut self) {
+        self.define_funcs();
+
+        // For each `some` parameter of a component, define function from the
+        // input parameters of the component to the `some` parameter.
+        self.comp_param_map.clear();
+        for (f, num_args) in self.exist_params.clone() {
+            let func = self.declare(
+                format!("comp{}_param{}", f.owner().get(), f.key().get()),
+                (0..num_args).map(|_| self.sort()).collect_vec(),
+                self.sort(),
+            );
+            self.comp_param_map.insert(f, func);
+        }
+    }
+
     /// Defines primitive functions used in the encoding like `pow` and `log`
     fn define_funcs(&mut self) {
         let is = self.sort();
 
         macro_rules! sol_fn(
             ($name:tt($($args:ident),*) -> $out:ident) => {
-                self.func_map.insert(ast::Fn::$name, self
-                    .sol
-                    .declare_fun(stringify!($name).to_lowercase(), vec![$($args),*], $out)
-                    .unwrap());
+                let f = self.declare(stringify!($name).to_lowercase(), vec![$($args),*], $out);
+                self.func_map.insert(ast::Fn::$name, f);
             }
         );
 
@@ -385,6 +612,37 @@ impl Discharge {
         )
     }
 
+    /// Record the counterexample for the proposition of `fact`.
+    /// REQUIRES: The solver has a model for the negation of the proposition.
+    fn get_model(&mut self, fact: &ir::Fact, ctx: &ir::Component) -> Model {
+        let assign = if self.show_models {
+            self.get_assignments(ctx.prop_params(fact.prop.consequent(ctx)))
+        } else {
+            Assign::default()
+        };
+        let sexps = self
+            .ev_map
+            .iter()
+            .map(|(_, s)| s)
+            .chain(self.time_map.iter().map(|(_, s)| s))
+            .chain(self.expr_map.iter().map(|(_, s)| s))
+            .copied()
+            .collect_vec();
+        let values = if sexps.is_empty() {
+            HashMap::new()
+        } else {
+            self.sol
+                .get_value(sexps)
+                .unwrap()
+                .into_iter()
+                .filter_map(|(s, v)| {
+                    Some((s, u64::try_from(self.sol.get(v)).ok()?))
+                })
+                .collect()
+        };
+        Model { assign, values }
+    }
+
     /// Check whether the proposition is valid.
     /// Adds an error to the diagnositcs reporter if the proposition cannot be proved.
     fn check_valid(&mut self, fact: ir::Fact, ctx: &ir::Component) {
@@ -403,15 +661,7 @@ impl Discharge {
                 100
             );
             let out = match res {
-                smt::Response::Sat => {
-                    if self.show_models {
-                        Some(self.get_assignments(
-                            ctx.prop_params(prop.consequent(ctx)),
-                        ))
-                    } else {
-                        Some(Assign::default())
-                    }
-                }
+                smt::Response::Sat => Some(self.get_model(&fact, ctx)),
                 smt::Response::Unsat => None,
                 smt::Response::Unknown => panic!("Solver returned unknown"),
             };
@@ -419,7 +669,10 @@ impl Discharge {
             self.sol.assert(self.sol.not(actlit)).unwrap();
             self.checked.insert(prop, out);
         }
-        if let Some(assign) = &self.checked[&prop] {
+        if let Some(model) = &self.checked[&prop] {
+            if self.debug_proofs {
+                self.failed.push(fact.clone());
+            }
             let Some(ir::info::Assert(reason)) =
                 ctx.get(fact.reason).as_assert()
             else {
@@ -435,6 +688,17 @@ impl Discharge {
                 self.diagnostics.push(diag);
                 return;
             };
+            // Suggestions and diagrams depend on the reason of this fact and
+            // not on the fact that first failed with this proposition.
+            let suggest = self.suggest(model, reason, ctx);
+            // Prefer the diagram computed from the model and fall back to one
+            // computed from concrete offsets.
+            let diagram = self
+                .show_models
+                .then(|| self.model_diagram(model, reason, ctx))
+                .flatten()
+                .or_else(|| Self::concrete_diagram(reason, ctx));
+            let assign = &model.assign;
             let mut diag = reason.diag(ctx);
             if self.show_models {
                 diag = reason.diag(ctx).with_notes(vec![format!(
@@ -447,7 +711,13 @@ impl Discharge {
                         assign.display(ctx)
                     )]);
                 }
+                diag = diag.with_notes(diagram.into_iter().collect());
+            } else if let Some(diagram) = diagram {
+                diag = diag.with_notes(vec![diagram]);
             }
+            diag = diag.with_notes(
+                suggest.iter().map(|s| format!("help: {s}")).collect(),
+            );
             self.diagnostics.push(diag);
         }
     }
@@ -463,7 +733,7 @@ impl Discharge {
     fn expr_to_sexp(&mut self, expr: &ir::Expr) -> smt::SExpr {
         match expr {
             ir::Expr::Param(p) => self.param_map[*p],
-            ir::Expr::Concrete(n) => self.num(*n),
+            ir::Expr::Concrete(n) => self.num(n),
             ir::Expr::Bin { op, lhs, rhs } => {
                 let l = self.expr_map[*lhs];
                 let r = self.expr_map[*rhs];
@@ -543,6 +813,363 @@ impl Discharge {
     }
 }
 
+/// Suggestions for fixing failing assertions computed from a counterexample.
+impl Discharge {
+    /// Value of the time difference in the model.
+    /// Returns `None` if it is not a natural number.
+    fn time_sub_value(&self, model: &Model, ts: &ir::TimeSub) -> Option<u64> {
+        match ts {
+            ir::TimeSub::Unit(e) => {
+                model.values.get(&self.expr_map[*e]).copied()
+            }
+            ir::TimeSub::Sym { l, r } => {
+                let l = model.values.get(&self.time_map[*l])?;
+                let r = model.values.get(&self.time_map[*r])?;
+                l.checked_sub(*r)
+            }
+        }
+    }
+
+    /// The liveness requirement of a failing assertion, if there is one.
+    fn liveness_ranges(
+        reason: &ir::info::Reason,
+    ) -> Option<(&ir::Range, &ir::Range)> {
+        match reason {
+            ir::info::Reason::Liveness {
+                dst_liveness,
+                src_liveness,
+                ..
+            } => Some((dst_liveness, src_liveness)),
+            ir::info::Reason::Generated { src, .. } => {
+                Self::liveness_ranges(src)
+            }
+            _ => None,
+        }
+    }
+
+    /// Render a timing diagram for a liveness failure using the values in
+    /// the model. Cycles are relative to the event of the required
+    /// interval.
+    fn model_diagram(
+        &self,
+        model: &Model,
+        reason: &ir::info::Reason,
+        ctx: &ir::Component,
+    ) -> Option<String> {
+        let (dst, src) = Self::liveness_ranges(reason)?;
+        let event = ctx.get(dst.start).event;
+        let vals = model.values(vec![
+            self.ev_map[event],
+            self.time_map[dst.start],
+            self.time_map[dst.end],
+            self.time_map[src.start],
+            self.time_map[src.end],
+        ])?;
+        let rel = |v: u64| v as i64 - vals[0] as i64;
+        TimingDiagram::new(
+            ctx.display(event),
+            (rel(vals[1]), rel(vals[2])),
+            (rel(vals[3]), rel(vals[4])),
+        )
+        .map(|d| d.to_string())
+    }
+
+    /// Render a timing diagram for a liveness failure when all the intervals
+    /// are concrete offsets from the same event.
+    fn concrete_diagram(
+        reason: &ir::info::Reason,
+        ctx: &ir::Component,
+    ) -> Option<String> {
+        let (dst, src) = Self::liveness_ranges(reason)?;
+        let event = ctx.get(dst.start).event;
+        let offset = |t: ir::TimeIdx| {
+            let ir::Time { event: ev, offset } = ctx.get(t);
+            if *ev == event {
+                offset.as_concrete(ctx).map(|o| o as i64)
+            } else {
+                None
+            }
+        };
+        TimingDiagram::new(
+            ctx.display(event),
+            (offset(dst.start)?, offset(dst.end)?),
+            (offset(src.start)?, offset(src.end)?),
+        )
+        .map(|d| d.to_string())
+    }
+
+    /// Compute suggestions for fixing the failing assertion generated by
+    /// `reason` using the counterexample `model`.
+    fn suggest(
+        &self,
+        model: &Model,
+        reason: &ir::info::Reason,
+        ctx: &ir::Component,
+    ) -> Vec<String> {
+        use ir::info::Reason;
+        match reason {
+            Reason::Liveness {
+                dst_liveness,
+                src_liveness,
+                dst_name,
+                src_name,
+                ..
+            } => {
+                let Some(&[ss, se, ds, de]) = model
+                    .values(vec![
+                        self.time_map[src_liveness.start],
+                        self.time_map[src_liveness.end],
+                        self.time_map[dst_liveness.start],
+                        self.time_map[dst_liveness.end],
+                    ])
+                    .as_deref()
+                else {
+                    return vec![];
+                };
+                if ss > ds {
+                    vec![format!(
+                        "`{src_name}' becomes available {} cycle(s) after `{dst_name}' requires it; schedule `{dst_name}' at least {} cycle(s) later",
+                        ss - ds,
+                        ss - ds
+                    )]
+                } else if se < de {
+                    let shift = de - se;
+                    if ss + shift <= ds {
+                        vec![format!(
+                            "insert a {shift}-cycle Delay/Shift before connecting `{src_name}' to `{dst_name}'"
+                        )]
+                    } else {
+                        vec![format!(
+                            "extend the liveness of `{src_name}' to {}",
+                            ctx.display(dst_liveness)
+                        )]
+                    }
+                } else {
+                    vec![]
+                }
+            }
+            Reason::EventTrig {
+                ev_delay, delay, ..
+            } => {
+                let (Some(ev), Some(d)) = (
+                    self.time_sub_value(model, ev_delay),
+                    self.time_sub_value(model, delay),
+                ) else {
+                    return vec![];
+                };
+                if d < ev {
+                    vec![format!(
+                        "increase the delay of the event from `{}' to at least {ev} (it is {d} in the counterexample)",
+                        ctx.display(delay)
+                    )]
+                } else {
+                    vec![]
+                }
+            }
+            Reason::EventLiveDelay {
+                borrow_len, delay, ..
+            } => {
+                let (Some(b), Some(d)) = (
+                    self.time_sub_value(model, borrow_len),
+                    self.time_sub_value(model, delay),
+                ) else {
+                    return vec![];
+                };
+                if d < b {
+                    vec![format!(
+                        "increase the delay of the event from `{}' to at least {b}, or shorten the instance's liveness to at most {d} cycle(s)",
+                        ctx.display(delay)
+                    )]
+                } else {
+                    vec![]
+                }
+            }
+            Reason::BundleDelay {
+                bundle_live,
+                event_delay,
+                ..
+            } => {
+                let (Some(l), Some(d)) = (
+                    self.time_sub_value(model, bundle_live),
+                    self.time_sub_value(model, event_delay),
+                ) else {
+                    return vec![];
+                };
+                if d < l {
+                    vec![format!(
+                        "increase the delay of the event from `{}' to at least {l}, or shorten the bundle's availability `{}' to at most {d} cycle(s)",
+                        ctx.display(event_delay),
+                        ctx.display(bundle_live)
+                    )]
+                } else {
+                    vec![]
+                }
+            }
+            Reason::WellFormedInterval {
+                range: (start, end),
+                ..
+            } => {
+                let sexps = vec![self.time_map[*start], self.time_map[*end]];
+                let Some(&[s, e]) = model.values(sexps).as_deref() else {
+                    return vec![];
+                };
+                if e <= s {
+                    vec![format!(
+                        "extend the end of the interval by at least {} cycle(s)",
+                        s - e + 1
+                    )]
+                } else {
+                    vec![]
+                }
+            }
+            Reason::EventLive {
+                borrow: (bs, be),
+                invoke_range: (is, ie),
+                ..
+            } => {
+                let sexps = vec![
+                    self.time_map[*bs],
+                    self.time_map[*be],
+                    self.time_map[*is],
+                    self.time_map[*ie],
+                ];
+                let Some(&[bsv, bev, isv, iev]) =
+                    model.values(sexps).as_deref()
+                else {
+                    return vec![];
+                };
+                if isv >= bsv && iev <= bev {
+                    return vec![];
+                }
+                let start = if isv < bsv { is } else { bs };
+                let end = if iev > bev { ie } else { be };
+                vec![format!(
+                    "extend the liveness of the instance to [{}, {}]",
+                    ctx.display(*start),
+                    ctx.display(*end)
+                )]
+            }
+            Reason::BundleWidthMatch {
+                dst_width,
+                src_width,
+                ..
+            } => {
+                let sexps =
+                    vec![self.expr_map[*dst_width], self.expr_map[*src_width]];
+                let Some(&[d, s]) = model.values(sexps).as_deref() else {
+                    return vec![];
+                };
+                // Concrete widths cannot be made equal by a constraint
+                let fix = if dst_width.as_concrete(ctx).is_some()
+                    && src_width.as_concrete(ctx).is_some()
+                {
+                    "change the width of one of the ports".to_string()
+                } else {
+                    format!(
+                        "add a constraint ensuring `{}' = `{}'",
+                        ctx.display(*dst_width),
+                        ctx.display(*src_width)
+                    )
+                };
+                vec![format!(
+                    "the destination is {d} bits wide but the source is {s} bits wide; {fix}"
+                )]
+            }
+            Reason::Generated { src, .. } => self.suggest(model, src, ctx),
+            Reason::ParamConstraint { .. }
+            | Reason::EventConstraint { .. }
+            | Reason::ExistsConstraint { .. }
+            | Reason::BundleLenMatch { .. }
+            | Reason::InBoundsAccess { .. }
+            | Reason::Misc { .. } => vec![],
+        }
+    }
+}
+
+/// Standalone queries for each obligation
+impl Discharge {
+    /// Header describing the component and the reason for an obligation
+    fn emit_header(name: &str, fact: &ir::Fact, ctx: &ir::Component) -> String {
+        let mut header = format!("; component: {name}\n");
+        let Some(ir::info::Assert(reason)) = ctx.get(fact.reason).as_assert()
+        else {
+            return header;
+        };
+        let diag = reason.diag(ctx);
+        header += &format!("; reason: {}\n", reason.name());
+        header += &format!("; message: {}\n", diag.message);
+        let table = GlobalPositionTable::as_ref();
+        let files = table.files();
+        for label in &diag.labels {
+            let (Ok(file), Ok(loc)) = (
+                files.name(label.file_id),
+                files.location(label.file_id, label.range.start),
+            ) else {
+                continue;
+            };
+            header += &format!(
+                "; at {file}:{}:{}: {}\n",
+                loc.line_number, loc.column_number, label.message
+            );
+        }
+        header
+    }
+
+    /// Write each obligation of the current component to its own file with
+    /// the encoding of the component. The query is unsatisfiable iff the
+    /// obligation holds.
+    fn emit_obligations(&mut self, data: &VisitorData) {
+        let Some(dir) = &self.emit_dir else {
+            return;
+        };
+        let comp = &data.comp;
+        let name = comp.def_name.to_string();
+        let mut diag = Diagnostics::default();
+        let error = |path: &Path, err: io::Error| {
+            Error::invalid_file(format!(
+                "Failed to write {}: {err}",
+                path.display()
+            ))
+        };
+        if let Err(err) = fs::create_dir_all(dir) {
+            diag.add_error(error(dir, err));
+        } else {
+            for (i, fact) in self.to_emit.iter().enumerate() {
+                let path = dir.join(format!("{name}-{i}.smt2"));
+                match self.emit_obligation(&path, &name, fact, comp) {
+                    Ok(()) => {
+                        log::debug!("Wrote obligation to {}", path.display())
+                    }
+                    Err(err) => diag.add_error(error(&path, err)),
+                }
+            }
+        }
+        if let Some(errs) = diag.report_all() {
+            self.error_count += errs;
+        }
+    }
+
+    /// Write the query for a single obligation to `path`
+    fn emit_obligation(
+        &self,
+        path: &Path,
+        name: &str,
+        fact: &ir::Fact,
+        comp: &ir::Component,
+    ) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        write!(file, "{}", Self::emit_header(name, fact, comp))?;
+        writeln!(file, "; unsat iff the obligation holds")?;
+        writeln!(file, "(set-logic ALL)")?;
+        for cmd in &self.preamble {
+            writeln!(file, "{}", self.sol.display(*cmd))?;
+        }
+        let goal = self.sol.not(self.prop_map[fact.prop]);
+        writeln!(file, "(assert {})", self.sol.display(goal))?;
+        writeln!(file, "(check-sat)")
+    }
+}
+
 impl Visitor for Discharge {
     fn name() -> &'static str {
         "discharge"
@@ -556,34 +1183,108 @@ impl Visitor for Discharge {
         }
 
         let comp = &data.comp;
+        self.signed = comp.params().iter().any(|(_, p)| p.signed)
+            || comp.exprs().iter().any(
+                |(_, e)| matches!(e, ir::Expr::Concrete(n) if n.is_negative()),
+            );
+        // Subtraction wraps around in the unsigned encoding while it may be
+        // negative over the integers. The automatically picked encoding must
+        // agree with the integer one so it reserves a sign bit for them.
+        if matches!(self.bv_opt, Some(cmdline::BvWidth::Auto)) {
+            self.signed |= comp.exprs().iter().any(|(_, e)| {
+                matches!(
+                    e,
+                    ir::Expr::Bin {
+                        op: ast::Op::Sub,
+                        ..
+                    }
+                )
+            }) || comp.props().iter().any(|(_, p)| {
+                let ir::Prop::TimeSubCmp(c) = p else {
+                    return false;
+                };
+                [&c.lhs, &c.rhs]
+                    .into_iter()
+                    .any(|ts| matches!(ts, ir::TimeSub::Sym { .. }))
+            });
+        }
+
+        let name = comp.def_name;
+        self.bv_size = match self.bv_opt {
+            None => None,
+            // Widen the bitvector encoding to fit the declared parameter ranges
+            Some(cmdline::BvWidth::Fixed(w)) => {
+                let range = Bounds::new(comp).range_width(self.signed);
+                if range > w as u32 {
+                    log::info!(
+                        "{name}: using {range}-bit encoding to fit parameter ranges",
+                    );
+                }
+                Some(range.max(w as u32))
+            }
+            Some(cmdline::BvWidth::Auto) => {
+                let width = Bounds::new(comp).width(self.signed);
+                let msg = match &width {
+                    Ok(w) => format!(
+                        "component `{name}' uses a {w}-bit bitvector encoding"
+                    ),
+                    Err(reason) => format!(
+                        "component `{name}' uses the integer encoding: {reason}"
+                    ),
+                };
+                if !comp.is_ext() {
+                    self.encodings.push(Diagnostic::note().with_message(msg));
+                }
+                width.ok()
+            }
+        };
+
+        // Obligations proved by the built-in decision procedure do not need
+        // to be sent to the solver.
+        if self.emit_dir.is_some() {
+            self.to_emit = self.to_prove.clone();
+        }
+        let mut linear = Linear::new(comp);
+        let total = self.to_prove.len();
+        self.to_prove.retain(|f| !linear.valid(f.prop));
+        let proved = (total - self.to_prove.len()) as u64;
+        self.builtin_count += proved;
+        log::debug!(
+            "{name}: proved {proved}/{total} obligations without the solver"
+        );
+        // The encoding is still required to emit the obligations
+        if self.to_prove.is_empty() && self.to_emit.is_empty() {
+            return Action::Stop;
+        }
+
+        if !self.to_prove.is_empty() {
+            self.start_solver();
+        }
+        self.declare_funcs();
+
         // Declare all parameters
         let int = self.sort();
-        for (idx, _) in data.comp.params().iter() {
-            let sexp = self
-                .sol
-                .declare_fun(self.fmt_param(idx, comp), vec![], int)
-                .unwrap();
+        for (idx, p) in data.comp.params().iter() {
+            let sexp = self.declare(self.fmt_param(idx, comp), vec![], int);
             self.overflow_assert(sexp);
+            if !p.signed {
+                self.natural_assert(sexp);
+            }
             self.param_map.push(idx, sexp);
         }
 
         // Declare all events
         for (idx, _) in data.comp.events().iter() {
-            let sexp = self
-                .sol
-                .declare_fun(self.fmt_event(idx, comp), vec![], int)
-                .unwrap();
+            let sexp = self.declare(self.fmt_event(idx, comp), vec![], int);
             self.overflow_assert(sexp);
+            self.natural_assert(sexp);
             self.ev_map.push(idx, sexp);
         }
 
         // Declare all expressions
         for (idx, expr) in data.comp.exprs().iter() {
             let assign = self.expr_to_sexp(expr);
-            let sexp = self
-                .sol
-                .define_const(Self::fmt_expr(idx), int, assign)
-                .unwrap();
+            let sexp = self.define(Self::fmt_expr(idx), int, assign);
             self.overflow_assert(sexp);
             self.expr_map.push(idx, sexp);
         }
@@ -596,16 +1297,13 @@ impl Visitor for Discharge {
             let param_s = self.param_map[idx];
             let bind_s = self.expr_map[*bind];
             let assign = self.sol.eq(param_s, bind_s);
-            self.sol.assert(assign).unwrap();
+            self.assume(assign);
         }
 
         // Declare all time expressions
         for (idx, ir::Time { event, offset }) in data.comp.times().iter() {
             let assign = self.plus(self.ev_map[*event], self.expr_map[*offset]);
-            let sexp = self
-                .sol
-                .define_const(Self::fmt_time(idx), int, assign)
-                .unwrap();
+            let sexp = self.define(Self::fmt_time(idx), int, assign);
             self.overflow_assert(sexp);
             self.time_map.push(idx, sexp);
         }
@@ -615,10 +1313,7 @@ impl Visitor for Discharge {
         for (idx, prop) in data.comp.props().iter() {
             // Define assertion equating the proposition to its assignment
             let assign = self.prop_to_sexp(prop);
-            let sexp = self
-                .sol
-                .define_const(Discharge::fmt_prop(idx), bs, assign)
-                .unwrap();
+            let sexp = self.define(Discharge::fmt_prop(idx), bs, assign);
             self.prop_map.push(idx, sexp);
         }
         // Pass does not need to traverse the control program.
@@ -640,13 +1335,14 @@ impl Visitor for Discharge {
                 let param_s = self.param_map[*param];
                 let app = self.app(*f, sexp_args.clone());
                 let assign = self.sol.eq(param_s, app);
-                self.sol.assert(assign).unwrap();
+                self.assume(assign);
             }
         }
         Action::Continue
     }
 
     fn end(&mut self, data: &mut VisitorData) {
+        self.emit_obligations(data);
         if self.to_prove.is_empty() {
             return;
         }
@@ -673,6 +1369,10 @@ impl Visitor for Discharge {
         }
 
         // Report all the errors
+        if self.quiet {
+            self.error_count += self.diagnostics.len() as u64;
+            return;
+        }
         let is_tty = atty::is(atty::Stream::Stderr);
         let writer = StandardStream::stderr(if is_tty {
             ColorChoice::Always
@@ -690,9 +1390,19 @@ impl Visitor for Discharge {
             .unwrap();
             self.error_count += 1;
         }
+        if self.debug_proofs {
+            self.debug_proofs(&data.comp);
+        }
     }
 
     fn after_traversal(&mut self) -> Option<u64> {
+        log::info!(
+            "{} obligation(s) proved without the solver",
+            self.builtin_count
+        );
+        if !self.quiet {
+            fil_utils::report_notes(&self.encodings);
+        }
         if self.error_count > 0 {
             Some(self.error_count)
         } else {
@@ -0,0 +1,456 @@
+use super::Discharge;
+use codespan_reporting::term;
+use easy_smt as smt;
+use fil_ast as ast;
+use fil_ir::{self as ir, Ctx, DisplayCtx};
+use fil_utils::{BigInt, GlobalPositionTable};
+use itertools::Itertools;
+use std::io::{self, BufRead, Write};
+use term::termcolor::{ColorChoice, StandardStream};
+
+const HELP: &str = "\
+commands:
+  list                 list the failing obligations
+  select <n>           select the n-th failing obligation
+  source               show the source of the selected obligation
+  assumptions          list the assumptions of the selected obligation
+  assume <a> <op> <b>  add a temporary assumption (op: >, >=, <, <=, ==)
+  clear                remove the temporary assumptions
+  model                find a counterexample for the selected obligation
+  next                 find a counterexample different from the last one
+  eval <expr>          evaluate an expression in the current counterexample
+  help                 show this message
+  quit                 stop debugging the component";
+
+#[derive(Clone, PartialEq, Debug)]
+enum Tok {
+    Num(BigInt),
+    Name(String),
+    Sym(&'static str),
+}
+
+/// Split a line into tokens
+fn lex(s: &str) -> Result<Vec<Tok>, String> {
+    const SYMS: [&str; 13] = [
+        ">=", "<=", "==", ">", "<", "=", "+", "-", "*", "/", "%", "(", ")",
+    ];
+    let is_name =
+        |c: char| c.is_alphanumeric() || matches!(c, '_' | '\'' | ':');
+    let mut toks = vec![];
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            toks.push(Tok::Num(rest[..end].parse().unwrap()));
+            rest = &rest[end..];
+        } else if is_name(c) {
+            let end = rest.find(|c| !is_name(c)).unwrap_or(rest.len());
+            toks.push(Tok::Name(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c == ',' {
+            toks.push(Tok::Sym(","));
+            rest = &rest[1..];
+        } else if let Some(sym) = SYMS.iter().find(|s| rest.starts_with(**s)) {
+            toks.push(Tok::Sym(sym));
+            rest = &rest[sym.len()..];
+        } else {
+            return Err(format!("unexpected character `{c}'"));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(toks)
+}
+
+/// Parser for the expressions and comparisons entered by the user. The
+/// result is encoded directly into the solver's terms.
+struct Parser<'a> {
+    toks: Vec<Tok>,
+    pos: usize,
+    dis: &'a Discharge,
+    ctx: &'a ir::Component,
+}
+
+impl<'a> Parser<'a> {
+    fn new(
+        s: &str,
+        dis: &'a Discharge,
+        ctx: &'a ir::Component,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            toks: lex(s)?,
+            pos: 0,
+            dis,
+            ctx,
+        })
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.pos)
+    }
+
+    fn eat(&mut self, sym: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Sym(s)) if *s == sym) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, sym: &str) -> Result<(), String> {
+        if self.eat(sym) {
+            Ok(())
+        } else {
+            Err(format!("expected `{sym}'"))
+        }
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(t) => Err(format!("unexpected token {t:?}")),
+        }
+    }
+
+    /// Look up a parameter or event by the name it is displayed with
+    fn name(&self, n: &str) -> Result<smt::SExpr, String> {
+        let param = self
+            .ctx
+            .params()
+            .idx_iter()
+            .find(|p| self.ctx.display(*p) == n)
+            .map(|p| self.dis.param_map[p]);
+        let event = || {
+            self.ctx
+                .events()
+                .idx_iter()
+                .find(|e| self.ctx.display(*e) == n)
+                .map(|e| self.dis.ev_map[e])
+        };
+        param
+            .or_else(event)
+            .ok_or_else(|| format!("unknown parameter or event `{n}'"))
+    }
+
+    fn func(&self, n: &str) -> Option<ast::Fn> {
+        [
+            ast::Fn::Pow2,
+            ast::Fn::Log2,
+            ast::Fn::SinB,
+            ast::Fn::CosB,
+            ast::Fn::BitRev,
+        ]
+        .into_iter()
+        .find(|f| f.to_string() == n)
+    }
+
+    fn atom(&mut self) -> Result<smt::SExpr, String> {
+        let tok = self.peek().cloned();
+        self.pos += 1;
+        match tok {
+            Some(Tok::Num(n)) => Ok(self.dis.num(&n)),
+            Some(Tok::Name(n)) if self.eat("(") => {
+                let f = self
+                    .func(&n)
+                    .ok_or_else(|| format!("unknown function `{n}'"))?;
+                let mut args = vec![self.expr()?];
+                while self.eat(",") {
+                    args.push(self.expr()?);
+                }
+                self.expect(")")?;
+                let f = self.dis.func_map[&f];
+                Ok(self.dis.sol.list(std::iter::once(f).chain(args).collect()))
+            }
+            Some(Tok::Name(n)) => self.name(&n),
+            Some(Tok::Sym("(")) => {
+                let e = self.expr()?;
+                self.expect(")")?;
+                Ok(e)
+            }
+            Some(t) => Err(format!("unexpected token {t:?}")),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn term(&mut self) -> Result<smt::SExpr, String> {
+        let mut l = self.atom()?;
+        loop {
+            l = if self.eat("*") {
+                self.dis.times(l, self.atom()?)
+            } else if self.eat("/") {
+                self.dis.div(l, self.atom()?)
+            } else if self.eat("%") {
+                self.dis.modulo(l, self.atom()?)
+            } else {
+                return Ok(l);
+            }
+        }
+    }
+
+    fn expr(&mut self) -> Result<smt::SExpr, String> {
+        let mut l = self.term()?;
+        loop {
+            l = if self.eat("+") {
+                self.dis.plus(l, self.term()?)
+            } else if self.eat("-") {
+                self.dis.sub(l, self.term()?)
+            } else {
+                return Ok(l);
+            }
+        }
+    }
+
+    /// Parse a complete expression
+    fn parse_expr(mut self) -> Result<smt::SExpr, String> {
+        let e = self.expr()?;
+        self.finish()?;
+        Ok(e)
+    }
+
+    /// Parse a complete comparison
+    fn parse_cmp(mut self) -> Result<smt::SExpr, String> {
+        let l = self.expr()?;
+        let Some(Tok::Sym(op)) = self.peek().cloned() else {
+            return Err("expected a comparison".to_string());
+        };
+        self.pos += 1;
+        let r = self.expr()?;
+        self.finish()?;
+        let dis = self.dis;
+        match op {
+            ">" => Ok(dis.gt(l, r)),
+            ">=" => Ok(dis.gte(l, r)),
+            "<" => Ok(dis.gt(r, l)),
+            "<=" => Ok(dis.gte(r, l)),
+            "==" | "=" => Ok(dis.eq(l, r)),
+            _ => Err(format!("unknown comparison `{op}'")),
+        }
+    }
+}
+
+#[derive(Default)]
+/// State of the debugger for a component
+struct Session {
+    /// Index of the selected failing obligation
+    selected: usize,
+    /// Temporary assumptions along with how they were written
+    assumes: Vec<(String, smt::SExpr)>,
+    /// Clauses excluding the counterexamples that were already shown
+    blocked: Vec<smt::SExpr>,
+    /// Assignments in the last counterexample
+    model: Vec<(smt::SExpr, smt::SExpr)>,
+    /// Did the last query find a counterexample
+    sat: bool,
+    /// Is there an open solver scope for the last query
+    pushed: bool,
+}
+
+/// Interactive exploration of failing obligations
+impl Discharge {
+    /// Start the debugger for the failing obligations of the component.
+    /// Commands are read from the standard input until it is closed or the
+    /// user quits.
+    pub(super) fn debug_proofs(&mut self, ctx: &ir::Component) {
+        let failed = std::mem::take(&mut self.failed);
+        if failed.is_empty() {
+            return;
+        }
+        let mut sess = Session::default();
+        println!(
+            "{} failing obligation(s). Type `help' for a list of commands.",
+            failed.len()
+        );
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            print!("(debug) ");
+            io::stdout().flush().unwrap();
+            let Some(Ok(line)) = lines.next() else {
+                break;
+            };
+            let (cmd, arg) = line
+                .trim()
+                .split_once(' ')
+                .map_or((line.trim(), ""), |(c, a)| (c, a.trim()));
+            let fact = &failed[sess.selected];
+            match cmd {
+                "" => (),
+                "help" => println!("{HELP}"),
+                "quit" | "q" => break,
+                "list" => {
+                    for (i, f) in failed.iter().enumerate() {
+                        let mark = if i == sess.selected { '*' } else { ' ' };
+                        println!("{mark} {i}: {}", Self::describe(f, ctx));
+                    }
+                }
+                "select" => match arg.parse::<usize>() {
+                    Ok(n) if n < failed.len() => {
+                        sess.selected = n;
+                        sess.blocked.clear();
+                        sess.model.clear();
+                        sess.sat = false;
+                        println!("{n}: {}", Self::describe(&failed[n], ctx));
+                    }
+                    _ => println!("expected a number below {}", failed.len()),
+                },
+                "source" => Self::show_source(fact, ctx),
+                "assumptions" => {
+                    let mut conj = vec![];
+                    if let ir::Prop::Implies(a, _) = ctx.get(fact.prop) {
+                        Self::conjuncts(*a, ctx, &mut conj);
+                    }
+                    for p in conj {
+                        println!("  {}", ctx.display(p));
+                    }
+                    for (a, _) in &sess.assumes {
+                        println!("  {a} (temporary)");
+                    }
+                    println!("goal: {}", ctx.display(fact.prop.consequent(ctx)));
+                }
+                "assume" => {
+                    match Parser::new(arg, self, ctx).and_then(|p| p.parse_cmp()) {
+                        Ok(e) => sess.assumes.push((arg.to_string(), e)),
+                        Err(e) => println!("error: {e}"),
+                    }
+                }
+                "clear" => {
+                    sess.assumes.clear();
+                    sess.blocked.clear();
+                }
+                "model" => {
+                    sess.blocked.clear();
+                    self.query(fact, &mut sess, ctx);
+                }
+                "next" => {
+                    if sess.model.is_empty() {
+                        println!("no counterexample to exclude; use `model' first");
+                        continue;
+                    }
+                    let diff = sess
+                        .model
+                        .iter()
+                        .map(|(p, v)| self.sol.not(self.sol.eq(*p, *v)))
+                        .collect_vec();
+                    sess.blocked.push(self.sol.or_many(diff));
+                    self.query(fact, &mut sess, ctx);
+                }
+                "eval" => {
+                    if !sess.sat {
+                        println!("no counterexample; use `model' first");
+                        continue;
+                    }
+                    match Parser::new(arg, self, ctx).and_then(|p| p.parse_expr()) {
+                        Ok(e) => {
+                            let v = self.sol.get_value(vec![e]).unwrap();
+                            println!("{arg} = {}", self.sol.display(v[0].1));
+                        }
+                        Err(e) => println!("error: {e}"),
+                    }
+                }
+                _ => println!("unknown command `{cmd}'. Type `help' for a list of commands."),
+            }
+        }
+        if sess.pushed {
+            self.sol.pop().unwrap();
+        }
+    }
+
+    /// One line description of a failing obligation
+    fn describe(fact: &ir::Fact, ctx: &ir::Component) -> String {
+        let goal = ctx.display(fact.prop.consequent(ctx));
+        match ctx.get(fact.reason).as_assert() {
+            Some(ir::info::Assert(r)) => {
+                format!("{goal} ({})", r.diag(ctx).message)
+            }
+            None => goal,
+        }
+    }
+
+    fn show_source(fact: &ir::Fact, ctx: &ir::Component) {
+        let Some(ir::info::Assert(reason)) = ctx.get(fact.reason).as_assert()
+        else {
+            println!("no source information for this obligation");
+            return;
+        };
+        let writer = StandardStream::stdout(ColorChoice::Never);
+        let table = GlobalPositionTable::as_ref();
+        term::emit(
+            &mut writer.lock(),
+            &term::Config::default(),
+            table.files(),
+            &reason.diag(ctx),
+        )
+        .unwrap();
+    }
+
+    fn conjuncts(
+        prop: ir::PropIdx,
+        ctx: &ir::Component,
+        acc: &mut Vec<ir::PropIdx>,
+    ) {
+        match ctx.get(prop) {
+            ir::Prop::True => (),
+            ir::Prop::And(l, r) => {
+                Self::conjuncts(*l, ctx, acc);
+                Self::conjuncts(*r, ctx, acc);
+            }
+            _ => acc.push(prop),
+        }
+    }
+
+    /// Search for a counterexample to the selected obligation under the
+    /// temporary assumptions. The solver scope of the query stays open so
+    /// that expressions can be evaluated in the model.
+    fn query(
+        &mut self,
+        fact: &ir::Fact,
+        sess: &mut Session,
+        ctx: &ir::Component,
+    ) {
+        if sess.pushed {
+            self.sol.pop().unwrap();
+        }
+        self.sol.push().unwrap();
+        sess.pushed = true;
+        sess.model.clear();
+        sess.sat = false;
+
+        let goal = self.sol.not(self.prop_map[fact.prop]);
+        self.sol.assert(goal).unwrap();
+        for e in sess
+            .assumes
+            .iter()
+            .map(|(_, e)| *e)
+            .chain(sess.blocked.clone())
+        {
+            self.sol.assert(e).unwrap();
+        }
+        match self.sol.check().unwrap() {
+            smt::Response::Sat => sess.sat = true,
+            smt::Response::Unsat => {
+                println!("no counterexample under the current assumptions");
+                return;
+            }
+            smt::Response::Unknown => {
+                println!("the solver could not decide the query");
+                return;
+            }
+        }
+        let params = ctx
+            .prop_params(fact.prop)
+            .into_iter()
+            .unique()
+            .collect_vec();
+        if params.is_empty() {
+            println!("counterexample does not depend on any parameter");
+            return;
+        }
+        let sexps = params.iter().map(|p| self.param_map[*p]).collect_vec();
+        sess.model = self.sol.get_value(sexps).unwrap();
+        for (p, (_, v)) in params.iter().zip(&sess.model) {
+            println!("  {} = {}", ctx.display(*p), self.sol.display(*v));
+        }
+    }
+}
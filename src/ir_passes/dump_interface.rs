@@ -1,95 +1,449 @@
-use crate::ir_passes::lower::max_states;
+//! Export the interfaces of monomorphized components as JSON.
+//!
+//! The output has the shape:
+//! ```json
+//! {
+//!   "version": 1,
+//!   "components": [{
+//!     "name": "main",          // name of the generated module
+//!     "source": "main",        // name of the source definition
+//!     "kind": "source",        // "source", "external", or "generated"
+//!     "entrypoint": true,
+//!     "params": [{ "name": "W", "value": 32, "min": 1, "max": 64 }],
+//!     "exists": [{ "name": "L", "value": 3 }],
+//!     "interfaces": [{
+//!       "name": "go", "event": "G", "delay": 1, "states": 2, "phantom": false
+//!     }],
+//!     "inputs": [{
+//!       "name": "left", "event": "G", "width": 32, "start": 0, "end": 1
+//!     }],
+//!     "outputs": [{
+//!       "name": "out", "event": "G", "width": 32, "start": "i", "end": "i+1",
+//!       "bundle": { "index": ["i"], "dims": [4] }
+//!     }],
+//!     "unannotated": [{ "name": "clk", "width": 1 }]
+//!   }]
+//! }
+//! ```
+//! Numbers that cannot be computed, such as the widths of external
+//! components, are printed as strings containing the expression. Numbers
+//! that do not fit in 64 bits are printed with all of their digits. Parameters
+//! and existentials of external components have no `value`, and ports whose
+//! liveness ends on a different event have an `end_event` field. `states` is
+//! omitted for external components because they do not get FSMs.
+//!
+//! Parameters declared with a range like `W: 1..=64` have `min` and `max`
+//! fields containing its bounds, and parameters declared as `pow2` have a
+//! `"pow2": true` field. The fields are omitted for parameters without such
+//! declarations.
+//!
+//! [SCHEMA_VERSION] is incremented whenever a field is removed or changes its
+//! meaning. Adding fields does not change the version.
+use crate::cmdline;
+use crate::ir_passes::lower::NameGenerator;
 use fil_ir::{self as ir, Ctx, DisplayCtx};
+use fil_utils::{self as utils, BigInt, Diagnostics, Error, ToPrimitive};
 use itertools::Itertools;
+use serde::Serialize;
+use std::collections::HashMap;
 
-pub struct DumpInterface;
-
-impl DumpInterface {
-    /// Print out the interface of the main component in JSON format
-    pub fn print(ctx: &ir::Context) {
-        let entrypoint = ctx
-            .entrypoint
-            .unwrap_or_else(|| panic!("No entrypoint found."));
-        let main = ctx.get(entrypoint);
-        let src_info = main
-            .src_info
-            .as_ref()
-            .unwrap_or_else(|| panic!("No source info found for main."));
-
-        // For an interface port like this:
-        //      @interface['G, 'G+5] go_G
-        // Generate the JSON information:
-        // {
-        //   "name": "go_G",
-        //   "event": "G",
-        //   "delay": 5,
-        //   "states": 2,
-        //   "phantom": false
-        // }
-        let states = max_states(main);
-        let interfaces = main
-            .events()
-            .iter()
-            .map(|(idx, ev)| {
-                let id = src_info.interface_ports.find(idx).map_or("null", |v| v.as_ref());
-                let phantom = !ev.has_interface;
-                let ir::TimeSub::Unit(delay) = ev.delay else {
-                    panic!("Event `{}` has a non-simple delay.", main.display(idx));
+/// Version of the interface schema
+pub const SCHEMA_VERSION: u64 = 1;
+
+#[derive(Serialize)]
+struct Interface {
+    version: u64,
+    components: Vec<CompInterface>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct CompInterface {
+    pub(crate) name: String,
+    pub(crate) source: String,
+    pub(crate) kind: &'static str,
+    pub(crate) entrypoint: bool,
+    pub(crate) params: Vec<Binding>,
+    pub(crate) exists: Vec<Binding>,
+    pub(crate) interfaces: Vec<EventInterface>,
+    pub(crate) inputs: Vec<PortInterface>,
+    pub(crate) outputs: Vec<PortInterface>,
+    pub(crate) unannotated: Vec<Unannotated>,
+}
+
+#[derive(Serialize, Clone)]
+/// A number or the expression computing it when it is not known
+#[serde(untagged)]
+pub(crate) enum Value {
+    Num(i64),
+    /// A number that does not fit in 64 bits. Printed as a JSON number with
+    /// all of its digits.
+    Big(serde_json::Number),
+    Expr(String),
+}
+
+impl Value {
+    pub(crate) fn big(v: &BigInt) -> Self {
+        v.to_i64().map_or_else(
+            || Value::Big(v.to_string().parse().unwrap()),
+            Value::Num,
+        )
+    }
+
+    fn expr(e: ir::ExprIdx, comp: &ir::Component) -> Self {
+        e.as_big(comp)
+            .map_or_else(|| Value::Expr(comp.display(e)), Value::big)
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{n}"),
+            Value::Big(n) => write!(f, "{n}"),
+            Value::Expr(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct Binding {
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) value: Option<Value>,
+    /// Lower bound of the declared range of the parameter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) min: Option<Value>,
+    /// Upper bound of the declared range of the parameter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max: Option<Value>,
+    /// Set if the parameter is declared to be a power of two
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) pow2: Option<bool>,
+}
+
+impl Binding {
+    /// A binding without a declared range
+    pub(crate) fn new(name: String, value: Option<Value>) -> Self {
+        Self {
+            name,
+            value,
+            min: None,
+            max: None,
+            pow2: None,
+        }
+    }
+
+    /// Add the declared range of the parameter
+    fn with_range(
+        self,
+        min: Option<Value>,
+        max: Option<Value>,
+        pow2: bool,
+    ) -> Self {
+        Self {
+            min,
+            max,
+            pow2: pow2.then_some(true),
+            ..self
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct EventInterface {
+    /// Name of the interface port or `null` for phantom events
+    pub(crate) name: Option<String>,
+    pub(crate) event: String,
+    pub(crate) delay: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) states: Option<u64>,
+    pub(crate) phantom: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct PortInterface {
+    pub(crate) name: String,
+    pub(crate) event: String,
+    pub(crate) width: Value,
+    pub(crate) start: Value,
+    pub(crate) end: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) end_event: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) bundle: Option<Bundle>,
+}
+
+#[derive(Serialize)]
+/// A port without timing information that is threaded through by the backend
+pub(crate) struct Unannotated {
+    pub(crate) name: String,
+    pub(crate) width: u64,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Bundle {
+    pub(crate) index: Vec<String>,
+    pub(crate) dims: Vec<Value>,
+}
+
+pub struct DumpInterface<'a> {
+    ctx: &'a ir::Context,
+}
+
+impl DumpInterface<'_> {
+    /// Print the interfaces of the components selected by `--interface-comp`
+    /// in JSON format. Defaults to the toplevel component.
+    pub fn print(opts: &cmdline::Opts, ctx: &ir::Context) -> Result<(), u64> {
+        let mut diag = Diagnostics::default();
+        let ng = NameGenerator::new(false);
+        let comps = match opts.interface_comp.as_deref() {
+            None => ctx.entrypoint.into_iter().collect_vec(),
+            Some("all") => ctx.comps.idx_iter().collect_vec(),
+            Some(name) => ctx
+                .iter()
+                .filter(|(idx, comp)| {
+                    ng.comp_name(*idx, ctx) == name
+                        || comp.def_name.as_ref() == name
+                })
+                .map(|(idx, _)| idx)
+                .collect_vec(),
+        };
+        if comps.is_empty() {
+            let name = opts.interface_comp.as_ref().unwrap_or(&opts.toplevel);
+            diag.add_error(Error::misc(format!(
+                "no component named `{name}' to dump the interface of"
+            )));
+            return Err(diag.report_all().unwrap());
+        }
+
+        let dump = DumpInterface { ctx };
+        let interface = Interface {
+            version: SCHEMA_VERSION,
+            components: comps
+                .into_iter()
+                .map(|idx| dump.comp(&ng, idx))
+                .collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&interface).unwrap());
+        Ok(())
+    }
+}
+
+impl DumpInterface<'_> {
+    /// The interface of a single component
+    pub(crate) fn interface(
+        ctx: &ir::Context,
+        idx: ir::CompIdx,
+    ) -> CompInterface {
+        DumpInterface { ctx }.comp(&NameGenerator::new(false), idx)
+    }
+
+    fn comp(&self, ng: &NameGenerator, idx: ir::CompIdx) -> CompInterface {
+        let comp = self.ctx.get(idx);
+        let src_info = comp.src_info.as_ref();
+
+        let param_name = |p: ir::ParamIdx| {
+            src_info
+                .and_then(|si| si.params.find(p))
+                .map(|n| n.to_string())
+                .or_else(|| comp.get(p).info.get_name(comp))
+                .unwrap_or_else(|| comp.display(p))
+        };
+        let (params, exists) = match &comp.mono_src {
+            Some(ms) => {
+                let bind = |(n, v): &(fil_ast::Id, BigInt)| {
+                    Binding::new(n.to_string(), Some(Value::big(v)))
                 };
-                let delay = delay.as_concrete(main).unwrap();
+                (
+                    ms.params
+                        .iter()
+                        .zip(&ms.ranges)
+                        .map(|(p, r)| {
+                            bind(p).with_range(
+                                r.min.as_ref().map(Value::big),
+                                r.max.as_ref().map(Value::big),
+                                r.pow2,
+                            )
+                        })
+                        .collect(),
+                    ms.exists.iter().map(bind).collect(),
+                )
+            }
+            // External components are not monomorphized
+            None => {
+                let unbound = |p| Binding::new(param_name(p), None);
+                (
+                    comp.sig_params()
+                        .map(|p| Self::range(unbound(p), p, comp))
+                        .collect(),
+                    comp.exist_params().map(unbound).collect(),
+                )
+            }
+        };
 
-                format!(
-                    "{{\"name\": \"{}\", \"event\": \"{}\", \"delay\": {}, \"states\": {}, \"phantom\": {} }}",
-                    id,
-                    src_info.events.get(idx),
+        let states = (!comp.is_ext()).then(|| Self::states(comp));
+        let interfaces = comp
+            .events()
+            .idx_iter()
+            .map(|ev| {
+                let event = comp.get(ev);
+                let info = comp.get(event.info).as_event();
+                let name = event.has_interface.then(|| {
+                    src_info
+                        .and_then(|si| si.interface_ports.find(ev))
+                        .or_else(|| {
+                            info.and_then(|i| i.interface_name.as_ref())
+                        })
+                        .map_or_else(
+                            || format!("ev{}", ev.get()),
+                            |n| n.to_string(),
+                        )
+                });
+                let delay = match &event.delay {
+                    ir::TimeSub::Unit(e) => Value::expr(*e, comp),
+                    d @ ir::TimeSub::Sym { .. } => Value::Expr(comp.display(d)),
+                };
+                EventInterface {
+                    name,
+                    event: Self::event_name(comp, ev),
                     delay,
-                    states[&idx],
-                    phantom
-                )
+                    states: states
+                        .as_ref()
+                        .map(|st| st.get(&ev).copied().unwrap_or(0)),
+                    phantom: !event.has_interface,
+                }
             })
-            .collect_vec().join(",\n");
-
-        // For each input and output that looks like:
-        // @[G+n, G+m] left: 32
-        // Generate the JSON information:
-        // {
-        //   "event": "G",
-        //   "name": "left",
-        //   "width": 32,
-        //   "start": n,
-        //   "end": m
-        // },
-        let pd_to_info = |(idx, p): (ir::PortIdx, &ir::Port)| {
-            let w = p.width.as_concrete(main).unwrap();
-            assert!(
-                idx.is_not_bundle(main),
-                "Main component cannot have bundle ports in signature."
-            );
-            let ir::Liveness { range, .. } = &p.live;
-            let start = main.get(range.start);
-            let end = main.get(range.end);
-
-            assert!(
-                start.event == end.event,
-                "Range `{}` cannot be represented as a simple offset",
-                main.display(range)
-            );
-
-            format!(
-                "{{ \"event\": \"{event}\", \"name\": \"{name}\", \"width\": {w} , \"start\": {st}, \"end\": {end} }}",
-                event = src_info.events.get(start.event),
-                name = src_info.ports.get(idx),
-                st = start.offset.as_concrete(main).unwrap(),
-                end = end.offset.as_concrete(main).unwrap(),
-            )
+            .collect();
+
+        let port = |(idx, p): (ir::PortIdx, &ir::Port)| {
+            let ir::Liveness { idxs, lens, range } = &p.live;
+            let start = comp.get(range.start);
+            let end = comp.get(range.end);
+            let bundle = (!idx.is_not_bundle(comp)).then(|| Bundle {
+                index: idxs.iter().map(|i| param_name(*i)).collect(),
+                dims: lens.iter().map(|l| Value::expr(*l, comp)).collect(),
+            });
+            PortInterface {
+                name: src_info
+                    .and_then(|si| si.ports.find(idx))
+                    .map(|n| n.to_string())
+                    .or_else(|| p.info.get_name(comp))
+                    .unwrap_or_else(|| comp.display(idx)),
+                event: Self::event_name(comp, start.event),
+                width: Value::expr(p.width, comp),
+                start: Value::expr(start.offset, comp),
+                end: Value::expr(end.offset, comp),
+                end_event: (start.event != end.event)
+                    .then(|| Self::event_name(comp, end.event)),
+                bundle,
+            }
+        };
+
+        let kind = match (comp.is_ext(), &comp.mono_src) {
+            (false, _) => "source",
+            (true, None) => "external",
+            (true, Some(_)) => "generated",
         };
 
-        let inputs = main.inputs().map(pd_to_info).collect_vec().join(",\n");
-        let outputs = main.outputs().map(pd_to_info).collect_vec().join(",\n");
+        CompInterface {
+            name: ng.comp_name(idx, self.ctx),
+            source: comp.def_name.to_string(),
+            kind,
+            entrypoint: self.ctx.is_main(idx),
+            params,
+            exists,
+            interfaces,
+            inputs: comp.inputs().map(port).collect(),
+            outputs: comp.outputs().map(port).collect(),
+            unannotated: comp
+                .unannotated_ports
+                .iter()
+                .map(|(name, width)| Unannotated {
+                    name: name.to_string(),
+                    width: *width,
+                })
+                .collect(),
+        }
+    }
+
+    /// Add the declared range of a parameter of an external component.
+    /// Concrete bounds of several ranges are intersected and symbolic bounds
+    /// are only shown when there are no concrete ones.
+    fn range(bind: Binding, p: ir::ParamIdx, comp: &ir::Component) -> Binding {
+        let (mut min, mut max) = (None, None);
+        let (mut sym_min, mut sym_max) = (None, None);
+        let mut pow2 = false;
+        for r in &comp.get(p).ranges {
+            let ir::ParamRange::Bounded { lo, hi } = r else {
+                pow2 = true;
+                continue;
+            };
+            match lo.as_big(comp) {
+                Some(lo) => min = min.into_iter().chain([lo]).max(),
+                None => {
+                    sym_min.get_or_insert_with(|| comp.display(*lo));
+                }
+            }
+            match hi.as_big(comp) {
+                Some(hi) => max = max.into_iter().chain([hi]).min(),
+                None => {
+                    sym_max.get_or_insert_with(|| comp.display(*hi));
+                }
+            }
+        }
+        bind.with_range(
+            min.map(Value::big).or(sym_min.map(Value::Expr)),
+            max.map(Value::big).or(sym_max.map(Value::Expr)),
+            pow2,
+        )
+    }
+
+    /// Source-level name of an event
+    fn event_name(comp: &ir::Component, ev: ir::EventIdx) -> String {
+        comp.src_info
+            .as_ref()
+            .and_then(|si| si.events.find(ev))
+            .map(|n| n.to_string())
+            .or_else(|| comp.get(ev).info.get_name(comp))
+            .unwrap_or_else(|| comp.display(ev))
+    }
 
-        // Look ma, a JSON serializer!
-        println!(
-            "{{\n\"interfaces\": [\n{interfaces}\n],\n\"inputs\": [\n{inputs}\n],\n\"outputs\": [\n{outputs}\n]\n}}",
-        );
+    /// Number of states in the FSM of each event. Computed the same way as
+    /// [crate::ir_passes::lower::max_states] but without requiring bundles to
+    /// be eliminated first.
+    fn states(comp: &ir::Component) -> HashMap<ir::EventIdx, u64> {
+        // Offsets of bundles are computed by substituting their indices which
+        // adds expressions to the component.
+        let mut scratch = comp.clone();
+        let mut states = HashMap::new();
+        for (idx, port) in comp.ports().iter() {
+            if matches!(port.owner, ir::PortOwner::Local) {
+                continue;
+            }
+            let ir::Liveness { idxs, lens, range } = &port.live;
+            let end = comp.get(range.end);
+            let lens =
+                lens.iter().map(|l| l.concrete(comp) as usize).collect_vec();
+            let offsets = if idx.is_not_bundle(comp) {
+                vec![end.offset.concrete(comp)]
+            } else {
+                (0..lens.iter().product())
+                    .map(|i| {
+                        let bind = ir::Bind::new(
+                            utils::nd_idx(i, &lens)
+                                .into_iter()
+                                .zip_eq(idxs)
+                                .map(|(v, idx)| (*idx, scratch.num(v as u64))),
+                        );
+                        ir::Subst::new(end.offset, &bind)
+                            .apply(&mut scratch)
+                            .concrete(&scratch)
+                    })
+                    .collect_vec()
+            };
+            let max = states.entry(end.event).or_insert(0);
+            *max = offsets.into_iter().fold(*max, u64::max);
+        }
+        states
     }
 }
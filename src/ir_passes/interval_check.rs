@@ -1,5 +1,5 @@
 use crate::ir_visitor::{Action, Visitor, VisitorData};
-use fil_ir::{self as ir, AddCtx, Ctx};
+use fil_ir::{self as ir, AddCtx, Ctx, DisplayCtx};
 use fil_utils::GPosIdx;
 use itertools::Itertools;
 
@@ -185,6 +185,7 @@ impl Visitor for IntervalCheck {
                     delay_loc,
                     live_loc,
                     len.clone(),
+                    delay.clone(),
                     param_info,
                 )
                 .into(),
@@ -351,6 +352,7 @@ impl Visitor for IntervalCheck {
                 src_loc,
                 dst_t.range,
                 src_t.range,
+                (comp.display(dst.port), comp.display(src.port)),
             )
             .into(),
         );
@@ -0,0 +1,226 @@
+use super::dump_interface::{CompInterface, DumpInterface, PortInterface};
+use crate::ir_passes::lower::INTERFACE_PORTS;
+use fil_ir as ir;
+use fil_utils::{Diagnostics, Error};
+use std::fmt::Write;
+
+/// Namespace of the IP-XACT standard (IEEE 1685-2014)
+const IPXACT_NS: &str = "http://www.accellera.org/XMLSchema/IPXACT/1685-2014";
+/// Namespace of the vendor extensions that carry Filament timing information
+const FILAMENT_NS: &str = "https://filamenthdl.com/ip-xact";
+
+/// Emits an IP-XACT component description of the toplevel component.
+///
+/// Ports are described in the order the Verilog backend generates them. The
+/// events of the component and the liveness of its ports are recorded as
+/// vendor extensions:
+/// ```xml
+/// <filament:event name="G" delay="1" states="2" interface="go"/>
+/// <filament:interface event="G"/>
+/// <filament:liveness event="G" start="0" end="1"/>
+/// ```
+pub struct IpXact {
+    out: String,
+    indent: usize,
+}
+
+impl IpXact {
+    /// Print the IP-XACT description of the toplevel component
+    pub fn print(ctx: &ir::Context) -> Result<(), u64> {
+        let mut diag = Diagnostics::default();
+        let Some(idx) = ctx.entrypoint else {
+            diag.add_error(Error::misc(
+                "--emit ip-xact requires a toplevel component".to_string(),
+            ));
+            return Err(diag.report_all().unwrap());
+        };
+        let interface = DumpInterface::interface(ctx, idx);
+        for port in interface.inputs.iter().chain(&interface.outputs) {
+            if port.bundle.is_some() {
+                diag.add_error(Error::misc(format!(
+                    "bundle port `{}' in the toplevel signature cannot be described in IP-XACT",
+                    port.name
+                )));
+            }
+        }
+        if let Some(errs) = diag.report_all() {
+            return Err(errs);
+        }
+
+        let mut ipx = IpXact {
+            out: String::new(),
+            indent: 0,
+        };
+        ipx.component(&interface);
+        print!("{}", ipx.out);
+        Ok(())
+    }
+
+    /// Write a line at the current indentation
+    fn line(&mut self, line: impl AsRef<str>) {
+        writeln!(self.out, "{:1$}{2}", "", self.indent * 2, line.as_ref())
+            .unwrap();
+    }
+
+    /// Write `<tag>` and indent the following lines
+    fn open(&mut self, tag: &str) {
+        self.line(format!("<{tag}>"));
+        self.indent += 1;
+    }
+
+    /// Write `</tag>` and dedent
+    fn close(&mut self, tag: &str) {
+        self.indent -= 1;
+        self.line(format!("</{tag}>"));
+    }
+
+    /// Write `<tag>text</tag>`
+    fn elem(&mut self, tag: &str, text: impl ToString) {
+        self.line(format!("<{tag}>{}</{tag}>", escape(&text.to_string())));
+    }
+
+    /// Write an empty element with attributes
+    fn empty(&mut self, tag: &str, attrs: &[(&str, String)]) {
+        let attrs = attrs
+            .iter()
+            .map(|(k, v)| format!(" {k}=\"{}\"", escape(v)))
+            .collect::<String>();
+        self.line(format!("<{tag}{attrs}/>"));
+    }
+
+    fn component(&mut self, comp: &CompInterface) {
+        self.line(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        self.line(format!(
+            r#"<ipxact:component xmlns:ipxact="{IPXACT_NS}" xmlns:filament="{FILAMENT_NS}">"#
+        ));
+        self.indent += 1;
+        self.elem("ipxact:vendor", "filamenthdl.com");
+        self.elem("ipxact:library", "filament");
+        self.elem("ipxact:name", &comp.name);
+        self.elem("ipxact:version", "1.0");
+
+        self.open("ipxact:model");
+        self.open("ipxact:views");
+        self.open("ipxact:view");
+        self.elem("ipxact:name", "rtl");
+        self.elem("ipxact:componentInstantiationRef", "verilog");
+        self.close("ipxact:view");
+        self.close("ipxact:views");
+        self.open("ipxact:instantiations");
+        self.open("ipxact:componentInstantiation");
+        self.elem("ipxact:name", "verilog");
+        self.elem("ipxact:language", "systemverilog");
+        self.elem("ipxact:moduleName", &comp.name);
+        self.close("ipxact:componentInstantiation");
+        self.close("ipxact:instantiations");
+
+        self.open("ipxact:ports");
+        for ev in &comp.interfaces {
+            if let Some(name) = &ev.name {
+                let attrs = [("event", ev.event.clone())];
+                self.port(name, "in", 1, Some(("filament:interface", &attrs)));
+            }
+        }
+        // The backend adds the clock and reset ports unless the signature
+        // already defines them.
+        for (_, (name, width, _)) in INTERFACE_PORTS.iter() {
+            let defined = comp
+                .unannotated
+                .iter()
+                .map(|p| &p.name)
+                .chain(comp.inputs.iter().map(|p| &p.name))
+                .any(|n| n == name);
+            if !defined {
+                self.port(name, "in", *width, None);
+            }
+        }
+        for p in &comp.unannotated {
+            self.port(&p.name, "in", p.width, None);
+        }
+        for (dir, ports) in [("in", &comp.inputs), ("out", &comp.outputs)] {
+            for p in ports {
+                self.data_port(dir, p);
+            }
+        }
+        self.close("ipxact:ports");
+        self.close("ipxact:model");
+
+        self.open("ipxact:vendorExtensions");
+        for ev in &comp.interfaces {
+            let mut attrs = vec![
+                ("name", ev.event.clone()),
+                ("delay", ev.delay.to_string()),
+            ];
+            if let Some(states) = ev.states {
+                attrs.push(("states", states.to_string()));
+            }
+            if let Some(interface) = &ev.name {
+                attrs.push(("interface", interface.clone()));
+            }
+            self.empty("filament:event", &attrs);
+        }
+        for p in &comp.params {
+            let mut attrs = vec![("name", p.name.clone())];
+            if let Some(v) = &p.value {
+                attrs.push(("value", v.to_string()));
+            }
+            self.empty("filament:param", &attrs);
+        }
+        self.close("ipxact:vendorExtensions");
+        self.indent -= 1;
+        self.line("</ipxact:component>");
+    }
+
+    /// A port of the component with an optional vendor extension element
+    fn port(
+        &mut self,
+        name: &str,
+        dir: &str,
+        width: u64,
+        ext: Option<(&str, &[(&str, String)])>,
+    ) {
+        self.open("ipxact:port");
+        self.elem("ipxact:name", name);
+        self.open("ipxact:wire");
+        self.elem("ipxact:direction", dir);
+        if width > 1 {
+            self.open("ipxact:vectors");
+            self.open("ipxact:vector");
+            self.elem("ipxact:left", width - 1);
+            self.elem("ipxact:right", 0);
+            self.close("ipxact:vector");
+            self.close("ipxact:vectors");
+        }
+        self.close("ipxact:wire");
+        if let Some((tag, attrs)) = ext {
+            self.open("ipxact:vendorExtensions");
+            self.empty(tag, attrs);
+            self.close("ipxact:vendorExtensions");
+        }
+        self.close("ipxact:port");
+    }
+
+    /// A port from the signature with its liveness
+    fn data_port(&mut self, dir: &str, p: &PortInterface) {
+        let width = p.width.to_string().parse().unwrap_or_else(|_| {
+            unreachable!("width of toplevel port `{}' is not concrete", p.name)
+        });
+        let mut attrs = vec![
+            ("event", p.event.clone()),
+            ("start", p.start.to_string()),
+            ("end", p.end.to_string()),
+        ];
+        if let Some(ev) = &p.end_event {
+            attrs.push(("end_event", ev.clone()));
+        }
+        self.port(&p.name, dir, width, Some(("filament:liveness", &attrs)));
+    }
+}
+
+/// Escape the characters that cannot appear in XML text and attributes
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
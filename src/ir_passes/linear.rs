@@ -0,0 +1,442 @@
+use fil_ast as ast;
+use fil_ir::{self as ir, Ctx};
+use fil_utils::{BigInt, BigIntExt, BigUint, Integer, One, Signed, Zero};
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap};
+
+/// Largest number of disjuncts generated when normalizing a proposition
+const MAX_DISJUNCTS: usize = 64;
+/// Largest number of constraints generated while eliminating variables
+const MAX_CONSTRAINTS: usize = 256;
+/// Largest number of steps, i.e., constraints visited or combined, taken
+/// while refuting a proposition. Propositions that require more are left to
+/// the solver.
+const MAX_STEPS: usize = 1024;
+
+/// Variables in a linear term
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) enum Var {
+    Param(ir::ParamIdx),
+    Event(ir::EventIdx),
+    /// A non-linear expression treated as an opaque variable
+    Opaque(ir::ExprIdx),
+}
+
+/// A linear term: sum of `coeff * var` plus a constant
+#[derive(Clone)]
+pub(super) struct Term {
+    pub coeffs: BTreeMap<Var, BigInt>,
+    pub k: BigInt,
+}
+
+impl Term {
+    pub fn constant(k: BigInt) -> Self {
+        Self {
+            coeffs: BTreeMap::new(),
+            k,
+        }
+    }
+
+    fn var(v: Var) -> Self {
+        Self {
+            coeffs: BTreeMap::from([(v, 1u64.into())]),
+            k: BigInt::zero(),
+        }
+    }
+
+    pub fn as_const(&self) -> Option<&BigInt> {
+        self.coeffs.is_empty().then_some(&self.k)
+    }
+
+    /// Compute `self + c * other`
+    pub fn add_scaled(mut self, c: &BigInt, other: &Term) -> Self {
+        for (v, n) in &other.coeffs {
+            let n = n * c;
+            let sum = match self.coeffs.get(v) {
+                Some(m) => m + &n,
+                None => n,
+            };
+            if sum.is_zero() {
+                self.coeffs.remove(v);
+            } else {
+                self.coeffs.insert(*v, sum);
+            }
+        }
+        self.k = &self.k + &(&other.k * c);
+        self
+    }
+
+    pub fn scale(&self, c: &BigInt) -> Self {
+        Term::constant(BigInt::zero()).add_scaled(c, self)
+    }
+
+    pub fn sub(self, other: &Term) -> Self {
+        self.add_scaled(&BigInt::from(-1i64), other)
+    }
+
+    /// Greatest common divisor of the coefficients. Zero if the term is
+    /// constant.
+    pub fn coeff_gcd(&self) -> BigUint {
+        self.coeffs
+            .values()
+            .fold(BigUint::zero(), |g, c| g.gcd(c.magnitude()))
+    }
+
+    /// Returns a term with the same integer solutions for `self >= 0` whose
+    /// coefficients do not have a common divisor.
+    pub fn tighten(mut self) -> Self {
+        let g = self.coeff_gcd();
+        if g.is_zero() || g.is_one() {
+            return self;
+        }
+        let g: BigInt = g.into();
+        for c in self.coeffs.values_mut() {
+            *c = c.div_floor(&g);
+        }
+        self.k = self.k.div_floor(&g);
+        self
+    }
+}
+
+/// A disjunction of conjunctions of constraints of the form `term >= 0`
+type Dnf = Vec<Vec<Term>>;
+
+/// An in-process decision procedure for propositions over linear integer
+/// arithmetic. Non-linear expressions are treated as opaque variables.
+/// Also used to compute the linear forms of comparisons.
+///
+/// Validity is checked by refuting the negation of a proposition using
+/// Fourier-Motzkin elimination. The procedure is sound but incomplete: if a
+/// proposition is reported valid, it holds for all integer values of the
+/// parameters and events. Otherwise, the proposition must be checked by the
+/// solver.
+pub(super) struct Linear<'a> {
+    comp: &'a ir::Component,
+    /// Replace let-bound parameters with their bindings. Otherwise, only
+    /// parameters bound to constants are replaced.
+    inline_lets: bool,
+    /// Linear forms of expressions that have already been computed
+    memo: HashMap<ir::ExprIdx, Term>,
+    /// Normal forms of propositions that have already been computed. Facts
+    /// of a component share their path conditions.
+    dnfs: HashMap<(ir::PropIdx, bool), Option<Dnf>>,
+}
+
+impl<'a> Linear<'a> {
+    pub fn new(comp: &'a ir::Component) -> Self {
+        Self {
+            comp,
+            inline_lets: true,
+            memo: HashMap::new(),
+            dnfs: HashMap::new(),
+        }
+    }
+
+    /// Keep let-bound parameters that are not bound to constants as
+    /// variables.
+    pub fn keep_lets(mut self) -> Self {
+        self.inline_lets = false;
+        self
+    }
+
+    /// The comparison performed by an atomic proposition along with the
+    /// difference between its left and right hand sides. Returns `None` for
+    /// propositions that are not comparisons.
+    pub fn cmp_diff(&mut self, prop: ir::PropIdx) -> Option<(ir::Cmp, Term)> {
+        let (op, l, r) = match self.comp.get(prop) {
+            ir::Prop::Cmp(ir::CmpOp { op, lhs, rhs }) => {
+                (op, self.expr(*lhs), self.expr(*rhs))
+            }
+            ir::Prop::TimeCmp(ir::CmpOp { op, lhs, rhs }) => {
+                (op, self.time(*lhs), self.time(*rhs))
+            }
+            ir::Prop::TimeSubCmp(ir::CmpOp { op, lhs, rhs }) => {
+                (op, self.time_sub(lhs), self.time_sub(rhs))
+            }
+            _ => return None,
+        };
+        Some((op.clone(), l.sub(&r)))
+    }
+
+    /// Returns true if the proposition is valid. A `false` result means that
+    /// the procedure could not prove the proposition, either because it is
+    /// incomplete or because the proof required more than [MAX_STEPS] steps.
+    pub fn valid(&mut self, prop: ir::PropIdx) -> bool {
+        let Some(neg) = self.dnf(prop, false) else {
+            return false;
+        };
+        let mut steps = MAX_STEPS;
+        neg.into_iter().all(|cons| infeasible(cons, &mut steps))
+    }
+
+    fn expr(&mut self, e: ir::ExprIdx) -> Term {
+        if let Some(t) = self.memo.get(&e) {
+            return t.clone();
+        }
+        let term = match self.comp.get(e) {
+            ir::Expr::Param(p) => match &self.comp.get(*p).owner {
+                // Let-bound parameters are equal to their binding
+                ir::ParamOwner::Let { bind } => {
+                    let t = self.expr(*bind);
+                    if self.inline_lets || t.as_const().is_some() {
+                        t
+                    } else {
+                        Term::var(Var::Param(*p))
+                    }
+                }
+                _ => Term::var(Var::Param(*p)),
+            },
+            ir::Expr::Concrete(n) => Term::constant(n.clone()),
+            ir::Expr::Bin { op, lhs, rhs } => {
+                let l = self.expr(*lhs);
+                let r = self.expr(*rhs);
+                match op {
+                    ast::Op::Add => l.add_scaled(&1u64.into(), &r),
+                    ast::Op::Sub => l.sub(&r),
+                    ast::Op::Mul => match (l.as_const(), r.as_const()) {
+                        (Some(c), _) => r.scale(c),
+                        (_, Some(c)) => l.scale(c),
+                        (None, None) => Term::var(Var::Opaque(e)),
+                    },
+                    ast::Op::Div | ast::Op::Mod => {
+                        // Division by zero is left unevaluated
+                        let res = l
+                            .as_const()
+                            .zip(r.as_const())
+                            .and_then(|(l, r)| l.checked_div_rem_euclid(r));
+                        match (res, op) {
+                            (Some((q, _)), ast::Op::Div) => Term::constant(q),
+                            (Some((_, m)), _) => Term::constant(m),
+                            (None, _) => Term::var(Var::Opaque(e)),
+                        }
+                    }
+                }
+            }
+            ir::Expr::Fn { op, args } => {
+                let args = args
+                    .iter()
+                    .map(|a| self.expr(*a).as_const().cloned())
+                    .collect::<Option<Vec<_>>>();
+                match args.and_then(|args| op.eval(args)) {
+                    Some(v) => Term::constant(v),
+                    None => Term::var(Var::Opaque(e)),
+                }
+            }
+        };
+        self.memo.insert(e, term.clone());
+        term
+    }
+
+    fn time(&mut self, t: ir::TimeIdx) -> Term {
+        let ir::Time { event, offset } = self.comp.get(t);
+        let ev = Term::var(Var::Event(*event));
+        self.expr(*offset).add_scaled(&1u64.into(), &ev)
+    }
+
+    fn time_sub(&mut self, ts: &ir::TimeSub) -> Term {
+        match ts {
+            ir::TimeSub::Unit(e) => self.expr(*e),
+            ir::TimeSub::Sym { l, r } => {
+                let r = self.time(*r);
+                self.time(*l).sub(&r)
+            }
+        }
+    }
+
+    /// Constraints equivalent to `d op 0` if `positive`, or its negation
+    /// otherwise.
+    fn cmp(op: &ir::Cmp, d: Term, positive: bool) -> Dnf {
+        let one = BigInt::from(1u64);
+        let minus_one = BigInt::from(-1i64);
+        // `d - 1 >= 0` and `-d - 1 >= 0`
+        let gt = |d: &Term| d.clone().sub(&Term::constant(one.clone()));
+        let lt =
+            |d: &Term| d.scale(&minus_one).sub(&Term::constant(one.clone()));
+        match (op, positive) {
+            (ir::Cmp::Gt, true) => vec![vec![gt(&d)]],
+            (ir::Cmp::Gt, false) => vec![vec![d.scale(&minus_one)]],
+            (ir::Cmp::Gte, true) => vec![vec![d]],
+            (ir::Cmp::Gte, false) => vec![vec![lt(&d)]],
+            (ir::Cmp::Eq, true) => vec![vec![d.scale(&minus_one), d]],
+            (ir::Cmp::Eq, false) => vec![vec![gt(&d)], vec![lt(&d)]],
+        }
+    }
+
+    /// Disjunctive normal form of the proposition if `positive` or its
+    /// negation otherwise. Returns `None` if the normal form is too large.
+    fn dnf(&mut self, prop: ir::PropIdx, positive: bool) -> Option<Dnf> {
+        if let Some(dnf) = self.dnfs.get(&(prop, positive)) {
+            return dnf.clone();
+        }
+        let dnf = self.compute_dnf(prop, positive);
+        self.dnfs.insert((prop, positive), dnf.clone());
+        dnf
+    }
+
+    fn compute_dnf(
+        &mut self,
+        prop: ir::PropIdx,
+        positive: bool,
+    ) -> Option<Dnf> {
+        let dnf = match self.comp.get(prop) {
+            ir::Prop::True | ir::Prop::False => {
+                let is_true = matches!(self.comp.get(prop), ir::Prop::True);
+                if is_true == positive {
+                    vec![vec![]]
+                } else {
+                    vec![]
+                }
+            }
+            ir::Prop::Cmp(_)
+            | ir::Prop::TimeCmp(_)
+            | ir::Prop::TimeSubCmp(_) => {
+                let (op, d) = self.cmp_diff(prop).unwrap();
+                Self::cmp(&op, d, positive)
+            }
+            ir::Prop::Not(p) => self.dnf(*p, !positive)?,
+            ir::Prop::And(l, r)
+            | ir::Prop::Or(l, r)
+            | ir::Prop::Implies(l, r) => {
+                let (l, r) = (*l, *r);
+                // Polarity of the operands and whether the result is a
+                // conjunction of the operands
+                let (l_pos, conj) = match self.comp.get(prop) {
+                    ir::Prop::And(..) => (positive, positive),
+                    ir::Prop::Or(..) => (positive, !positive),
+                    _ => (!positive, !positive),
+                };
+                let l = self.dnf(l, l_pos)?;
+                let r = self.dnf(r, positive)?;
+                if conj {
+                    product(l, r)?
+                } else {
+                    let mut l = l;
+                    l.extend(r);
+                    l
+                }
+            }
+        };
+        (dnf.len() <= MAX_DISJUNCTS).then_some(dnf)
+    }
+}
+
+/// Conjunction of two formulas in disjunctive normal form. Returns `None` if
+/// the result has more than [MAX_DISJUNCTS] disjuncts or more constraints
+/// than can be visited in [MAX_STEPS] steps.
+fn product(l: Dnf, r: Dnf) -> Option<Dnf> {
+    let size = |dnf: &Dnf| dnf.iter().map(Vec::len).sum::<usize>();
+    if l.len() * r.len() > MAX_DISJUNCTS
+        || size(&l) * r.len() + size(&r) * l.len() > MAX_STEPS
+    {
+        return None;
+    }
+    Some(
+        l.iter()
+            .flat_map(|lc| {
+                r.iter()
+                    .map(move |rc| lc.iter().chain(rc).cloned().collect())
+            })
+            .collect(),
+    )
+}
+
+/// Constraints of the form `term >= 0` indexed by the coefficients of the
+/// term. Constraints with the same coefficients are subsumed by the one with
+/// the smallest constant so only that constant is kept.
+type Constraints = BTreeMap<BTreeMap<Var, BigInt>, BigInt>;
+
+/// Add the constraint `c >= 0` to `cons`. Constraints that are trivially true
+/// are dropped. Returns true if the constraint is trivially false.
+fn insert(cons: &mut Constraints, c: Term) -> bool {
+    let Term { coeffs, k } = c.tighten();
+    if coeffs.is_empty() {
+        return k.is_negative();
+    }
+    match cons.entry(coeffs) {
+        Entry::Vacant(e) => {
+            e.insert(k);
+        }
+        Entry::Occupied(mut e) => {
+            if k < *e.get() {
+                e.insert(k);
+            }
+        }
+    }
+    false
+}
+
+/// Returns true if the conjunction of constraints has no rational (and
+/// therefore no integer) solutions. A `false` result means the procedure
+/// could not refute the constraints within the remaining `steps`, which
+/// are decremented by the number of constraints visited and combined.
+fn infeasible(terms: Vec<Term>, steps: &mut usize) -> bool {
+    let mut cons = Constraints::new();
+    for t in terms {
+        if insert(&mut cons, t) {
+            return true;
+        }
+    }
+    loop {
+        if cons.is_empty() || cons.len() > *steps {
+            return false;
+        }
+        // Each round visits every remaining constraint
+        *steps -= cons.len();
+
+        // Number of constraints in which each variable has a positive and a
+        // negative coefficient.
+        let mut occurs: BTreeMap<Var, (usize, usize)> = BTreeMap::new();
+        for coeffs in cons.keys() {
+            for (v, c) in coeffs {
+                let (p, n) = occurs.entry(*v).or_default();
+                if c.is_negative() {
+                    *n += 1;
+                } else {
+                    *p += 1;
+                }
+            }
+        }
+        // Constraints mentioning a variable that only has coefficients of one
+        // sign can always be satisfied by picking a large enough value for it
+        // and are eliminated together.
+        let one_sided = occurs
+            .iter()
+            .filter(|(_, (p, n))| *p == 0 || *n == 0)
+            .map(|(v, _)| *v)
+            .collect::<BTreeSet<_>>();
+        if !one_sided.is_empty() {
+            cons.retain(|coeffs, _| {
+                !coeffs.keys().any(|v| one_sided.contains(v))
+            });
+            continue;
+        }
+        // Eliminate the variable that generates the fewest constraints
+        let (var, (p, n)) =
+            occurs.into_iter().min_by_key(|(_, (p, n))| p * n).unwrap();
+        let combined = p * n;
+        if cons.len() - p - n + combined > MAX_CONSTRAINTS || combined > *steps
+        {
+            return false;
+        }
+        *steps -= combined;
+
+        let (with, without): (Constraints, Constraints) =
+            std::mem::take(&mut cons)
+                .into_iter()
+                .partition(|(coeffs, _)| coeffs.contains_key(&var));
+        let (neg, pos): (Vec<_>, Vec<_>) = with
+            .into_iter()
+            .map(|(coeffs, k)| Term { coeffs, k })
+            .partition(|c| c.coeffs[&var].is_negative());
+        cons = without;
+        for p in &pos {
+            for n in &neg {
+                // Both coefficients of `var` cancel out
+                let cp = p.coeffs[&var].clone();
+                let cn = -n.coeffs[&var].clone();
+                if insert(&mut cons, p.scale(&cn).add_scaled(&cp, n)) {
+                    return true;
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use super::fsm::{FsmBind, FsmType};
+use super::source_map;
 use super::utils::{cell_to_port_def, NameGenerator};
 use super::Fsm;
 use calyx_ir::{self as calyx, RRC};
 use fil_ir::{self as ir, Ctx, DenseIndexInfo, DisplayCtx};
+use fil_utils::{BigInt, ToPrimitive};
 use itertools::Itertools;
 use std::{collections::HashMap, rc::Rc};
 
@@ -44,6 +46,8 @@ pub(super) struct BuildCtx<'a> {
     instances: DenseIndexInfo<ir::Instance, RRC<calyx::Cell>>,
     /// Mapping from [ir::InstIdx]s to a reference of the calyx cell instantiated/invoked
     invokes: DenseIndexInfo<ir::Invoke, RRC<calyx::Cell>>,
+    /// Source positions of the cells of the component
+    pub source: source_map::Module,
 }
 
 impl<'a> BuildCtx<'a> {
@@ -67,6 +71,7 @@ impl<'a> BuildCtx<'a> {
             instances: DenseIndexInfo::default(),
             invokes: DenseIndexInfo::default(),
             fsms: HashMap::new(),
+            source: source_map::Module::new(ctx, idx, ng),
         }
     }
 
@@ -86,11 +91,15 @@ impl<'a> BuildCtx<'a> {
         } else {
             // this instance must be referring to a primitive, so we add one to the component
 
-            // gets the parameters of this instance as concrete numbers
+            // gets the parameters of this instance as concrete numbers.
+            // Negative values use their two's complement representation.
             let conc_bind = inst
                 .args
                 .iter()
-                .map(|v| v.concrete(self.comp))
+                .map(|v| match v.as_big(self.comp).and_then(BigInt::to_i64) {
+                    Some(n) if n < 0 => n as u64,
+                    _ => v.concrete(self.comp),
+                })
                 .collect_vec();
             self.builder.add_primitive(inst_name, comp_name, &conc_bind)
         };
@@ -99,6 +108,11 @@ impl<'a> BuildCtx<'a> {
             .attributes
             .insert(calyx::BoolAttr::Data, 1);
 
+        let info: Option<&ir::info::Instance> = self.comp.get(inst.info).into();
+        if let Some(info) = info {
+            self.source.instance(Rc::clone(&cell), info.bind_loc);
+        }
+
         // add this instance to the instance mapping
         self.instances.push(idx, cell);
     }
@@ -242,6 +256,11 @@ impl<'a> BuildCtx<'a> {
 
             // Construct the FSM
             let fsm = Fsm::new(event, typ, self, self.ng);
+            let info: Option<&ir::info::Event> = self.comp.get(evt.info).into();
+            if let Some(info) = info {
+                let pos = info.interface_bind_loc.unwrap_or(info.bind_loc);
+                self.source.fsm(Rc::clone(fsm.cell()), pos);
+            }
             self.fsms.insert(event, fsm);
         }
     }
@@ -1,13 +1,15 @@
 use super::{
     build_ctx::{Binding, BuildCtx},
     max_states,
+    source_map::{self, SourceMap},
     utils::{NameGenerator, INTERFACE_PORTS},
 };
 use calyx_frontend as frontend;
 use calyx_ir as calyx;
 use calyx_utils::CalyxResult;
 use fil_ir::{self as ir, Ctx, Traversal};
-use std::{collections::HashSet, convert::identity, path::PathBuf, rc::Rc};
+use fil_utils::{Diagnostics, Error, Signed, ToPrimitive};
+use std::{convert::identity, path::PathBuf, rc::Rc};
 
 #[derive(Default)]
 /// Compiles Filament directly into Calyx
@@ -103,8 +105,8 @@ impl Compile {
             )
             .collect();
 
-        let mut interface_ports =
-            INTERFACE_PORTS.iter().collect::<HashSet<_>>();
+        // Kept in order so that the generated signatures are deterministic
+        let mut interface_ports = INTERFACE_PORTS.iter().collect::<Vec<_>>();
 
         // add interface port attributes if necessary
         for pd in &mut ports {
@@ -125,7 +127,7 @@ impl Compile {
                 // for [calyx::Width].
 
                 // Removes this interface port from the list so it is not added later on.
-                interface_ports.remove(pair);
+                interface_ports.retain(|p| *p != pair);
                 pd.attributes.insert(*attr, *value);
             }
         }
@@ -183,7 +185,8 @@ impl Compile {
         }
     }
 
-    /// Compiles an [ir::Component] into a [calyx::Component]
+    /// Compiles an [ir::Component] into a [calyx::Component] and records the
+    /// source positions of the cells it defines
     fn component(
         disable_slow_fsms: bool,
         ctx: &ir::Context,
@@ -191,7 +194,7 @@ impl Compile {
         bind: &mut Binding,
         lib: &calyx::LibrarySignatures,
         name_gen: &NameGenerator,
-    ) -> calyx::Component {
+    ) -> (calyx::Component, source_map::Module) {
         log::debug!("Compiling component {idx}");
         let comp = ctx.get(idx);
 
@@ -270,7 +273,8 @@ impl Compile {
             }
         }
 
-        component
+        let source = buildctx.source;
+        (component, source)
     }
 
     fn init(
@@ -298,12 +302,78 @@ impl Compile {
         Ok(ctx)
     }
 
-    /// Compiles filament into calyx
+    /// Calyx represents port widths and the parameters of primitives using
+    /// 64-bit numbers. Report values that do not fit.
+    /// Parameters of Filament components are not affected since they are
+    /// eliminated by monomorphization.
+    fn check_wide_values(ctx: &ir::Context) -> Result<(), u64> {
+        let mut diag = Diagnostics::default();
+        for (_, comp) in ctx.iter().filter(|(_, c)| !c.is_ext()) {
+            for (_, port) in comp.ports().iter() {
+                let msg = match port.width.as_big(comp) {
+                    Some(v) if v.is_negative() => {
+                        format!("port width `{v}' is negative")
+                    }
+                    Some(v) if v.to_u64().is_none() => {
+                        format!("port width `{v}' does not fit in 64 bits")
+                    }
+                    _ => continue,
+                };
+                let info: Option<&ir::info::Port> = comp.get(port.info).into();
+                let mut err = Error::misc(msg);
+                if let Some(info) = info {
+                    err = err.add_note(
+                        diag.add_info("port defined here", info.width_loc),
+                    );
+                }
+                diag.add_error(err);
+            }
+            for (_, inst) in comp.instances().iter() {
+                if !ctx.get(inst.comp).is_ext() {
+                    continue;
+                }
+                // Negative values are passed using their two's complement
+                // representation.
+                let too_wide = inst.args.iter().filter_map(|a| {
+                    a.as_big(comp).filter(|v| {
+                        v.to_u64().is_none() && v.to_i64().is_none()
+                    })
+                });
+                for v in too_wide {
+                    let info: Option<&ir::info::Instance> =
+                        comp.get(inst.info).into();
+                    let mut err = Error::misc(format!(
+                        "primitive parameter `{v}' does not fit in 64 bits"
+                    ));
+                    if let Some(info) = info {
+                        err =
+                            err.add_note(diag.add_info(
+                                "instance defined here",
+                                info.comp_loc,
+                            ));
+                    }
+                    err = err.add_note(diag.add_message(
+                        "parameters of external components are passed to the generated Verilog as 64-bit numbers; wider constants can be built using `Concat'",
+                    ));
+                    diag.add_error(err);
+                }
+            }
+        }
+        match diag.report_all() {
+            Some(errs) => Err(errs),
+            None => Ok(()),
+        }
+    }
+
+    /// Compiles filament into calyx along with a map from the generated
+    /// identifiers to their source positions
     pub fn compile(
         ctx: ir::Context,
         disable_slow_fsms: bool,
         debug: bool,
-    ) -> calyx::Context {
+    ) -> Result<(calyx::Context, SourceMap), u64> {
+        Compile::check_wide_values(&ctx)?;
+
         // Creates a map between the file name and the external components defined in that file
         let externals =
             ctx.externals.iter().map(|(k, v)| (k, v.clone())).collect();
@@ -315,13 +385,19 @@ impl Compile {
                 panic!("Error initializing calyx context: {:?}", e);
             });
 
+        // The toplevel component may have a name other than `main'
+        if let Some(idx) = ctx.entrypoint {
+            calyx_ctx.entrypoint = name_gen.comp_name(idx, &ctx).into();
+        }
+
         let mut bindings = Binding::default();
+        let mut source_map = SourceMap::default();
 
         let po = Traversal::from(ctx);
 
         // Compile the components in post-order.
         po.apply_pre_order(|ctx, idx| {
-            let comp = Compile::component(
+            let (comp, source) = Compile::component(
                 disable_slow_fsms,
                 ctx,
                 idx,
@@ -331,11 +407,12 @@ impl Compile {
             );
             bindings.insert(idx, Rc::clone(&comp.signature));
             calyx_ctx.components.push(comp);
+            source_map.add(source);
         });
 
         // add the fsm components to the calyx context
         calyx_ctx.components.extend(bindings.fsm_comps.take());
 
-        calyx_ctx
+        Ok((calyx_ctx, source_map))
     }
 }
@@ -586,6 +586,11 @@ impl Fsm {
         Fsm { cell, typ }
     }
 
+    /// The cell instantiating the FSM
+    pub fn cell(&self) -> &RRC<calyx::Cell> {
+        &self.cell
+    }
+
     /// Generates a guard that is active for a range of states from start to end.
     pub fn range_guard(
         &self,
@@ -1,10 +1,13 @@
 mod build_ctx;
 mod compile;
 mod fsm;
+mod source_map;
 mod utils;
 
 use build_ctx::BuildCtx;
 use fsm::{Fsm, FsmType};
 
 pub use compile::Compile;
+pub use source_map::SourceMap;
 pub use utils::max_states;
+pub(crate) use utils::{NameGenerator, INTERFACE_PORTS};
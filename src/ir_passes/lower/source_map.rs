@@ -0,0 +1,256 @@
+//! Map the identifiers of the generated Verilog back to the Filament source.
+//!
+//! The output has the shape:
+//! ```json
+//! {
+//!   "version": 1,
+//!   "modules": [{
+//!     "name": "main",              // name of the generated module
+//!     "source": "main",            // name of the source definition
+//!     "params": [{ "name": "W", "value": 32 }],
+//!     "identifiers": [{
+//!       "name": "inst0_out",       // identifier in the generated module
+//!       "kind": "wire",            // "port", "interface", "instance", "fsm", or "wire"
+//!       "component": "Add_32",     // module of the cell that defines the identifier
+//!       "file": "main.fil", "line": 4, "column": 5
+//!     }]
+//!   }]
+//! }
+//! ```
+//! Wires are the ports of instances and FSMs and point to the definition of
+//! the cell. Identifiers without a source position, such as the `clk` and
+//! `reset` ports added by the compiler, are omitted, as are the modules
+//! implementing FSMs.
+use crate::ir_passes::dump_interface::{Binding, Value};
+use calyx_ir::{self as calyx, RRC};
+use codespan_reporting::files::Files;
+use fil_ir::{self as ir, Ctx};
+use fil_utils::{GPosIdx, GlobalPositionTable};
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::NameGenerator;
+
+/// Version of the source map schema
+pub const SCHEMA_VERSION: u64 = 1;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    /// A port in the signature of the module
+    Port,
+    /// An interface port of an event
+    Interface,
+    /// An instance of a component
+    Instance,
+    /// The FSM reifying an event
+    Fsm,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Port => "port",
+            Kind::Interface => "interface",
+            Kind::Instance => "instance",
+            Kind::Fsm => "fsm",
+        }
+    }
+}
+
+/// A cell generated for the source
+struct Cell {
+    cell: RRC<calyx::Cell>,
+    kind: Kind,
+    pos: GPosIdx,
+}
+
+/// Source information of a generated component
+pub(super) struct Module {
+    name: String,
+    source: String,
+    params: Vec<Binding>,
+    ports: Vec<(String, Kind, GPosIdx)>,
+    cells: Vec<Cell>,
+}
+
+impl Module {
+    /// The signature of a component
+    pub fn new(
+        ctx: &ir::Context,
+        idx: ir::CompIdx,
+        ng: &NameGenerator,
+    ) -> Self {
+        let comp = ctx.get(idx);
+        let ports = comp
+            .ports()
+            .iter()
+            .filter(|(_, p)| p.is_sig())
+            .filter_map(|(pidx, p)| {
+                let info: Option<&ir::info::Port> = comp.get(p.info).into();
+                Some((
+                    ng.port_name(pidx, ctx, comp),
+                    Kind::Port,
+                    info?.bind_loc,
+                ))
+            })
+            .chain(comp.events().iter().filter_map(|(ev, event)| {
+                let info: Option<&ir::info::Event> =
+                    comp.get(event.info).into();
+                Some((
+                    ng.interface_name(ev, comp)?,
+                    Kind::Interface,
+                    info?.interface_bind_loc?,
+                ))
+            }))
+            .collect();
+        let params = comp
+            .mono_src
+            .iter()
+            .flat_map(|ms| ms.params.iter())
+            .map(|(n, v)| Binding::new(n.to_string(), Some(Value::big(v))))
+            .collect();
+        Module {
+            name: ng.comp_name(idx, ctx),
+            source: comp.def_name.to_string(),
+            params,
+            ports,
+            cells: vec![],
+        }
+    }
+
+    /// Add an instance defined at `pos`
+    pub fn instance(&mut self, cell: RRC<calyx::Cell>, pos: GPosIdx) {
+        self.cells.push(Cell {
+            cell,
+            kind: Kind::Instance,
+            pos,
+        });
+    }
+
+    /// Add the FSM of an event defined at `pos`
+    pub fn fsm(&mut self, cell: RRC<calyx::Cell>, pos: GPosIdx) {
+        self.cells.push(Cell {
+            cell,
+            kind: Kind::Fsm,
+            pos,
+        });
+    }
+}
+
+#[derive(Default)]
+/// Source positions of the identifiers generated by [super::Compile]
+pub struct SourceMap {
+    modules: Vec<Module>,
+}
+
+#[derive(Serialize)]
+struct JsonMap {
+    version: u64,
+    modules: Vec<JsonModule>,
+}
+
+#[derive(Serialize)]
+struct JsonModule {
+    name: String,
+    source: String,
+    params: Vec<Binding>,
+    identifiers: Vec<Identifier>,
+}
+
+#[derive(Serialize)]
+struct Identifier {
+    name: String,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    component: Option<String>,
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+impl SourceMap {
+    pub(super) fn add(&mut self, module: Module) {
+        self.modules.push(module);
+    }
+
+    /// Resolve a position into its file, line, and column
+    fn location(pos: GPosIdx) -> Option<(String, usize, usize)> {
+        let pos = pos.into_option()?;
+        let table = GlobalPositionTable::as_ref();
+        let data = table.get_pos(pos.0);
+        let files = table.files();
+        let file = files.name(data.file.get()).ok()?;
+        let loc = files.location(data.file.get(), data.start).ok()?;
+        Some((file.clone(), loc.line_number, loc.column_number))
+    }
+
+    /// Serialize the map for the final Calyx program `ctx`. Cells removed by
+    /// the Calyx passes are not part of the output.
+    pub fn to_json(&self, ctx: &calyx::Context) -> String {
+        let comps: HashMap<_, _> =
+            ctx.components.iter().map(|c| (c.name, c)).collect();
+        let modules = self
+            .modules
+            .iter()
+            .filter_map(|m| {
+                let comp = comps.get(&calyx::Id::from(m.name.as_str()))?;
+                let ident = |name: String, kind, component, pos| {
+                    let (file, line, column) = Self::location(pos)?;
+                    Some(Identifier {
+                        name,
+                        kind,
+                        component,
+                        file,
+                        line,
+                        column,
+                    })
+                };
+                let ports = m.ports.iter().filter_map(|(name, kind, pos)| {
+                    ident(name.clone(), kind.name(), None, *pos)
+                });
+                let cells = m
+                    .cells
+                    .iter()
+                    .filter(|c| {
+                        comp.cells.find(c.cell.borrow().name()).is_some()
+                    })
+                    .flat_map(|c| {
+                        let cell = c.cell.borrow();
+                        let component = cell.type_name().map(|n| n.to_string());
+                        // The Verilog backend declares a wire for every port
+                        // of the cell.
+                        let wires = cell
+                            .ports()
+                            .iter()
+                            .map(|p| {
+                                format!("{}_{}", cell.name(), p.borrow().name)
+                            })
+                            .sorted()
+                            .map(|w| (w, "wire"));
+                        std::iter::once((
+                            cell.name().to_string(),
+                            c.kind.name(),
+                        ))
+                        .chain(wires)
+                        .filter_map(|(name, kind)| {
+                            ident(name, kind, component.clone(), c.pos)
+                        })
+                        .collect_vec()
+                    });
+                Some(JsonModule {
+                    name: m.name.clone(),
+                    source: m.source.clone(),
+                    params: m.params.clone(),
+                    identifiers: ports.chain(cells).collect(),
+                })
+            })
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+            .collect();
+        serde_json::to_string_pretty(&JsonMap {
+            version: SCHEMA_VERSION,
+            modules,
+        })
+        .unwrap()
+    }
+}
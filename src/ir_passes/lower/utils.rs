@@ -7,7 +7,7 @@ use linked_hash_map::LinkedHashMap;
 
 type AttrPair = (calyx::Attribute, u64);
 /// A set of interface ports that are required for all components.
-pub(super) const INTERFACE_PORTS: [(AttrPair, (&str, u64, calyx::Direction));
+pub(crate) const INTERFACE_PORTS: [(AttrPair, (&str, u64, calyx::Direction));
     2] = [
     (
         (calyx::Attribute::Bool(calyx::BoolAttr::Clk), 1),
@@ -20,7 +20,7 @@ pub(super) const INTERFACE_PORTS: [(AttrPair, (&str, u64, calyx::Direction));
 ];
 
 /// Helper struct that generates names for [crate::utils::Idx]s given their [Ctx].
-pub(super) struct NameGenerator {
+pub(crate) struct NameGenerator {
     use_info: bool,
 }
 
@@ -60,7 +60,9 @@ impl NameGenerator {
             ir::Expr::Param(p) => calyx::Width::Param {
                 value: self.param_name(*p, comp).into(),
             },
-            ir::Expr::Concrete(val) => calyx::Width::Const { value: *val },
+            ir::Expr::Concrete(_) => calyx::Width::Const {
+                value: idx.concrete(comp),
+            },
             ir::Expr::Bin { .. } | ir::Expr::Fn { .. } => comp
                 .internal_error("Port width must be a parameter or constant."),
         }
@@ -112,10 +114,12 @@ impl NameGenerator {
 
     /// Returns the name of a [Component]
     pub fn comp_name(&self, idx: CompIdx, ctx: &impl Ctx<Component>) -> String {
-        ctx.get(idx)
-            .src_info
+        let comp = ctx.get(idx);
+        comp.src_info
             .as_ref()
-            .map(|src| src.name.to_string())
+            .map(|src| src.name)
+            .or_else(|| comp.mono_src.as_ref().and_then(|ms| ms.name))
+            .map(|name| name.to_string())
             .unwrap_or_else(|| format!("comp{}", idx.get()))
     }
 }
@@ -0,0 +1,173 @@
+use super::{
+    Assume, BuildDomination, Discharge, IntervalCheck, Simplify, TypeCheck,
+};
+use crate::{
+    cmdline,
+    ir_visitor::{Construct, Visitor},
+};
+use codespan_reporting::diagnostic::Diagnostic;
+use fil_ir::{self as ir, Ctx, MutCtx};
+use fil_utils::{Diagnostics, Error};
+
+/// Largest delay attempted before giving up on the search.
+const MAX_DELAY: u64 = 1 << 12;
+
+/// Searches for the smallest delay of an event in the toplevel component under
+/// which the component still type checks. The search doubles the delay until
+/// the component checks and then binary searches below it.
+///
+/// This is only correct if feasibility is monotone in the delay, i.e., if the
+/// component checks for some delay it also checks for every larger one. This
+/// holds because the delay of a component's own event only occurs as a lower
+/// bound in the obligations generated by [IntervalCheck]: it must be positive,
+/// at least the delay of the events of invoked components, at least the
+/// availability of bundles, and at least the length of instance borrows.
+/// Liveness and event constraints only mention times, never delays. If a
+/// future check bounds the delay from above, the search still reports a
+/// delay that checks while the delay one below it does not, but a smaller
+/// delay may also check.
+///
+/// Each attempt re-runs the checking passes on a copy of the toplevel
+/// component with the delay of the event replaced by a concrete value.
+pub struct MinDelay<'a> {
+    opts: &'a cmdline::Opts,
+    /// The program right after conversion to the IR
+    ctx: &'a ir::Context,
+    /// The component being searched
+    comp: ir::CompIdx,
+    /// The event whose delay is being minimized
+    event: ir::EventIdx,
+}
+
+impl<'a> MinDelay<'a> {
+    /// Search for the minimum delay of the event named by `--min-delay` and
+    /// report it along with the constraints that fail for smaller delays.
+    pub fn search(
+        opts: &'a cmdline::Opts,
+        ctx: &'a ir::Context,
+    ) -> Result<(), u64> {
+        let Some(name) = &opts.min_delay else {
+            return Ok(());
+        };
+        let name = name.trim_start_matches('\'');
+        let mut diag = Diagnostics::default();
+
+        if opts.unsafe_skip_discharge {
+            diag.add_error(Error::misc(
+                "--min-delay cannot be used with --unsafe-skip-discharge"
+                    .to_string(),
+            ));
+            return Err(diag.report_all().unwrap());
+        }
+        let Some(comp) = ctx.entrypoint else {
+            diag.add_error(Error::misc(format!(
+                "--min-delay requires the toplevel component `{}'",
+                opts.toplevel
+            )));
+            return Err(diag.report_all().unwrap());
+        };
+        let Some(event) = ctx.get(comp).events().idx_iter().find(|ev| {
+            let info = ctx.get(comp).get(*ev).info;
+            info.get_name(ctx.get(comp)).map_or(false, |n| n == name)
+        }) else {
+            diag.add_error(Error::misc(format!(
+                "component `{}' does not define event '{name}",
+                opts.toplevel
+            )));
+            return Err(diag.report_all().unwrap());
+        };
+
+        let search = MinDelay {
+            opts,
+            ctx,
+            comp,
+            event,
+        };
+
+        // Find a delay that works and then search below it
+        let mut hi = 1;
+        while !search.feasible(hi) {
+            if hi >= MAX_DELAY {
+                diag.add_error(Error::misc(format!(
+                    "component `{}' does not check for any delay of '{name} up to {MAX_DELAY}",
+                    opts.toplevel
+                )));
+                return Err(diag.report_all().unwrap());
+            }
+            hi *= 2;
+        }
+        let mut lo = hi / 2;
+        // Invariant: `lo` is infeasible (or zero) and `hi` is feasible
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if search.feasible(mid) {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+
+        println!("minimum delay of '{name} in `{}': {hi}", opts.toplevel);
+
+        // Report the constraints that prevent a smaller delay
+        if lo > 0 {
+            let comp = ctx.get(comp);
+            let &ir::info::Event { delay_loc, .. } =
+                comp.get(comp.get(event).info).into();
+            let note = Diagnostic::note().with_message(format!(
+                "a delay of {lo} for '{name} fails the following constraints"
+            ));
+            let note = if let Some(pos) = delay_loc.into_option() {
+                note.with_labels(vec![pos
+                    .primary()
+                    .with_message(format!("minimum delay is {hi}"))])
+            } else {
+                note
+            };
+            fil_utils::report_notes(&[note]);
+            // The errors are reported by the passes
+            let _ = search.check(lo, false);
+        }
+        Ok(())
+    }
+
+    /// Does the component check when the event has the given delay?
+    fn feasible(&self, delay: u64) -> bool {
+        log::info!("min-delay: attempting delay {delay}");
+        self.check(delay, true).is_ok()
+    }
+
+    /// Run the checking passes on the component with the given delay for the
+    /// event. Returns the number of errors if the component does not check.
+    /// If `quiet` is set, the failing assertions are not reported.
+    fn check(&self, delay: u64, quiet: bool) -> Result<(), u64> {
+        let mut ctx = self.ctx.clone();
+        let comp = ctx.get_mut(self.comp);
+        let delay = comp.num(delay);
+        comp.get_mut(self.event).delay = delay.into();
+
+        self.run::<BuildDomination>(&mut ctx)?;
+        self.run::<TypeCheck>(&mut ctx)?;
+        self.run::<IntervalCheck>(&mut ctx)?;
+        self.run::<Assume>(&mut ctx)?;
+        self.run::<Simplify>(&mut ctx)?;
+
+        let discharge = <Discharge as Construct>::from(self.opts, &mut ctx);
+        let mut discharge = if quiet { discharge.quiet() } else { discharge };
+        discharge.visit((self.comp, self.opts, &mut ctx).into());
+        match discharge.after_traversal() {
+            Some(errs) => Err(errs),
+            None => Ok(()),
+        }
+    }
+
+    /// Run a pass on only the component being searched.
+    fn run<V: Visitor>(&self, ctx: &mut ir::Context) -> Result<(), u64> {
+        let mut pass = <V as Construct>::from(self.opts, ctx);
+        pass.visit((self.comp, self.opts, &mut *ctx).into());
+        match pass.after_traversal() {
+            Some(errs) => Err(errs),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,25 +1,39 @@
 mod assignment_check;
 mod assume;
+mod auto_balance;
 mod build_domination;
 mod bundle_elim;
+mod bv_width;
 mod discharge;
 mod dump_interface;
 mod interval_check;
+mod ip_xact;
+mod linear;
 mod lower;
+mod min_delay;
 mod mono;
 mod phantom_check;
 mod prop_simplify;
+mod recheck_concrete;
+mod testbench;
+mod timing_checks;
 mod type_check;
 
 pub use assignment_check::AssignCheck;
 pub use assume::Assume;
+pub use auto_balance::AutoBalance;
 pub use build_domination::BuildDomination;
 pub use bundle_elim::BundleElim;
 pub use discharge::Discharge;
 pub use dump_interface::DumpInterface;
 pub use interval_check::IntervalCheck;
-pub use lower::Compile;
-pub use mono::Monomorphize;
+pub use ip_xact::IpXact;
+pub use lower::{Compile, SourceMap};
+pub use min_delay::MinDelay;
+pub use mono::{MangleMap, Monomorphize};
 pub use phantom_check::PhantomCheck;
 pub use prop_simplify::Simplify;
+pub use recheck_concrete::RecheckConcrete;
+pub use testbench::Testbench;
+pub use timing_checks::TimingChecks;
 pub use type_check::TypeCheck;
@@ -1,5 +1,6 @@
 use super::{Base, Underlying};
 use fil_ir as ir;
+use fil_utils::BigInt;
 use ir::SparseInfoMap;
 use itertools::Itertools;
 
@@ -7,15 +8,15 @@ use itertools::Itertools;
 /// A key defined by a component and all of its parameters.
 pub struct CompKey {
     pub comp: Underlying<ir::Component>,
-    pub params: Vec<u64>,
+    pub params: Vec<BigInt>,
 }
 impl CompKey {
-    pub fn new(comp: Underlying<ir::Component>, params: Vec<u64>) -> Self {
+    pub fn new(comp: Underlying<ir::Component>, params: Vec<BigInt>) -> Self {
         Self { comp, params }
     }
 }
-impl From<(Underlying<ir::Component>, Vec<u64>)> for CompKey {
-    fn from((comp, params): (Underlying<ir::Component>, Vec<u64>)) -> Self {
+impl From<(Underlying<ir::Component>, Vec<BigInt>)> for CompKey {
+    fn from((comp, params): (Underlying<ir::Component>, Vec<BigInt>)) -> Self {
         Self::new(comp, params)
     }
 }
@@ -38,7 +39,7 @@ pub struct InstanceInfo {
     /// Mapping from old events to new events, for resolving Foreigns
     event_map: SparseInfoMap<ir::Event, Base<ir::Event>, Underlying<ir::Event>>,
     /// Values of existentially quantified parameters
-    exist_param_vals: SparseInfoMap<ir::Param, u64, Underlying<ir::Param>>,
+    exist_param_vals: SparseInfoMap<ir::Param, BigInt, Underlying<ir::Param>>,
 }
 
 impl InstanceInfo {
@@ -71,7 +72,7 @@ impl InstanceInfo {
     }
 
     /// Add binding for an existentially quantified parameter
-    pub fn add_exist_val(&mut self, param: Underlying<ir::Param>, val: u64) {
+    pub fn add_exist_val(&mut self, param: Underlying<ir::Param>, val: BigInt) {
         assert!(
             !self.exist_param_vals.contains(param),
             "existential parameter already has a value"
@@ -80,14 +81,14 @@ impl InstanceInfo {
     }
 
     /// Get the value for an existentially quantified parameter
-    pub fn get_exist_val(&self, old: Underlying<ir::Param>) -> Option<u64> {
-        self.exist_param_vals.find(old).copied()
+    pub fn get_exist_val(&self, old: Underlying<ir::Param>) -> Option<BigInt> {
+        self.exist_param_vals.find(old).cloned()
     }
 
     /// Iterate over all existentially quantified parameters
     pub fn iter_exist_vals(
         &self,
-    ) -> impl Iterator<Item = (Underlying<ir::Param>, u64)> + '_ {
-        self.exist_param_vals.iter().map(|(ul, v)| (ul, *v))
+    ) -> impl Iterator<Item = (Underlying<ir::Param>, BigInt)> + '_ {
+        self.exist_param_vals.iter().map(|(ul, v)| (ul, v.clone()))
     }
 }
@@ -0,0 +1,137 @@
+//! Names of the components generated by monomorphization.
+//!
+//! The table written by [MangleMap] has the shape:
+//! ```json
+//! {
+//!   "version": 1,
+//!   "components": [{
+//!     "name": "Foo_8",           // name of the generated module
+//!     "source": "Foo",           // name of the source definition
+//!     "params": [{ "name": "W", "value": 8 }]
+//!   }]
+//! }
+//! ```
+use crate::cmdline::Mangle;
+use crate::ir_passes::dump_interface::{Binding, Value};
+use crate::ir_passes::lower::NameGenerator;
+use fil_ir as ir;
+use fil_utils::{BigInt, Diagnostics, Error, Signed};
+use itertools::Itertools;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+/// Version of the mapping table schema
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Name of the component `idx` generated by monomorphization under `scheme`
+pub(super) fn name(
+    scheme: Mangle,
+    idx: ir::CompIdx,
+    comp: &ir::Component,
+) -> String {
+    let params = comp
+        .mono_src
+        .as_ref()
+        .map(|ms| ms.params.iter().map(|(_, v)| v).collect_vec())
+        .unwrap_or_default();
+    let def = comp.def_name;
+    match scheme {
+        Mangle::Sequential => format!("comp{}", idx.get()),
+        _ if params.is_empty() => def.to_string(),
+        // Identifiers cannot contain `-` so negative values use a prefix
+        Mangle::Readable => {
+            let vals = params.iter().map(|v| value(v)).join("_");
+            format!("{def}_{vals}")
+        }
+        Mangle::Hashed => {
+            let vals = params.iter().map(|v| v.to_string()).join(",");
+            format!("{def}_{:08x}", fnv1a(vals.as_bytes()))
+        }
+    }
+}
+
+/// A parameter value that can be used in an identifier
+fn value(v: &BigInt) -> String {
+    if v.is_negative() {
+        format!("n{}", v.magnitude())
+    } else {
+        v.to_string()
+    }
+}
+
+/// 32-bit FNV-1a hash. Unlike the hashers in the standard library, the
+/// result is guaranteed to be the same across platforms and compiler
+/// versions.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, b| {
+        (hash ^ u32::from(*b)).wrapping_mul(0x01000193)
+    })
+}
+
+/// Describe the component generated for a definition and its parameters
+pub(super) fn describe(comp: &ir::Component) -> String {
+    match &comp.mono_src {
+        Some(ms) if !ms.params.is_empty() => format!(
+            "{}[{}]",
+            comp.def_name,
+            ms.params.iter().map(|(_, v)| v).join(", ")
+        ),
+        _ => comp.def_name.to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct Table {
+    version: u64,
+    components: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    name: String,
+    source: String,
+    params: Vec<Binding>,
+}
+
+/// Writes the table from the names of monomorphized components to the
+/// definitions and parameters they were generated from.
+pub struct MangleMap;
+
+impl MangleMap {
+    /// Write the table for the monomorphized program `ctx` to `path`.
+    /// External components are not monomorphized and are omitted.
+    pub fn write(ctx: &ir::Context, path: &Path) -> Result<(), u64> {
+        let ng = NameGenerator::new(false);
+        let components = ctx
+            .iter()
+            .filter_map(|(idx, comp)| {
+                let ms = comp.mono_src.as_ref()?;
+                Some(Entry {
+                    name: ng.comp_name(idx, ctx),
+                    source: comp.def_name.to_string(),
+                    params: ms
+                        .params
+                        .iter()
+                        .map(|(n, v)| {
+                            Binding::new(n.to_string(), Some(Value::big(v)))
+                        })
+                        .collect(),
+                })
+            })
+            .collect();
+        let table = Table {
+            version: SCHEMA_VERSION,
+            components,
+        };
+        let json = serde_json::to_string_pretty(&table).unwrap() + "\n";
+        if let Err(err) = fs::write(path, json) {
+            let mut diag = Diagnostics::default();
+            diag.add_error(Error::invalid_file(format!(
+                "Failed to write {}: {err}",
+                path.display()
+            )));
+            return Err(diag.report_all().unwrap());
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 mod global;
+mod mangle;
 mod monodeferred;
 mod monomorphize;
 mod monosig;
@@ -11,4 +12,5 @@ pub(super) use utils::{
     Base, BaseComp, IntoBase, IntoUdl, Underlying, UnderlyingComp,
 };
 
+pub use mangle::MangleMap;
 pub use monomorphize::Monomorphize;
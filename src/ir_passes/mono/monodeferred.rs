@@ -2,6 +2,7 @@ use super::{
     Base, CompKey, IntoUdl, MonoSig, Monomorphize, Underlying, UnderlyingComp,
 };
 use fil_ir::{self as ir, AddCtx, Ctx};
+use fil_utils::BigInt;
 use ir::DisplayCtx;
 use itertools::Itertools;
 
@@ -39,7 +40,8 @@ impl<'a, 'pass: 'a> MonoDeferred<'a, 'pass> {
 
 impl MonoDeferred<'_, '_> {
     /// Get the underlying component
-    pub fn take(self) -> ir::Component {
+    pub fn take(mut self) -> ir::Component {
+        self.pass.eval_errors.append(&mut self.monosig.errors);
         self.monosig.base.take()
     }
 
@@ -52,7 +54,7 @@ impl MonoDeferred<'_, '_> {
             binding
                 .iter()
                 .filter(|(p, _)| self.underlying.get(*p).is_sig_owned())
-                .map(|(_, n)| *n)
+                .map(|(_, n)| n.clone())
                 .collect_vec()
         };
 
@@ -146,7 +148,7 @@ impl MonoDeferred<'_, '_> {
     }
 
     /// Add to the parameter binding
-    pub fn push_binding(&mut self, p: Underlying<ir::Param>, v: u64) {
+    pub fn push_binding(&mut self, p: Underlying<ir::Param>, v: BigInt) {
         self.monosig.binding.push(p, v);
     }
 
@@ -180,7 +182,7 @@ impl MonoDeferred<'_, '_> {
         self.sig_complete_mono();
 
         // Return the component
-        self.monosig.base.take()
+        self.take()
     }
 
     fn prop(&mut self, pidx: Underlying<ir::Prop>) -> Base<ir::Prop> {
@@ -317,7 +319,7 @@ impl MonoDeferred<'_, '_> {
         while i < bound {
             let index = index.ul();
             let orig_l = self.monosig.binding.len();
-            self.monosig.binding.push(index, i);
+            self.monosig.binding.push(index, i.into());
             for cmd in body.iter() {
                 let cmd = self.command(cmd);
                 self.monosig.base.extend_cmds(cmd);
@@ -416,7 +418,8 @@ impl MonoDeferred<'_, '_> {
             ir::Command::Let(ir::Let { param, expr }) => {
                 let p = param.ul();
                 let e = self.monosig.expr(&self.underlying, expr.ul()).get();
-                let Some(v) = e.as_concrete(self.monosig.base.comp()) else {
+                let Some(v) = e.as_big(self.monosig.base.comp()).cloned()
+                else {
                     unreachable!(
                         "let binding evaluated to: {}",
                         self.monosig.base.comp().display(e)
@@ -438,7 +441,7 @@ impl MonoDeferred<'_, '_> {
                 let comp_key = self.comp_key();
                 let e = self.monosig.expr(&self.underlying, expr.ul()).get();
                 let base_comp = self.monosig.base.comp();
-                let Some(v) = e.as_concrete(base_comp) else {
+                let Some(v) = e.as_big(base_comp).cloned() else {
                     unreachable!(
                         "exists binding evaluated to: {}",
                         base_comp.display(e)
@@ -1,9 +1,12 @@
 use super::{
-    Base, CompKey, InstanceInfo, IntoBase, IntoUdl, MonoDeferred, MonoSig,
-    Underlying, UnderlyingComp,
+    mangle, Base, CompKey, InstanceInfo, IntoBase, IntoUdl, MonoDeferred,
+    MonoSig, Underlying, UnderlyingComp,
 };
+use crate::cmdline::Mangle;
+use fil_ast as ast;
 use fil_gen as gen;
-use fil_ir::{self as ir, Ctx, IndexStore};
+use fil_ir::{self as ir, Ctx, DisplayCtx, IndexStore, MutCtx};
+use fil_utils::{BigInt, Diagnostics, Error, GPosIdx, Signed};
 use ir::AddCtx;
 use itertools::Itertools;
 use std::collections::HashMap;
@@ -69,6 +72,12 @@ pub struct Monomorphize<'a> {
     pub ext_map: HashMap<String, Vec<ir::CompIdx>>,
     /// Generator executor
     gen_exec: &'a mut Option<gen::GenExec>,
+    /// Expressions in the last monomorphized component that could not be
+    /// evaluated. They are reported at the instance that created the
+    /// component.
+    pub eval_errors: Vec<(String, String)>,
+    /// Errors generated during monomorphization
+    diag: Diagnostics,
 }
 
 impl<'a> Monomorphize<'a> {
@@ -84,6 +93,8 @@ impl<'a> Monomorphize<'a> {
             inst_info: HashMap::new(),
             ext_map: HashMap::new(),
             gen_exec,
+            eval_errors: vec![],
+            diag: Diagnostics::default(),
         }
     }
 }
@@ -103,11 +114,162 @@ impl<'ctx> Monomorphize<'ctx> {
         self.inst_info.entry(comp_key).or_default()
     }
 
+    /// Report expressions that could not be evaluated, optionally at the
+    /// location of the instance that created the component.
+    pub fn report_eval_errors(&mut self, inst_loc: Option<GPosIdx>) {
+        for (msg, binding) in std::mem::take(&mut self.eval_errors) {
+            let mut err = Error::misc(msg);
+            if let Some(pos) = inst_loc {
+                err = err.add_note(self.diag.add_info(
+                    format!("component instantiated here with [{binding}]"),
+                    pos,
+                ));
+            }
+            self.diag.add_error(err);
+        }
+    }
+
+    /// Report negative values passed to the natural parameters of the
+    /// component instantiated at `inst_loc`.
+    pub fn check_natural_args(&mut self, ck: &CompKey, inst_loc: GPosIdx) {
+        let comp = self.old.get(ck.comp.idx());
+        for (p, v) in comp.param_args().iter().zip(&ck.params) {
+            if !v.is_negative() || comp.get(*p).signed {
+                continue;
+            }
+            let err = Error::misc(format!(
+                "negative value {v} passed to natural parameter `{}'",
+                comp.display(*p)
+            ))
+            .add_note(self.diag.add_info("instance defined here", inst_loc));
+            self.diag.add_error(err);
+        }
+    }
+
+    /// Source-level bindings of the component generated for `ck`. Must be
+    /// called after the component has been monomorphized.
+    fn mono_src(&self, ck: &CompKey) -> ir::MonoSrc {
+        let comp = self.old.get(ck.comp.idx());
+        let name = |p: ir::ParamIdx| {
+            comp.get(comp.get(p).info)
+                .as_param()
+                .map_or_else(|| ast::Id::new(p), |info| info.name)
+        };
+        let params = comp
+            .sig_params()
+            .zip(&ck.params)
+            .map(|(p, v)| (name(p), v.clone()))
+            .collect();
+        let exists = self
+            .inst_info
+            .get(ck)
+            .into_iter()
+            .flat_map(|info| info.iter_exist_vals())
+            .map(|(p, v)| (name(p.idx()), v))
+            .collect();
+        ir::MonoSrc {
+            params,
+            exists,
+            ranges: Self::ranges(comp, &ck.params),
+            name: None,
+        }
+    }
+
+    /// Declared ranges of the signature parameters of `comp` evaluated with
+    /// the parameter values `vals`. A parameter with several ranges lies
+    /// within their intersection.
+    fn ranges(comp: &ir::Component, vals: &[BigInt]) -> Vec<ir::RangeSrc> {
+        // Bounds that mention other parameters are evaluated by substituting
+        // their values which adds expressions to the component.
+        let mut scratch: Option<ir::Component> = None;
+        let mut eval = |e: ir::ExprIdx| {
+            if let Some(v) = e.as_big(comp) {
+                return Some(v.clone());
+            }
+            let scratch = scratch.get_or_insert_with(|| comp.clone());
+            let bind = ir::Bind::new(
+                comp.sig_params()
+                    .zip(vals)
+                    .map(|(p, v)| {
+                        (p, scratch.add(ir::Expr::Concrete(v.clone())))
+                    })
+                    .collect_vec(),
+            );
+            ir::Subst::new(e, &bind)
+                .apply(scratch)
+                .as_big(scratch)
+                .cloned()
+        };
+        comp.sig_params()
+            .map(|p| {
+                let mut range = ir::RangeSrc::default();
+                for r in &comp.get(p).ranges {
+                    match r {
+                        ir::ParamRange::Bounded { lo, hi } => {
+                            range.min = range
+                                .min
+                                .take()
+                                .into_iter()
+                                .chain(eval(*lo))
+                                .max();
+                            range.max = range
+                                .max
+                                .take()
+                                .into_iter()
+                                .chain(eval(*hi))
+                                .min();
+                        }
+                        ir::ParamRange::Pow2 => range.pow2 = true,
+                    }
+                }
+                range
+            })
+            .collect()
+    }
+
+    /// Name the components generated from source definitions using `scheme`
+    /// and report names that are used by more than one component.
+    fn mangle(&mut self, scheme: Mangle) {
+        // Components with source-level names, such as the toplevel and
+        // external components, keep them and are named first.
+        let comps = self
+            .ctx
+            .iter()
+            .map(|(idx, comp)| (idx, comp.src_info.is_some()))
+            .sorted_by_key(|(_, named)| !named)
+            .collect_vec();
+        let mut names: HashMap<String, ir::CompIdx> = HashMap::new();
+        for (idx, named) in comps {
+            let comp = self.ctx.get_mut(idx);
+            let name = if named {
+                comp.src_info.as_ref().unwrap().name.to_string()
+            } else {
+                let name = mangle::name(scheme, idx, comp);
+                comp.mono_src.get_or_insert_with(Default::default).name =
+                    Some(name.as_str().into());
+                name
+            };
+            if let Some(prev) = names.get(&name) {
+                let err = Error::misc(format!(
+                    "components `{}' and `{}' are both named `{name}'",
+                    mangle::describe(self.ctx.get(*prev)),
+                    mangle::describe(self.ctx.get(idx)),
+                ))
+                .add_note(self.diag.add_message(
+                    "use `--mangle' to select a different naming scheme",
+                ));
+                self.diag.add_error(err);
+            } else {
+                names.insert(name, idx);
+            }
+        }
+    }
+
     /// Generate an component using the `gen` framework
     pub fn gen(
         &mut self,
         comp: Underlying<ir::Component>,
-        params: Vec<u64>,
+        params: Vec<BigInt>,
         key: CompKey,
     ) -> Base<ir::Component> {
         let underlying = self.old.get(comp.idx());
@@ -148,12 +310,12 @@ impl<'ctx> Monomorphize<'ctx> {
         let exists = exist_params
             .into_iter()
             .map(|(name, val)| {
-                let v: u64 = val.parse().unwrap();
+                let v: BigInt = val.parse().unwrap();
                 let Some(param) = is.param_from_src_name(name.clone()) else {
                     unreachable!("component does not have parameter `{name}'")
                 };
                 // Add to the binding
-                mono_comp.push_binding(param.ul(), v);
+                mono_comp.push_binding(param.ul(), v.clone());
                 (param.ul(), v)
             })
             .collect_vec();
@@ -170,6 +332,7 @@ impl<'ctx> Monomorphize<'ctx> {
         for (p, v) in exists {
             info.add_exist_val(p, v);
         }
+        comp.mono_src = Some(self.mono_src(&key));
 
         // Add component to the context
         let idx = self.ctx.add(comp).base();
@@ -255,12 +418,13 @@ impl<'ctx> Monomorphize<'ctx> {
 
         // the component whose signature we want to monomorphize
         // Monomorphize the sig
-        let mono_comp = MonoDeferred::new(
+        let mut mono_comp = MonoDeferred::new(
             UnderlyingComp::new(self.old.get(comp.idx())),
             self,
             monosig,
         )
         .comp();
+        mono_comp.mono_src = Some(self.mono_src(&n_ck));
 
         let new_comp = self.ctx.add(mono_comp).base();
         self.processed.insert(n_ck, new_comp);
@@ -273,28 +437,48 @@ impl<'ctx> Monomorphize<'ctx> {
 impl Monomorphize<'_> {
     /// Monomorphize the context by tracing starting from the top-level component.
     /// Returns an empty context if there is no top-level component.
+    /// Reports an error if a parameter expression cannot be evaluated, for
+    /// example, when a subtraction results in a negative number.
     pub fn transform(
         ctx: &ir::Context,
         gen: &mut Option<gen::GenExec>,
-    ) -> ir::Context {
+        mangle: Mangle,
+    ) -> Result<ir::Context, u64> {
+        Self::transform_with(ctx, gen, vec![], mangle)
+    }
+
+    /// Monomorphize the context starting from the top-level component
+    /// instantiated with the given values for all of its parameters.
+    /// Generated components are named using the `mangle` scheme.
+    pub fn transform_with(
+        ctx: &ir::Context,
+        gen: &mut Option<gen::GenExec>,
+        params: Vec<BigInt>,
+        mangle: Mangle,
+    ) -> Result<ir::Context, u64> {
         let Some(entrypoint) = ctx.entrypoint else {
             log::warn!("Program has no entrypoint. Result will be empty.");
-            return ir::Context {
+            return Ok(ir::Context {
                 comps: IndexStore::default(),
                 entrypoint: None,
                 externals: HashMap::new(),
-            };
+            });
         };
         let entrypoint = entrypoint.ul();
         // Monomorphize the entrypoint
         let mut mono = Monomorphize::new(ctx, gen);
-        let ck = CompKey::new(entrypoint, vec![]);
+        let ck = CompKey::new(entrypoint, params);
         mono.monomorphize(ck.clone());
+        mono.report_eval_errors(None);
+        mono.mangle(mangle);
+        if let Some(errs) = mono.diag.report_all() {
+            return Err(errs);
+        }
 
         let new_entrypoint = mono.processed.get(&ck).unwrap();
         mono.ctx.entrypoint = Some(new_entrypoint.get());
         mono.ctx.externals = mono.ext_map;
         ir::Validate::context(&mono.ctx);
-        mono.ctx
+        Ok(mono.ctx)
     }
 }
@@ -6,6 +6,7 @@ use fil_ir::{
     self as ir, AddCtx, Ctx, DenseIndexInfo, DisplayCtx, Foreign, MutCtx,
     SparseInfoMap,
 };
+use fil_utils::{BigInt, Signed, Zero};
 use itertools::Itertools;
 use std::collections::HashMap;
 
@@ -24,7 +25,7 @@ pub struct MonoSig {
     /// The underlying component's idx
     pub underlying_idx: Underlying<ir::Component>,
     /// Mapping from parameters in the underlying component to their constant bindings.
-    pub binding: ir::Bind<Underlying<ir::Param>, u64>,
+    pub binding: ir::Bind<Underlying<ir::Param>, BigInt>,
 
     // Keep track of things that have benen monomorphized already
     /// Events
@@ -40,6 +41,10 @@ pub struct MonoSig {
     invoke_map: DenseMap<ir::Invoke>,
     /// Map from underlying instances to base instances
     instance_map: DenseMap<ir::Instance>,
+
+    /// Expressions that could not be evaluated under the binding along with
+    /// the binding they were evaluated under
+    pub errors: Vec<(String, String)>,
 }
 
 impl MonoSig {
@@ -47,7 +52,7 @@ impl MonoSig {
         underlying: &ir::Component,
         typ: ir::CompType,
         idx: Underlying<ir::Component>,
-        params: Vec<u64>,
+        params: Vec<BigInt>,
     ) -> Self {
         let binding = ir::Bind::new(
             underlying
@@ -56,7 +61,8 @@ impl MonoSig {
                 .zip(params)
                 .collect_vec(),
         );
-        let comp = ir::Component::new(typ);
+        let mut comp = ir::Component::new(typ);
+        comp.def_name = underlying.def_name;
 
         Self {
             base: BaseComp::new(comp),
@@ -68,6 +74,7 @@ impl MonoSig {
             event_map: DenseMap::default(),
             invoke_map: DenseMap::default(),
             instance_map: DenseMap::default(),
+            errors: vec![],
         }
     }
 
@@ -189,11 +196,15 @@ impl MonoSig {
                 src_loc,
                 dst_liveness,
                 src_liveness,
+                dst_name,
+                src_name,
             } => ir::info::Reason::Liveness {
                 dst_loc: *dst_loc,
                 src_loc: *src_loc,
                 dst_liveness: self.range(underlying, pass, dst_liveness),
                 src_liveness: self.range(underlying, pass, src_liveness),
+                dst_name: dst_name.clone(),
+                src_name: src_name.clone(),
             },
             ir::info::Reason::ParamConstraint { .. }
             | ir::info::Reason::ExistsConstraint { .. }
@@ -274,7 +285,7 @@ impl MonoSig {
                 // If this is a parameter in the underlying component that is bound,
                 // return its binding
                 if let Some(n) = self.binding.get(&p.ul()) {
-                    let new_idx = self.base.num(*n);
+                    let new_idx = self.base.num(n.clone());
                     return new_idx;
                 } else {
                     let p = self.param_use(underlying, p.ul()).get();
@@ -286,7 +297,31 @@ impl MonoSig {
                 let lhs = self.expr(underlying, lhs.ul()).get();
                 let rhs = self.expr(underlying, rhs.ul()).get();
                 let binop = ir::Expr::Bin { op, lhs, rhs };
-                self.base.add(binop)
+                let idx = self.base.add(binop);
+                let comp = self.base.comp();
+                let (Some(l), Some(r)) = (lhs.as_big(comp), rhs.as_big(comp))
+                else {
+                    return idx;
+                };
+                let msg = match idx.get().as_big(comp) {
+                    // Concrete operands that do not fold means the operation
+                    // is not defined on them.
+                    None => format!(
+                        "`{}' divides by zero: {l} {op} {r}",
+                        underlying.display(expr)
+                    ),
+                    // Expressions over natural parameters cannot be negative
+                    Some(v)
+                        if v.is_negative() && !underlying.is_signed(expr) =>
+                    {
+                        format!(
+                            "subtraction `{}' underflows: {l} - {r} is negative",
+                            underlying.display(expr)
+                        )
+                    }
+                    Some(_) => return idx,
+                };
+                self.eval_error(underlying, msg)
             }
             ir::Expr::Fn { op, args } => {
                 let args = args
@@ -294,12 +329,33 @@ impl MonoSig {
                     .map(|idx| self.expr(underlying, idx.ul()).get())
                     .collect_vec();
                 let func = ir::Expr::Fn { op, args };
-                self.base.func(func)
+                let idx = self.base.add(func);
+                if idx.get().as_big(self.base.comp()).is_none() {
+                    let msg = format!(
+                        "`{}' is too large to evaluate",
+                        underlying.display(expr)
+                    );
+                    return self.eval_error(underlying, msg);
+                }
+                idx
             }
         };
         new_idx
     }
 
+    /// Record an expression that cannot be evaluated under the current
+    /// binding. Evaluation continues with the value zero so that the rest of
+    /// the component can be monomorphized.
+    fn eval_error(
+        &mut self,
+        underlying: &UnderlyingComp,
+        msg: String,
+    ) -> Base<ir::Expr> {
+        let binding = self.binding_rep(underlying);
+        self.errors.push((msg, binding));
+        self.base.num(BigInt::zero())
+    }
+
     /// Given a Range owned by underlying, returns a Range that is meaningful in base
     pub fn range(
         &mut self,
@@ -417,7 +473,7 @@ impl MonoSig {
         event: Underlying<ir::Event>,
     ) -> Base<ir::Event> {
         let binding = self.binding.inner();
-        let conc_params = binding.iter().map(|(_, n)| *n).collect_vec();
+        let conc_params = binding.iter().map(|(_, n)| n.clone()).collect_vec();
 
         let new_event = self.event_map.get(event);
         let ck: CompKey = (self.underlying_idx, conc_params).into();
@@ -430,18 +486,25 @@ impl MonoSig {
     /// corresponding index
     fn bundle_params(
         &mut self,
-        _underlying: &UnderlyingComp,
-        _pass: &mut Monomorphize,
+        underlying: &UnderlyingComp,
+        pass: &mut Monomorphize,
         params: &[Underlying<ir::Param>],
         port: Base<ir::Port>,
     ) -> Vec<Base<ir::Param>> {
-        let info = self.base.add(ir::Info::empty()).get();
+        let infos = params
+            .iter()
+            .map(|p| {
+                let info = underlying.get(*p).info;
+                self.info(underlying, pass, info.ul()).get()
+            })
+            .collect_vec();
         let mono_owner = ir::ParamOwner::Bundle(port.get());
 
         if let Some(new_params) = self.bundle_param_map.get(&port) {
             return new_params
                 .iter()
-                .map(|&new_param_idx| {
+                .zip(infos)
+                .map(|(&new_param_idx, info)| {
                     let new_param = self.base.get_mut(new_param_idx);
                     new_param.owner = mono_owner.clone();
                     new_param.info = info;
@@ -452,11 +515,9 @@ impl MonoSig {
 
         let mono_params = params
             .iter()
-            .map(|old_param| {
-                let mono_param = ir::Param {
-                    owner: mono_owner.clone(),
-                    info,
-                };
+            .zip(infos)
+            .map(|(old_param, info)| {
+                let mono_param = ir::Param::new(mono_owner.clone(), info);
                 let new_idx = self.base.add(mono_param);
                 self.param_map.push(*old_param, new_idx);
                 new_idx
@@ -588,8 +649,9 @@ impl MonoSig {
             .map(|p| {
                 self.expr(underlying, p.ul())
                     .get()
-                    .as_concrete(self.base.comp())
+                    .as_big(self.base.comp())
                     .unwrap()
+                    .clone()
             })
             .collect_vec();
         CompKey::new(comp.ul(), conc_params)
@@ -629,7 +691,12 @@ impl MonoSig {
 
         // Monomorphize the component
         let ck = self.comp_key(underlying, inst);
+        let &ir::info::Instance { comp_loc, .. } =
+            underlying.get(info.ul()).into();
+        pass.check_natural_args(&ck, comp_loc);
         let mono_comp = pass.monomorphize(ck.clone());
+        // Errors in the instantiated component are reported at this instance
+        pass.report_eval_errors(Some(comp_loc));
 
         // Binding for parameters defined by this instance
         self.binding.extend(params.iter().map(|p| {
@@ -2,6 +2,7 @@ use fil_ast as ast;
 use fil_ir::{
     self as ir, AddCtx, Ctx, DisplayCtx, Idx, IndexStore, InterfaceSrc, MutCtx,
 };
+use fil_utils::BigInt;
 
 use super::{Base, IntoBase, IntoUdl, Underlying};
 
@@ -37,6 +38,9 @@ impl<'a> UnderlyingComp<'a> {
     pub fn all_exist_assumes(&self) -> Vec<ir::PropIdx> {
         self.0.all_exist_assumes()
     }
+    pub fn is_signed(&self, expr: Underlying<ir::Expr>) -> bool {
+        expr.idx().is_signed(self.0)
+    }
     pub fn relevant_vars(
         &self,
         prop: Underlying<ir::Prop>,
@@ -102,15 +106,12 @@ impl BaseComp {
         self.0.cmds.extend(other);
     }
 
-    pub fn num(&mut self, n: u64) -> Base<ir::Expr> {
-        self.0.add(ir::Expr::Concrete(n)).base()
+    pub fn num(&mut self, n: BigInt) -> Base<ir::Expr> {
+        self.0.big_num(n).base()
     }
     pub fn bin(&mut self, expr: ir::Expr) -> Base<ir::Expr> {
         self.0.bin(expr).base()
     }
-    pub fn func(&mut self, expr: ir::Expr) -> Base<ir::Expr> {
-        self.0.func(expr).base()
-    }
     pub fn resolve_prop(&mut self, prop: ir::Prop) -> Base<ir::Prop> {
         self.0.resolve_prop(prop).base()
     }
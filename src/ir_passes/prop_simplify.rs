@@ -1,12 +1,150 @@
-use crate::ir_visitor::{Action, Visitor, VisitorData};
+use super::linear::{Linear, Term, Var};
+use crate::cmdline;
+use crate::ir_visitor::{Action, Construct, Visitor, VisitorData};
 use fil_ir::{self as ir, AddCtx, Ctx};
+use fil_utils::{BigInt, BigIntExt, One, Signed, Zero};
 use linked_hash_set::LinkedHashSet;
 
-#[derive(Default)]
-/// Simplify propositions and expressions in a component
+/// Simplify propositions and expressions in a component.
+///
+/// Comparisons are normalized into canonical linear forms so that equivalent
+/// comparisons are syntactically equal, and comparisons with a constant
+/// value are replaced by `true` or `false`. Obligations that simplify to
+/// `true` are removed.
 pub struct Simplify {
     /// Simplified version of each proposition
     prop_map: ir::DenseIndexInfo<ir::Prop, ir::PropIdx>,
+    /// Rewrite comparisons into their canonical forms. Disabled with the
+    /// bitvector encoding because the rewritten expressions are subject to
+    /// overflow constraints that the original ones are not.
+    canonical: bool,
+    /// Number of obligations eliminated
+    eliminated: u64,
+}
+
+impl Construct for Simplify {
+    fn from(opts: &cmdline::Opts, _: &mut ir::Context) -> Self {
+        Self {
+            prop_map: Default::default(),
+            canonical: opts.solver_bv.is_none(),
+            eliminated: 0,
+        }
+    }
+
+    fn clear_data(&mut self) {
+        self.prop_map.clear();
+    }
+}
+
+/// Canonical forms of comparisons
+impl Simplify {
+    /// Expression for the sum of the variables with positive (if `pos`) or
+    /// negative coefficients in the term and the constant if it has the same
+    /// sign.
+    fn side(t: &Term, pos: bool, ctx: &mut ir::Component) -> ir::ExprIdx {
+        let mut acc = ctx.num(0);
+        for (v, c) in &t.coeffs {
+            if c.is_negative() == pos {
+                continue;
+            }
+            let mut e = match v {
+                Var::Param(p) => ctx.add(ir::Expr::Param(*p)),
+                Var::Opaque(e) => *e,
+                Var::Event(_) => unreachable!("events cannot be expressions"),
+            };
+            if !c.magnitude().is_one() {
+                let c = ctx.big_num(c.magnitude().clone().into());
+                e = e.mul(c, ctx);
+            }
+            acc = acc.add(e, ctx);
+        }
+        if !t.k.is_zero() && t.k.is_negative() != pos {
+            let k = ctx.big_num(t.k.magnitude().clone().into());
+            acc = acc.add(k, ctx);
+        }
+        acc
+    }
+
+    /// Canonical proposition equivalent to `d op 0`. Returns `None` if the
+    /// term mentions events.
+    fn canonical_cmp(
+        op: ir::Cmp,
+        d: Term,
+        ctx: &mut ir::Component,
+    ) -> Option<ir::PropIdx> {
+        if d.coeffs.keys().any(|v| matches!(v, Var::Event(_))) {
+            return None;
+        }
+        let one = Term::constant(1u64.into());
+        let prop = match op {
+            ir::Cmp::Eq => {
+                let g: BigInt = d.coeff_gcd().into();
+                if !d.k.checked_div_rem_euclid(&g).unwrap().1.is_zero() {
+                    return Some(ctx.add(ir::Prop::False));
+                }
+                // The constant is divisible by the common divisor so
+                // tightening does not change the solutions.
+                let d = d.tighten();
+                // The first coefficient is always positive
+                let first = d.coeffs.values().next().unwrap();
+                let d = if first.is_negative() {
+                    d.scale(&(-1i64).into())
+                } else {
+                    d
+                };
+                let (l, r) =
+                    (Self::side(&d, true, ctx), Self::side(&d, false, ctx));
+                l.equal(r, ctx)
+            }
+            ir::Cmp::Gt | ir::Cmp::Gte => {
+                // Over integers, `d > 0` is equivalent to `d - 1 >= 0`
+                let d = if matches!(op, ir::Cmp::Gt) {
+                    d.sub(&one)
+                } else {
+                    d
+                }
+                .tighten();
+                // Use `>` if it moves a constant to the right hand side
+                if d.k.is_negative() {
+                    let d = d.add_scaled(&1u64.into(), &one);
+                    let (l, r) =
+                        (Self::side(&d, true, ctx), Self::side(&d, false, ctx));
+                    l.gt(r, ctx)
+                } else {
+                    let (l, r) =
+                        (Self::side(&d, true, ctx), Self::side(&d, false, ctx));
+                    l.gte(r, ctx)
+                }
+            }
+        };
+        Some(prop)
+    }
+
+    /// Simplify a comparison
+    fn simplify_cmp(
+        &self,
+        prop: ir::PropIdx,
+        ctx: &mut ir::Component,
+    ) -> ir::PropIdx {
+        let (op, d) = Linear::new(ctx).keep_lets().cmp_diff(prop).unwrap();
+        if let Some(k) = d.as_const() {
+            let zero = BigInt::zero();
+            let holds = match op {
+                ir::Cmp::Gt => *k > zero,
+                ir::Cmp::Gte => *k >= zero,
+                ir::Cmp::Eq => *k == zero,
+            };
+            return ctx.add(if holds {
+                ir::Prop::True
+            } else {
+                ir::Prop::False
+            });
+        }
+        if !self.canonical {
+            return prop;
+        }
+        Self::canonical_cmp(op, d, ctx).unwrap_or(prop)
+    }
 }
 
 /// Simplify propositions
@@ -123,23 +261,17 @@ impl Simplify {
     ) -> ir::PropIdx {
         if !self.prop_map.contains(prop) {
             let out = match ctx.get(prop).clone() {
-                ir::Prop::True
-                | ir::Prop::False
-                | ir::Prop::Cmp(_)
+                ir::Prop::True | ir::Prop::False => prop,
+                ir::Prop::Cmp(_)
                 | ir::Prop::TimeCmp(_)
-                | ir::Prop::TimeSubCmp(_) => prop,
+                | ir::Prop::TimeSubCmp(_) => self.simplify_cmp(prop, ctx),
                 ir::Prop::Not(p) => self.simplify_prop(p, ctx).not(ctx),
-                ir::Prop::And(_, _) => {
+                ir::Prop::And(l, r) => {
                     let mut conj = Vec::new();
-                    self.conjuncts(prop, ctx, &mut conj);
-                    let conj_len = conj.len();
+                    self.conjuncts(self.get_simpl(l), ctx, &mut conj);
+                    self.conjuncts(self.get_simpl(r), ctx, &mut conj);
                     let set: LinkedHashSet<_> = conj.into_iter().collect();
-                    // Only perform the simplification if there are duplicates
-                    if set.len() == conj_len {
-                        prop
-                    } else {
-                        Self::prop_from_conjuncts(set, ctx)
-                    }
+                    Self::prop_from_conjuncts(set, ctx)
                 }
                 ir::Prop::Or(_, _) => {
                     let mut disj = LinkedHashSet::new();
@@ -147,6 +279,14 @@ impl Simplify {
                     Self::prop_from_disjuncts(disj, ctx)
                 }
                 ir::Prop::Implies(a, c) => {
+                    let a = self.simplify_prop(a, ctx);
+                    let c = self.simplify_prop(c, ctx);
+                    // The implication is vacuous if the antecedent is false
+                    if a.is_false(ctx) {
+                        let out = ctx.add(ir::Prop::True);
+                        self.prop_map.push(prop, out);
+                        return out;
+                    }
                     // Compute the conjuncts for the antecedant and consequent and eliminate terms in the consequent that are in the antecedant
                     // NOTE(rachit): The simplification of `a` would already have computed the conjuncts so we're wasting some work here.
                     let mut conj_a = Vec::new();
@@ -160,10 +300,10 @@ impl Simplify {
                     let diff: LinkedHashSet<_> =
                         set_c.difference(&set_a).copied().collect();
                     if diff.len() == set_c.len() {
-                        prop
+                        a.implies(c, ctx)
                     } else {
                         let new_c = Self::prop_from_conjuncts(diff, ctx);
-                        self.simplify_prop(a, ctx).implies(new_c, ctx)
+                        a.implies(new_c, ctx)
                     }
                 }
             };
@@ -201,10 +341,18 @@ impl Visitor for Simplify {
         // Simplify the proposition in the fact
         let simpl = self.simplify_prop(fact.prop, &mut data.comp);
         if simpl.is_true(&data.comp) {
+            if fact.is_assert() {
+                self.eliminated += 1;
+            }
             Action::Change(vec![])
         } else {
             fact.prop = simpl;
             Action::Continue
         }
     }
+
+    fn after_traversal(&mut self) -> Option<u64> {
+        log::info!("simplify: eliminated {} obligation(s)", self.eliminated);
+        None
+    }
 }
@@ -0,0 +1,242 @@
+use super::{BuildDomination, IntervalCheck, TypeCheck};
+use crate::{
+    cmdline,
+    ir_visitor::{Action, Construct, Visitor, VisitorData},
+    utils::HoistFacts,
+};
+use codespan_reporting::diagnostic as cr;
+use fil_ir::{self as ir, Ctx};
+use fil_utils::{BigInt, Diagnostics};
+use std::collections::{HashMap, HashSet};
+
+/// Re-generates the obligations of [TypeCheck] and [IntervalCheck] on the
+/// monomorphized program and evaluates them using plain arithmetic.
+/// All parameters are concrete after monomorphization so the obligations can
+/// be decided without a solver, except for comparisons between times relative
+/// to different events which are left undecided.
+/// Reports every obligation that is false and warns about the number of
+/// undecided ones.
+///
+/// Monomorphization already carries over the obligations generated before it
+/// ran. These are only reported when no regenerated obligation is false at the
+/// same location since the regenerated ones have more precise explanations.
+pub struct RecheckConcrete {
+    /// Reasons of the obligations carried over by monomorphization
+    carried: HashMap<ir::CompIdx, HashSet<ir::InfoIdx>>,
+    /// Regenerated obligations that are false
+    diagnostics: Vec<cr::Diagnostic<usize>>,
+    /// Carried over obligations that are false
+    carried_diagnostics: Vec<cr::Diagnostic<usize>>,
+    /// Number of obligations that could not be evaluated
+    undecided: u64,
+}
+
+impl Construct for RecheckConcrete {
+    fn from(_: &cmdline::Opts, _: &mut ir::Context) -> Self {
+        Self {
+            carried: HashMap::new(),
+            diagnostics: vec![],
+            carried_diagnostics: vec![],
+            undecided: 0,
+        }
+    }
+
+    fn clear_data(&mut self) {
+        /* Diagnostics are reported after the traversal */
+    }
+}
+
+impl RecheckConcrete {
+    /// Check the obligations of the monomorphized program.
+    pub fn check(opts: &cmdline::Opts, ctx: &ir::Context) -> Result<(), u64> {
+        // The obligations are only used for checking and are not added to the
+        // program.
+        let mut ctx = ctx.clone();
+        let carried = ctx
+            .comps
+            .iter_mut()
+            .map(|(idx, comp)| {
+                let reasons = HoistFacts::hoist(comp)
+                    .into_iter()
+                    .map(|f| f.reason)
+                    .collect();
+                (idx, reasons)
+            })
+            .collect();
+        BuildDomination::do_pass(opts, &mut ctx)?;
+        TypeCheck::do_pass(opts, &mut ctx)?;
+        IntervalCheck::do_pass(opts, &mut ctx)?;
+
+        let mut pass = <RecheckConcrete as Construct>::from(opts, &mut ctx);
+        pass.carried = carried;
+        for idx in ctx.comps.idx_iter() {
+            pass.visit((idx, opts, &mut ctx).into());
+        }
+        match pass.after_traversal() {
+            Some(n) => Err(n),
+            None => Ok(()),
+        }
+    }
+
+    /// The error reported for a false obligation
+    fn diag(fact: &ir::Fact, comp: &ir::Component) -> cr::Diagnostic<usize> {
+        let diag = match comp.get(fact.reason).as_assert() {
+            Some(ir::info::Assert(reason)) => reason.diag(comp),
+            None => cr::Diagnostic::error(),
+        };
+        diag.with_notes(vec![
+            "obligation is false after monomorphization".to_string()
+        ])
+    }
+
+    fn expr(e: ir::ExprIdx, comp: &ir::Component) -> Option<&BigInt> {
+        e.as_big(comp)
+    }
+
+    /// Offset of the time along with the event it is relative to
+    fn time(
+        t: ir::TimeIdx,
+        comp: &ir::Component,
+    ) -> Option<(ir::EventIdx, &BigInt)> {
+        let ir::Time { event, offset } = comp.get(t);
+        Some((*event, Self::expr(*offset, comp)?))
+    }
+
+    fn time_sub(ts: &ir::TimeSub, comp: &ir::Component) -> Option<BigInt> {
+        match ts {
+            ir::TimeSub::Unit(e) => Self::expr(*e, comp).cloned(),
+            ir::TimeSub::Sym { l, r } => {
+                let (l_ev, l) = Self::time(*l, comp)?;
+                let (r_ev, r) = Self::time(*r, comp)?;
+                (l_ev == r_ev).then(|| l - r)
+            }
+        }
+    }
+
+    fn cmp<T: Ord>(op: &ir::Cmp, l: T, r: T) -> bool {
+        match op {
+            ir::Cmp::Gt => l > r,
+            ir::Cmp::Gte => l >= r,
+            ir::Cmp::Eq => l == r,
+        }
+    }
+
+    /// Evaluate the proposition. Returns `None` if its value depends on
+    /// the values of events.
+    fn prop(p: ir::PropIdx, comp: &ir::Component) -> Option<bool> {
+        match comp.get(p) {
+            ir::Prop::True => Some(true),
+            ir::Prop::False => Some(false),
+            ir::Prop::Cmp(ir::CmpOp { op, lhs, rhs }) => Some(Self::cmp(
+                op,
+                Self::expr(*lhs, comp)?,
+                Self::expr(*rhs, comp)?,
+            )),
+            ir::Prop::TimeCmp(ir::CmpOp { op, lhs, rhs }) => {
+                let (l_ev, l) = Self::time(*lhs, comp)?;
+                let (r_ev, r) = Self::time(*rhs, comp)?;
+                (l_ev == r_ev).then(|| Self::cmp(op, l, r))
+            }
+            ir::Prop::TimeSubCmp(ir::CmpOp { op, lhs, rhs }) => {
+                Some(Self::cmp(
+                    op,
+                    Self::time_sub(lhs, comp)?,
+                    Self::time_sub(rhs, comp)?,
+                ))
+            }
+            ir::Prop::Not(p) => Self::prop(*p, comp).map(|b| !b),
+            ir::Prop::And(l, r) => {
+                match (Self::prop(*l, comp), Self::prop(*r, comp)) {
+                    (Some(false), _) | (_, Some(false)) => Some(false),
+                    (Some(true), Some(true)) => Some(true),
+                    _ => None,
+                }
+            }
+            ir::Prop::Or(l, r) => {
+                match (Self::prop(*l, comp), Self::prop(*r, comp)) {
+                    (Some(true), _) | (_, Some(true)) => Some(true),
+                    (Some(false), Some(false)) => Some(false),
+                    _ => None,
+                }
+            }
+            ir::Prop::Implies(l, r) => {
+                match (Self::prop(*l, comp), Self::prop(*r, comp)) {
+                    (Some(false), _) | (_, Some(true)) => Some(true),
+                    (Some(true), Some(false)) => Some(false),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+impl Visitor for RecheckConcrete {
+    fn name() -> &'static str {
+        "recheck-concrete"
+    }
+
+    fn start(&mut self, data: &mut VisitorData) -> Action {
+        if data.comp.is_ext() {
+            return Action::Stop;
+        }
+        let comp = &mut data.comp;
+        let carried = self.carried.remove(&data.idx).unwrap_or_default();
+        for fact in HoistFacts::hoist(comp) {
+            match Self::prop(fact.prop, comp) {
+                Some(true) => (),
+                None => self.undecided += 1,
+                Some(false) => {
+                    let diag = Self::diag(&fact, comp);
+                    if carried.contains(&fact.reason) {
+                        self.carried_diagnostics.push(diag);
+                    } else {
+                        self.diagnostics.push(diag);
+                    }
+                }
+            }
+        }
+        Action::Stop
+    }
+
+    fn after_traversal(&mut self) -> Option<u64> {
+        if self.undecided > 0 {
+            log::warn!(
+                "{} obligation(s) relate different events and were not rechecked after monomorphization",
+                self.undecided
+            );
+        }
+        // Location of the primary label of a diagnostic
+        let loc = |d: &cr::Diagnostic<usize>| {
+            d.labels.first().map(|l| (l.file_id, l.range.clone()))
+        };
+        let seen: HashSet<_> = self.diagnostics.iter().map(loc).collect();
+        // Keep the carried diagnostic with the most context for each location
+        let mut carried: Vec<cr::Diagnostic<usize>> = vec![];
+        let mut idxs: HashMap<_, usize> = HashMap::new();
+        for diag in std::mem::take(&mut self.carried_diagnostics) {
+            let l = loc(&diag);
+            if seen.contains(&l) {
+                continue;
+            }
+            match idxs.get(&l) {
+                Some(&i) if carried[i].labels.len() < diag.labels.len() => {
+                    carried[i] = diag
+                }
+                Some(_) => (),
+                None => {
+                    idxs.insert(l, carried.len());
+                    carried.push(diag);
+                }
+            }
+        }
+
+        let mut diag = Diagnostics::default();
+        for d in std::mem::take(&mut self.diagnostics)
+            .into_iter()
+            .chain(carried)
+        {
+            diag.add_diagnostic(d);
+        }
+        diag.report_all()
+    }
+}
@@ -0,0 +1,642 @@
+use super::dump_interface::{CompInterface, DumpInterface, PortInterface};
+use crate::cmdline;
+use crate::ir_passes::lower::INTERFACE_PORTS;
+use fil_ast as ast;
+use fil_ir as ir;
+use fil_utils::{BigUint, Diagnostics, Error, Num};
+use itertools::Itertools;
+use serde_json::{Map, Value as Json};
+use std::{fmt::Write, fs, path::Path};
+
+type JsonMap = Map<String, Json>;
+
+/// Number of cycles the reset signal is held for
+const RESET_CYCLES: u64 = 3;
+
+/// A port of the toplevel with concrete timing
+struct TbPort {
+    name: String,
+    width: u64,
+    start: u64,
+    end: u64,
+}
+
+impl TbPort {
+    /// Number of cycles the port is live for in each transaction
+    fn len(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// Emits a self-checking SystemVerilog testbench for the toplevel component.
+///
+/// Stimulus is read from a JSON file that maps each input to the values it
+/// takes in every transaction, the same layout as `tests/run/*.fil.data`:
+/// ```json
+/// { "left": [1, "0x09", "0b01"], "right": [2, 3, 4] }
+/// ```
+/// Transactions start every `delay` cycles of the interface event. The
+/// testbench pulses the interface port at the start of each transaction,
+/// drives inputs during their availability intervals, and samples outputs
+/// during theirs. The sampled outputs are printed in the format of the
+/// cocotb harness:
+/// ```json
+/// {"out": {"0": [3], "1": [12], "2": [5]}}
+/// ```
+/// If a file with expected outputs is provided, the testbench compares the
+/// outputs against it and fails with `$fatal` on mismatches. Each output maps
+/// to a value for every transaction that is expected during the whole
+/// availability interval, or a list with a value for each of its cycles. The
+/// output of the cocotb harness is also accepted.
+pub struct Testbench {
+    out: String,
+    comp: String,
+    /// Name of the interface port that starts a transaction
+    go: Option<String>,
+    delay: u64,
+    txns: usize,
+    /// Values of the ports. Unknown values are `None`.
+    inputs: Vec<(TbPort, Vec<Option<BigUint>>)>,
+    outputs: Vec<(TbPort, Option<Vec<Option<BigUint>>>)>,
+    /// Ports driven to a constant
+    unannotated: Vec<(String, u64)>,
+}
+
+impl Testbench {
+    /// Print the testbench for the toplevel component
+    pub fn print(opts: &cmdline::Opts, ctx: &ir::Context) -> Result<(), u64> {
+        let mut diag = Diagnostics::default();
+        let data = opts.emit_testbench.as_ref().unwrap();
+        let tb = Self::read(data).and_then(|data| {
+            let expect = opts
+                .testbench_expect
+                .as_deref()
+                .map(Self::read)
+                .transpose()?;
+            Self::build(ctx, data, expect)
+        });
+        match tb {
+            Ok(mut tb) => {
+                tb.emit();
+                print!("{}", tb.out);
+                Ok(())
+            }
+            Err(err) => {
+                diag.add_error(err);
+                Err(diag.report_all().unwrap())
+            }
+        }
+    }
+
+    /// The testbench for a `test` block. The entrypoint of `ctx` must be the
+    /// component under test monomorphized with the parameters of the test.
+    pub fn test(ctx: &ir::Context, test: &ast::Test) -> Result<String, u64> {
+        let mut diag = Diagnostics::default();
+        let mut data = JsonMap::new();
+        let mut expect = JsonMap::new();
+        let value = |v: &ast::TestValue| match v {
+            Some(v) => Json::String(v.to_string()),
+            None => Json::String("x".to_string()),
+        };
+        let inputs = test
+            .txns
+            .iter()
+            .flat_map(|t| t.inner().inputs.iter().map(|(p, _)| *p.inner()))
+            .unique()
+            .collect_vec();
+        let outputs = test
+            .txns
+            .iter()
+            .flat_map(|t| t.inner().outputs.iter().map(|(p, _)| *p.inner()))
+            .unique()
+            .collect_vec();
+        for (i, txn) in test.txns.iter().enumerate() {
+            for port in &inputs {
+                let Some((_, v)) =
+                    txn.inner().inputs.iter().find(|(p, _)| p.inner() == port)
+                else {
+                    let err = Error::malformed(format!(
+                        "transaction {i} of test `{}' has no value for input `{port}'",
+                        test.name
+                    ))
+                    .add_note(diag.add_info("transaction defined here", txn.pos()));
+                    diag.add_error(err);
+                    continue;
+                };
+                Self::push(&mut data, port, value(v));
+            }
+            // Outputs without expected values are not checked
+            for port in &outputs {
+                let v = txn
+                    .inner()
+                    .outputs
+                    .iter()
+                    .find(|(p, _)| p.inner() == port)
+                    .and_then(|(_, v)| v.as_ref());
+                Self::push(&mut expect, port, value(&v.cloned()));
+            }
+        }
+        if let Some(errs) = diag.report_all() {
+            return Err(errs);
+        }
+        match Self::build(ctx, data, Some(expect)) {
+            Ok(mut tb) => {
+                // Expected values of ports that are not outputs are ignored
+                // by the build.
+                let unknown = test.txns.iter().flat_map(|t| {
+                    t.inner().outputs.iter().map(|(p, _)| p).filter(|p| {
+                        !tb.outputs.iter().any(|(o, _)| o.name == p.as_ref())
+                    })
+                });
+                for p in unknown.unique_by(|p| *p.inner()) {
+                    let err = Error::malformed(format!(
+                        "expected value for `{p}' which is not an output of `{}'",
+                        tb.comp
+                    ))
+                    .add_note(diag.add_info("port used here", p.pos()));
+                    diag.add_error(err);
+                }
+                if let Some(errs) = diag.report_all() {
+                    return Err(errs);
+                }
+                tb.emit();
+                Ok(tb.out)
+            }
+            Err(err) => {
+                let err = err.add_note(
+                    diag.add_info("test defined here", test.name.pos()),
+                );
+                diag.add_error(err);
+                Err(diag.report_all().unwrap())
+            }
+        }
+    }
+
+    /// Add a value to the list of values of a port
+    fn push(map: &mut JsonMap, port: &ast::Id, v: Json) {
+        match map
+            .entry(port.to_string())
+            .or_insert_with(|| Json::Array(vec![]))
+        {
+            Json::Array(vs) => vs.push(v),
+            _ => unreachable!(),
+        }
+    }
+
+    fn build(
+        ctx: &ir::Context,
+        data: JsonMap,
+        expect: Option<JsonMap>,
+    ) -> Result<Self, Error> {
+        let Some(idx) = ctx.entrypoint else {
+            return Err(Error::misc(
+                "--emit-testbench requires a toplevel component".to_string(),
+            ));
+        };
+        let comp = DumpInterface::interface(ctx, idx);
+        let [event] = &comp.interfaces[..] else {
+            return Err(Error::misc(format!(
+                "testbenches can only be generated for components with a single event but `{}' has {}",
+                comp.name,
+                comp.interfaces.len()
+            )));
+        };
+        let delay = Self::num(&event.delay, "delay")?;
+        let ports = |ports: &Vec<PortInterface>| {
+            ports
+                .iter()
+                .map(|p| Self::port(&comp, p, delay))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let inputs = ports(&comp.inputs)?;
+        let outputs = ports(&comp.outputs)?;
+
+        let mut txns = None;
+        let mut inputs_data = vec![];
+        for port in inputs {
+            let Some(vals) = data.get(&port.name) else {
+                return Err(Error::misc(format!(
+                    "no stimulus for input `{}'",
+                    port.name
+                )));
+            };
+            let vals = Self::values(&port, vals, 1)?
+                .into_iter()
+                .map(|mut v| v.remove(0))
+                .collect_vec();
+            if *txns.get_or_insert(vals.len()) != vals.len() {
+                return Err(Error::misc(format!(
+                    "input `{}' has {} values but other inputs have {}",
+                    port.name,
+                    vals.len(),
+                    txns.unwrap()
+                )));
+            }
+            inputs_data.push((port, vals));
+        }
+        if let Some(name) = data
+            .keys()
+            .find(|k| !comp.inputs.iter().any(|p| &p.name == *k))
+        {
+            return Err(Error::misc(format!(
+                "stimulus for `{name}' which is not an input of `{}'",
+                comp.name
+            )));
+        }
+        let Some(txns) = txns else {
+            return Err(Error::misc(format!(
+                "`{}' has no inputs to generate transactions from",
+                comp.name
+            )));
+        };
+
+        let mut outputs_data = vec![];
+        for port in outputs {
+            let vals = match expect.as_ref().and_then(|e| e.get(&port.name)) {
+                Some(vals) => {
+                    let vals = Self::values(&port, vals, port.len())?;
+                    if vals.len() != txns {
+                        return Err(Error::misc(format!(
+                            "output `{}' has {} expected values but there are {txns} transactions",
+                            port.name,
+                            vals.len(),
+                        )));
+                    }
+                    Some(vals.into_iter().flatten().collect())
+                }
+                None => None,
+            };
+            outputs_data.push((port, vals));
+        }
+
+        // The backend adds the clock and reset ports unless they are defined
+        let unannotated = comp
+            .unannotated
+            .iter()
+            .filter(|p| {
+                !INTERFACE_PORTS.iter().any(|(_, (n, _, _))| *n == p.name)
+            })
+            .map(|p| (p.name.clone(), p.width))
+            .collect();
+
+        Ok(Testbench {
+            out: String::new(),
+            comp: comp.name.clone(),
+            go: event.name.clone(),
+            delay,
+            txns,
+            inputs: inputs_data,
+            outputs: outputs_data,
+            unannotated,
+        })
+    }
+
+    fn num(v: &impl ToString, what: &str) -> Result<u64, Error> {
+        v.to_string().parse().map_err(|_| {
+            Error::misc(format!(
+                "{what} `{}' of the toplevel component is not concrete",
+                v.to_string()
+            ))
+        })
+    }
+
+    /// A port of the toplevel component and the cycles it is live for
+    fn port(
+        comp: &CompInterface,
+        p: &PortInterface,
+        delay: u64,
+    ) -> Result<TbPort, Error> {
+        if p.bundle.is_some() || p.end_event.is_some() {
+            return Err(Error::misc(format!(
+                "port `{}' of `{}' is not supported in testbenches: ports must be live for an interval of a single event and cannot be bundles",
+                p.name, comp.name
+            )));
+        }
+        let port = TbPort {
+            name: p.name.clone(),
+            width: Self::num(&p.width, "width")?,
+            start: Self::num(&p.start, "start")?,
+            end: Self::num(&p.end, "end")?,
+        };
+        // Ports are shared by overlapping transactions
+        if port.len() > delay {
+            return Err(Error::misc(format!(
+                "port `{}' is live for {} cycles but transactions start every {delay} cycles",
+                port.name,
+                port.len()
+            )));
+        }
+        Ok(port)
+    }
+
+    /// Read a JSON object from a file
+    fn read(path: &Path) -> Result<JsonMap, Error> {
+        let file = path.to_string_lossy();
+        let content = fs::read_to_string(path).map_err(|err| {
+            Error::invalid_file(format!("Failed to read {file}: {err}"))
+        })?;
+        match serde_json::from_str(&content) {
+            Ok(Json::Object(map)) => Ok(map),
+            Ok(_) => Err(Error::invalid_file(format!(
+                "{file}: expected an object mapping ports to values"
+            ))),
+            Err(err) => Err(Error::invalid_file(format!(
+                "Failed to parse {file}: {err}"
+            ))),
+        }
+    }
+
+    /// The values of a port in each transaction. Each transaction has `len`
+    /// values, one for each cycle the port is live for. A single value is
+    /// repeated for all cycles.
+    fn values(
+        port: &TbPort,
+        vals: &Json,
+        len: u64,
+    ) -> Result<Vec<Vec<Option<BigUint>>>, Error> {
+        let txns = match vals {
+            Json::Array(vals) => vals.iter().collect_vec(),
+            // Outputs of the cocotb harness map transaction indices to values
+            Json::Object(map) => (0..map.len())
+                .map(|i| map.get(&i.to_string()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| {
+                    Error::misc(format!(
+                        "values of `{}' must map every transaction index from 0 to {}",
+                        port.name,
+                        map.len() - 1
+                    ))
+                })?,
+            _ => {
+                return Err(Error::misc(format!(
+                    "values of `{}' must be a list",
+                    port.name
+                )))
+            }
+        };
+        txns.into_iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let vals = match v {
+                    Json::Array(vs) if vs.len() as u64 == len => {
+                        vs.iter().map(|v| Self::value(port, v)).collect()
+                    }
+                    Json::Array(vs) => Err(Error::misc(format!(
+                        "transaction {i} of `{}' has {} values but the port is live for {len} cycles",
+                        port.name,
+                        vs.len()
+                    ))),
+                    v => {
+                        let v = Self::value(port, v)?;
+                        Ok(vec![v; len as usize])
+                    }
+                };
+                vals
+            })
+            .collect()
+    }
+
+    /// Parse a number or a string with a `0x`, `0o`, or `0b` prefix. Strings
+    /// of `x`s, which the cocotb harness prints for undefined outputs, are
+    /// unknown values that are not checked.
+    fn value(port: &TbPort, v: &Json) -> Result<Option<BigUint>, Error> {
+        let err =
+            || Error::misc(format!("invalid value for `{}': {v}", port.name));
+        let n = match v {
+            Json::Number(n) => n.as_u64().map(BigUint::from).ok_or_else(err)?,
+            Json::String(s)
+                if !s.is_empty()
+                    && s.chars().all(|c| matches!(c, 'x' | 'X' | '_')) =>
+            {
+                return Ok(None)
+            }
+            Json::String(s) => {
+                let (radix, digits) = match s.get(..2) {
+                    Some("0x" | "0X") => (16, &s[2..]),
+                    Some("0o" | "0O") => (8, &s[2..]),
+                    Some("0b" | "0B") => (2, &s[2..]),
+                    _ => (10, s.as_str()),
+                };
+                let digits: String =
+                    digits.chars().filter(|c| *c != '_').collect();
+                BigUint::from_str_radix(&digits, radix).map_err(|_| err())?
+            }
+            _ => return Err(err()),
+        };
+        if n.bits() > port.width {
+            return Err(Error::misc(format!(
+                "value {n} of `{}' is not representable in {} bits",
+                port.name, port.width
+            )));
+        }
+        Ok(Some(n))
+    }
+
+    fn line(&mut self, indent: usize, line: impl AsRef<str>) {
+        writeln!(self.out, "{:1$}{2}", "", indent * 2, line.as_ref()).unwrap();
+    }
+
+    fn decl(width: u64) -> String {
+        if width > 1 {
+            format!("[{}:0] ", width - 1)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Condition under which the port is live in cycle `c` and the index of
+    /// the transaction and of the cycle in the transaction.
+    fn live(p: &TbPort) -> (String, String, String) {
+        let rel = if p.start == 0 {
+            "c".to_string()
+        } else {
+            format!("(c - {})", p.start)
+        };
+        let cond = format!(
+            "c >= {} && {rel} % DELAY < {} && {rel} / DELAY < TXNS",
+            p.start,
+            p.len()
+        );
+        (cond, format!("{rel} / DELAY"), format!("{rel} % DELAY"))
+    }
+
+    fn emit(&mut self) {
+        let comp = self.comp.clone();
+        let last_end = self
+            .inputs
+            .iter()
+            .map(|(p, _)| p)
+            .chain(self.outputs.iter().map(|(p, _)| p))
+            .map(|p| p.end)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let cycles = (self.txns as u64 - 1) * self.delay + last_end;
+
+        self.line(
+            0,
+            format!("// Testbench for `{comp}' generated by Filament"),
+        );
+        self.line(0, format!("module {comp}_tb;"));
+        self.line(1, format!("localparam TXNS = {};", self.txns));
+        self.line(1, format!("localparam DELAY = {};", self.delay));
+        self.line(1, format!("localparam CYCLES = {cycles};"));
+        self.line(0, "");
+
+        // Signals connected to the design
+        self.line(1, "logic clk = 0;");
+        self.line(1, "logic reset = 1;");
+        if let Some(go) = &self.go {
+            self.line(1, format!("logic {go} = 0;"));
+        }
+        let unannotated = self.unannotated.clone();
+        for (name, width) in &unannotated {
+            self.line(1, format!("logic {}{name} = 0;", Self::decl(*width)));
+        }
+        let inputs = self.inputs.iter().map(|(p, _)| p);
+        let decls = inputs
+            .map(|p| format!("logic {}{};", Self::decl(p.width), p.name))
+            .chain(self.outputs.iter().map(|(p, _)| {
+                format!("logic {}{};", Self::decl(p.width), p.name)
+            }))
+            .collect_vec();
+        for d in decls {
+            self.line(1, d);
+        }
+        self.line(0, "");
+        let conns = INTERFACE_PORTS
+            .iter()
+            .map(|(_, (n, _, _))| n.to_string())
+            .chain(self.go.clone())
+            .chain(unannotated.iter().map(|(n, _)| n.clone()))
+            .chain(self.inputs.iter().map(|(p, _)| p.name.clone()))
+            .chain(self.outputs.iter().map(|(p, _)| p.name.clone()))
+            .map(|n| format!(".{n}({n})"))
+            .join(",\n    ");
+        self.line(1, format!("{comp} dut (\n    {conns}\n  );"));
+        self.line(0, "");
+
+        // Stimulus, expected outputs, and observed outputs
+        let data = self
+            .inputs
+            .iter()
+            .map(|(p, vals)| (p, Some(vals), "data"))
+            .chain(
+                self.outputs
+                    .iter()
+                    .map(|(p, vals)| (p, vals.as_ref(), "expect")),
+            )
+            .collect_vec();
+        let mut init = vec![];
+        for (p, vals, kind) in data {
+            let Some(vals) = vals else { continue };
+            init.push(format!(
+                "logic {}{}_{kind} [0:{}];",
+                Self::decl(p.width),
+                p.name,
+                vals.len() - 1
+            ));
+            for (i, v) in vals.iter().enumerate() {
+                let v = match v {
+                    Some(v) => format!("{}'d{v}", p.width),
+                    None => "'x".to_string(),
+                };
+                init.push(format!("  {}_{kind}[{i}] = {v};", p.name));
+            }
+        }
+        for (p, _) in &self.outputs {
+            init.push(format!(
+                "logic {}{}_observed [0:TXNS*{}-1];",
+                Self::decl(p.width),
+                p.name,
+                p.len()
+            ));
+        }
+        let (decls, assigns): (Vec<_>, Vec<_>) =
+            init.into_iter().partition(|l| l.starts_with("logic"));
+        for d in decls {
+            self.line(1, d);
+        }
+        self.line(1, "initial begin");
+        for a in assigns {
+            self.line(1, a);
+        }
+        self.line(1, "end");
+        self.line(0, "");
+
+        self.line(1, "always #5 clk = ~clk;");
+        self.line(0, "");
+        self.line(1, "integer c, errors = 0;");
+        self.line(1, "initial begin");
+        self.line(2, format!("repeat ({RESET_CYCLES}) @(posedge clk);"));
+        self.line(2, "reset = 0;");
+        self.line(2, "for (c = 0; c < CYCLES; c = c + 1) begin");
+        // Drive inputs after the rising edge so that they are not sampled in
+        // the same cycle.
+        self.line(3, "@(posedge clk); #1;");
+        if let Some(go) = &self.go {
+            let go = go.clone();
+            self.line(3, format!("{go} = c % DELAY == 0 && c / DELAY < TXNS;"));
+        }
+        for i in 0..self.inputs.len() {
+            let p = &self.inputs[i].0;
+            let (cond, txn, _) = Self::live(p);
+            let name = p.name.clone();
+            self.line(3, format!("{name} = {cond} ? {name}_data[{txn}] : 'x;"));
+        }
+        // Sample outputs after combinational logic has settled
+        self.line(3, "@(negedge clk);");
+        for i in 0..self.outputs.len() {
+            let (p, expect) = &self.outputs[i];
+            let (cond, txn, cycle) = Self::live(p);
+            let idx = format!("{txn} * {} + {cycle}", p.len());
+            let name = p.name.clone();
+            let check = expect.is_some();
+            self.line(3, format!("if ({cond}) begin"));
+            self.line(4, format!("{name}_observed[{idx}] = {name};"));
+            if check {
+                self.line(
+                    4,
+                    format!("if ({name}_expect[{idx}] !== 'x && {name} !== {name}_expect[{idx}]) begin"),
+                );
+                self.line(
+                    5,
+                    format!("$display(\"ERROR: transaction %0d: `{name}' is %0d in cycle %0d of its interval but expected %0d\", {txn}, {name}, {cycle}, {name}_expect[{idx}]);"),
+                );
+                self.line(5, "errors = errors + 1;");
+                self.line(4, "end");
+            }
+            self.line(3, "end");
+        }
+        self.line(2, "end");
+
+        // Print the observed outputs in the format of the cocotb harness
+        self.line(2, "$write(\"{\");");
+        for i in 0..self.outputs.len() {
+            let p = &self.outputs[i].0;
+            let (name, len) = (p.name.clone(), p.len());
+            let sep = if i == 0 { "" } else { ", " };
+            self.line(2, format!("$write(\"{sep}\\\"{name}\\\": {{\");"));
+            self.line(2, "for (c = 0; c < TXNS; c = c + 1) begin");
+            self.line(
+                3,
+                "$write(\"%s\\\"%0d\\\": [\", c == 0 ? \"\" : \", \", c);",
+            );
+            let vals = (0..len)
+                .map(|k| format!("{name}_observed[c * {len} + {k}]"))
+                .join(", ");
+            let fmt = vec!["%0d"; len as usize].join(", ");
+            self.line(3, format!("$write(\"{fmt}]\", {vals});"));
+            self.line(2, "end");
+            self.line(2, "$write(\"}\");");
+        }
+        self.line(2, "$display(\"}\");");
+        if self.outputs.iter().any(|(_, e)| e.is_some()) {
+            self.line(2, "if (errors != 0) $fatal(1, \"FAILED: %0d mismatches\", errors);");
+            self.line(2, "$display(\"PASSED\");");
+        }
+        self.line(2, "$finish;");
+        self.line(1, "end");
+        self.line(0, "endmodule");
+    }
+}
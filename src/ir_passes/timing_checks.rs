@@ -0,0 +1,245 @@
+use super::dump_interface::{
+    CompInterface, DumpInterface, PortInterface, Value,
+};
+use crate::ir_passes::lower::INTERFACE_PORTS;
+use fil_ir as ir;
+use fil_utils::{Diagnostics, Error};
+use itertools::Itertools;
+use std::fmt::Write;
+
+/// An event of the toplevel that is triggered by an interface port
+struct Trigger {
+    /// Name of the interface port
+    port: String,
+    event: String,
+    delay: u64,
+    /// Number of cycles after which no port of the event is live
+    horizon: u64,
+}
+
+/// An input that must hold its value during its availability interval
+struct Stable {
+    name: String,
+    width: u64,
+    /// Interface port of the event the interval is relative to
+    trigger: String,
+    event: String,
+    start: u64,
+    end: u64,
+}
+
+/// Generates runtime checks for the timing contract of the toplevel
+/// component.
+///
+/// Filament proves a design correct assuming that its environment follows
+/// the contract of the toplevel signature. The checks report violations of
+/// the contract using `$error` when the design is simulated:
+/// - an interface port is asserted less than the delay of its event after
+///   the previous time it was asserted, and
+/// - an input changes while it is live.
+///
+/// The checks are placed in a separate module that is attached to the design
+/// using `bind` and both are excluded from synthesis by `SYNTHESIS`. Ports in
+/// bundles and ports whose intervals span multiple events are not checked.
+pub struct TimingChecks {
+    out: String,
+}
+
+impl TimingChecks {
+    /// Generate the checks for the toplevel component
+    pub fn generate(ctx: &ir::Context) -> Result<String, u64> {
+        let mut diag = Diagnostics::default();
+        let Some(idx) = ctx.entrypoint else {
+            diag.add_error(Error::misc(
+                "--timing-checks requires a toplevel component".to_string(),
+            ));
+            return Err(diag.report_all().unwrap());
+        };
+        let comp = DumpInterface::interface(ctx, idx);
+        let mut tc = TimingChecks { out: String::new() };
+        if let Err(err) = tc.module(&comp) {
+            diag.add_error(err);
+            return Err(diag.report_all().unwrap());
+        }
+        Ok(tc.out)
+    }
+
+    /// The value of a number in the interface of the toplevel. `what`
+    /// describes the value when it is not a natural number that fits in 64
+    /// bits, which happens for delays that depend on other events.
+    fn num(v: &Value, what: impl FnOnce() -> String) -> Result<u64, Error> {
+        match v {
+            Value::Num(n) => u64::try_from(*n).ok(),
+            Value::Big(_) | Value::Expr(_) => None,
+        }
+        .ok_or_else(|| {
+            Error::misc(format!(
+                "--timing-checks requires the {} to be a concrete number but it is `{v}'",
+                what()
+            ))
+        })
+    }
+
+    fn line(&mut self, indent: usize, line: impl AsRef<str>) {
+        writeln!(self.out, "{:1$}{2}", "", indent * 2, line.as_ref()).unwrap();
+    }
+
+    /// A time in the syntax of Filament
+    fn time(event: &str, offset: u64) -> String {
+        if offset == 0 {
+            format!("'{event}")
+        } else {
+            format!("'{event}+{offset}")
+        }
+    }
+
+    fn decl(width: u64) -> String {
+        if width > 1 {
+            format!("[{}:0] ", width - 1)
+        } else {
+            String::new()
+        }
+    }
+
+    fn module(&mut self, comp: &CompInterface) -> Result<(), Error> {
+        let inputs = comp
+            .inputs
+            .iter()
+            .filter(|p| p.bundle.is_none() && p.end_event.is_none())
+            .collect_vec();
+        let end = |p: &PortInterface| {
+            Self::num(&p.end, || format!("end of the interval of `{}'", p.name))
+        };
+        let mut triggers = vec![];
+        for ev in &comp.interfaces {
+            let Some(port) = ev.name.clone() else {
+                continue;
+            };
+            let horizon = inputs
+                .iter()
+                .filter(|p| p.event == ev.event)
+                .map(|p| end(p))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .max()
+                .unwrap_or(0);
+            let delay =
+                Self::num(&ev.delay, || format!("delay of '{}", ev.event))?;
+            triggers.push(Trigger {
+                port,
+                event: ev.event.clone(),
+                delay,
+                horizon: horizon.max(delay),
+            });
+        }
+        // Inputs live for a single cycle are trivially stable
+        let mut stable = vec![];
+        for p in &inputs {
+            let Some(trigger) = triggers.iter().find(|t| t.event == p.event)
+            else {
+                continue;
+            };
+            let start = Self::num(&p.start, || {
+                format!("start of the interval of `{}'", p.name)
+            })?;
+            let end = end(p)?;
+            if end - start > 1 {
+                stable.push(Stable {
+                    name: p.name.clone(),
+                    width: Self::num(&p.width, || {
+                        format!("width of `{}'", p.name)
+                    })?,
+                    trigger: trigger.port.clone(),
+                    event: p.event.clone(),
+                    start,
+                    end,
+                });
+            }
+        }
+        let name = format!("{}_timing_checks", comp.name);
+
+        self.line(0, "`ifndef SYNTHESIS");
+        self.line(
+            0,
+            format!(
+                "// Checks that the environment of `{}' follows its timing contract",
+                comp.name
+            ),
+        );
+        let ports = INTERFACE_PORTS
+            .iter()
+            .map(|(_, (n, w, _))| format!("input logic {}{n}", Self::decl(*w)))
+            .chain(triggers.iter().map(|t| format!("input logic {}", t.port)))
+            .chain(stable.iter().map(|s| {
+                format!("input logic {}{}", Self::decl(s.width), s.name)
+            }))
+            .join(",\n  ");
+        self.line(0, format!("module {name} (\n  {ports}\n);"));
+
+        for t in &triggers {
+            let (port, event, delay, horizon) =
+                (&t.port, &t.event, t.delay, t.horizon);
+            self.line(
+                1,
+                format!("// Cycles since `{port}' started a transaction of '{event}"),
+            );
+            self.line(1, format!("integer {port}_since = {horizon};"));
+            self.line(1, "always @(posedge clk) begin");
+            self.line(2, format!("if (reset) {port}_since <= {horizon};"));
+            self.line(2, format!("else if ({port}) {port}_since <= 1;"));
+            self.line(
+                2,
+                format!(
+                    "else if ({port}_since < {horizon}) {port}_since <= {port}_since + 1;"
+                ),
+            );
+            if delay > 1 {
+                self.line(
+                    2,
+                    format!("if (!reset && {port} && {port}_since < {delay})"),
+                );
+                self.line(
+                    3,
+                    format!("$error(\"`{port}' was asserted %0d cycles after the previous transaction but '{event} has a delay of {delay}\", {port}_since);"),
+                );
+            }
+            self.line(1, "end");
+        }
+
+        for s in &stable {
+            let Stable {
+                name,
+                width,
+                trigger,
+                event,
+                start,
+                end,
+            } = s;
+            let live = format!(
+                "[{}, {}]",
+                Self::time(event, *start),
+                Self::time(event, *end)
+            );
+            self.line(1, format!("// `{name}' is live in {live}"));
+            self.line(1, format!("logic {}{name}_prev;", Self::decl(*width)));
+            self.line(1, "always @(posedge clk) begin");
+            self.line(2, format!("{name}_prev <= {name};"));
+            self.line(
+                2,
+                format!(
+                    "if (!reset && !{trigger} && {trigger}_since > {start} && {trigger}_since < {end} && {name} !== {name}_prev)"
+                ),
+            );
+            self.line(
+                3,
+                format!("$error(\"`{name}' changed in cycle %0d of '{event} while it is live in {live}\", {trigger}_since);"),
+            );
+            self.line(1, "end");
+        }
+        self.line(0, "endmodule");
+        self.line(0, "");
+        self.line(0, format!("bind {} {name} timing_checks (.*);", comp.name));
+        self.line(0, "`endif");
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
+pub mod api_diff;
+pub mod ast_passes;
 pub mod cmdline;
 pub mod ir_passes;
 pub mod ir_visitor;
 pub mod resolver;
 pub(crate) mod utils;
+pub mod verilog;
 
 mod macros;
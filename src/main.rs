@@ -1,31 +1,48 @@
+use argh::FromArgs;
 use calyx_backend::Backend;
 use calyx_opt::pass_manager::PassManager;
+use fil_ast as ast;
+use fil_gen::GenExec;
 use fil_ir as ir;
+use fil_utils::BigInt;
+use filament::api_diff::ApiDiff;
 use filament::ir_passes::BuildDomination;
-use filament::{cmdline, ir_passes as ip, resolver::Resolver};
+use filament::verilog::{ImportSv, SplitVerilog};
+use filament::{ast_passes, cmdline, ir_passes as ip, resolver::Resolver};
 use filament::{log_pass, log_time, pass_pipeline};
+use std::{fs, io::Write, path::Path};
 
-// Prints out the interface for main component in the input program.
-fn run(opts: &cmdline::Opts) -> Result<(), u64> {
-    // enable tracing
+/// Enable tracing at the given level
+fn init_logger(level: log::LevelFilter) {
     env_logger::Builder::from_default_env()
         .format_timestamp(None)
         .format_module_path(false)
         .format_target(false)
-        .filter_level(opts.log_level)
+        .filter_level(level)
         .target(env_logger::Target::Stderr)
         .init();
+}
+
+/// Parse the input program and its imports
+fn parse(opts: &cmdline::Opts) -> Result<ast::Namespace, u64> {
+    Resolver::from(opts).parse_namespace().map_err(|e| {
+        eprintln!("Error: {e:?}");
+        1
+    })
+}
+
+// Prints out the interface for main component in the input program.
+fn run(opts: &cmdline::Opts) -> Result<(), u64> {
+    init_logger(opts.log_level);
+
+    let mut ns = parse(opts)?;
+    ns.toplevel = opts.toplevel.clone();
+    if opts.check_externs {
+        log_time!(ast_passes::CheckExterns::check(&ns)?, "check-externs");
+    }
+    // Infer event bindings written as `_`
+    log_time!(ast_passes::InferSchedule::infer(&mut ns)?, "infer-schedule");
 
-    let ns = match Resolver::from(opts).parse_namespace() {
-        Ok(mut ns) => {
-            ns.toplevel = opts.toplevel.clone();
-            ns
-        }
-        Err(e) => {
-            eprintln!("Error: {e:?}");
-            return Err(1);
-        }
-    };
     // Initialize the generator
     let mut gen_exec = if ns.requires_gen() {
         if opts.out_dir.is_none()
@@ -42,14 +59,119 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
         None
     };
 
+    let Some(mut ir) = check(opts, ns, vec![], &mut gen_exec)? else {
+        return Ok(());
+    };
+    if let Some(path) = &opts.mangle_map {
+        ip::MangleMap::write(&ir, path)?;
+    }
+
+    // Return early if we're asked to dump the interface, describe the
+    // toplevel, or generate a testbench for it. This happens before bundle
+    // elimination so that bundles in signatures are preserved.
+    if opts.dump_interface {
+        return ip::DumpInterface::print(opts, &ir);
+    }
+    if let Some(cmdline::Emit::IpXact) = opts.emit {
+        return ip::IpXact::print(&ir);
+    }
+    if opts.emit_testbench.is_some() {
+        return ip::Testbench::print(opts, &ir);
+    }
+
+    // The checks refer to the ports of the toplevel before bundles are
+    // eliminated.
+    let timing_checks = if opts.timing_checks {
+        Some(ip::TimingChecks::generate(&ir)?)
+    } else {
+        None
+    };
+
+    pass_pipeline! { opts, ir;
+        ip::BundleElim,
+        ip::AssignCheck
+    }
+
+    // Return if we are only checking
+    if opts.check {
+        return Ok(());
+    }
+    let (calyx, source_map) = log_time!(
+        ip::Compile::compile(ir, opts.disable_slow_fsms, opts.preserve_names)?,
+        "compile"
+    );
+    match opts.backend {
+        cmdline::Backend::Verilog => {
+            let source_map =
+                opts.emit_source_map.as_deref().map(|p| (&source_map, p));
+            if let Some(dir) = &opts.split_verilog {
+                // The checks are bound to the toplevel from a separate file
+                let checks = timing_checks
+                    .map(|c| {
+                        (format!("{}_timing_checks.sv", calyx.entrypoint), c)
+                    })
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                let calyx = prepare_verilog(calyx, source_map)?;
+                SplitVerilog::write(calyx, dir, &checks)?;
+            } else {
+                gen_verilog(
+                    calyx,
+                    calyx_utils::OutputFile::Stdout,
+                    source_map,
+                )?;
+                if let Some(checks) = timing_checks {
+                    print!("{checks}");
+                }
+            }
+        }
+        cmdline::Backend::Calyx => {
+            if timing_checks.is_some() {
+                log::warn!("--timing-checks is ignored by the calyx backend");
+            }
+            if opts.emit_source_map.is_some() {
+                log::warn!("--emit-source-map is ignored by the calyx backend");
+            }
+            if opts.split_verilog.is_some() {
+                log::warn!("--split-verilog is ignored by the calyx backend");
+            }
+            let out = &mut std::io::stdout();
+            calyx_ir::Printer::write_context(&calyx, false, out).unwrap();
+        }
+    }
+
+    // Drop the generator executor after the execution finishes to ensure that
+    // Calyx has access to the generated file.
+    drop(gen_exec);
+    Ok(())
+}
+
+/// Check the program and monomorphize it starting from its toplevel, which
+/// is instantiated with `params`. Returns `None` if the program was only
+/// searched for the minimum delay of an event.
+fn check(
+    opts: &cmdline::Opts,
+    ns: ast::Namespace,
+    params: Vec<BigInt>,
+    gen_exec: &mut Option<GenExec>,
+) -> Result<Option<ir::Context>, u64> {
     // Transform AST to IR
     let mut ir = log_pass! { opts; ir::transform(ns)?, "astconv" };
+    if opts.auto_balance {
+        pass_pipeline! {opts, ir; ip::AutoBalance }
+    }
+    // Search for the minimum delay instead of compiling the program
+    if opts.min_delay.is_some() {
+        log_time!(ip::MinDelay::search(opts, &ir)?, "min-delay");
+        return Ok(None);
+    }
     pass_pipeline! {opts, ir;
         ip::BuildDomination,
         ip::TypeCheck,
         ip::IntervalCheck,
         ip::PhantomCheck,
-        ip::Assume
+        ip::Assume,
+        ip::Simplify
     }
     if !opts.unsafe_skip_discharge {
         pass_pipeline! {opts, ir; ip::Discharge }
@@ -57,45 +179,145 @@ fn run(opts: &cmdline::Opts) -> Result<(), u64> {
     pass_pipeline! { opts, ir;
         BuildDomination
     };
-    ir = log_pass! { opts; ip::Monomorphize::transform(&ir, &mut gen_exec), "monomorphize"};
+    ir = log_pass! { opts; ip::Monomorphize::transform_with(&ir, gen_exec, params, opts.mangle)?, "monomorphize"};
+    // Bundle elimination generates connections without source information so
+    // the obligations are recomputed before it runs.
+    if opts.recheck_concrete {
+        log_time!(ip::RecheckConcrete::check(opts, &ir)?, "recheck-concrete");
+    }
     pass_pipeline! { opts, ir;
         ip::Simplify,
-        ip::AssignCheck,
-        ip::BundleElim,
         ip::AssignCheck
     }
+    Ok(Some(ir))
+}
 
-    // Return early if we're asked to dump the interface
-    if opts.dump_interface {
-        ip::DumpInterface::print(&ir);
+/// Compile each `test` block in the input program into a simulation harness.
+/// The component under test is monomorphized with the parameters of the test
+/// and written to `<out-dir>/<test>.sv` along with a self-checking testbench
+/// in `<out-dir>/<test>_tb.sv`.
+fn test(opts: &cmdline::Opts) -> Result<(), u64> {
+    init_logger(opts.log_level);
+
+    let Some(dir) = &opts.out_dir else {
+        eprintln!("Error: `filament test' requires an output directory. Provide one using `--out-dir <dir>`.");
+        return Err(1);
+    };
+    let mut ns = parse(opts)?;
+    let tests = std::mem::take(&mut ns.tests);
+    if tests.is_empty() {
+        eprintln!("No tests found in {}.", opts.input.display());
         return Ok(());
     }
+    if let Err(e) = fs::create_dir_all(dir) {
+        eprintln!("Error: failed to create {}: {e}", dir.display());
+        return Err(1);
+    }
 
-    // Return if we are only checking
-    if opts.check {
+    if opts.check_externs {
+        log_time!(ast_passes::CheckExterns::check(&ns)?, "check-externs");
+    }
+    log_time!(ast_passes::InferSchedule::infer(&mut ns)?, "infer-schedule");
+
+    // Tests are compiled independently so that a failing test does not hide
+    // the errors of the others.
+    let errs: u64 = tests
+        .iter()
+        .map(|test| test_harness(opts, &ns, dir, test).err().unwrap_or(0))
+        .sum();
+    if errs > 0 {
+        return Err(errs);
+    }
+    Ok(())
+}
+
+/// Compile the design and the testbench of a single test
+fn test_harness(
+    opts: &cmdline::Opts,
+    ns: &ast::Namespace,
+    dir: &Path,
+    test: &ast::Test,
+) -> Result<(), u64> {
+    // Each test compiles the program with a different toplevel
+    let mut ns = ns.clone();
+    ns.toplevel = test.comp.to_string();
+    let params = ast_passes::TestParams::params(&ns, test)?;
+    let mut gen_exec =
+        ns.requires_gen().then(|| ns.init_gen(opts.out_dir.clone()));
+
+    let Some(mut ir) = check(opts, ns, params, &mut gen_exec)? else {
         return Ok(());
+    };
+    let tb = ip::Testbench::test(&ir, test)?;
+    let timing_checks = if opts.timing_checks {
+        Some(ip::TimingChecks::generate(&ir)?)
+    } else {
+        None
+    };
+    pass_pipeline! { opts, ir;
+        ip::BundleElim,
+        ip::AssignCheck
     }
-    let calyx = log_time!(
-        ip::Compile::compile(ir, opts.disable_slow_fsms, opts.preserve_names),
+    let (calyx, _) = log_time!(
+        ip::Compile::compile(ir, opts.disable_slow_fsms, opts.preserve_names)?,
         "compile"
     );
-    match opts.backend {
-        cmdline::Backend::Verilog => {
-            gen_verilog(calyx).unwrap();
-        }
-        cmdline::Backend::Calyx => {
-            let out = &mut std::io::stdout();
-            calyx_ir::Printer::write_context(&calyx, false, out).unwrap();
+
+    let design = dir.join(format!("{}.sv", test.name));
+    let tb_file = dir.join(format!("{}_tb.sv", test.name));
+    gen_verilog(calyx, calyx_utils::OutputFile::File(design.clone()), None)?;
+    if let Some(checks) = timing_checks {
+        let res = fs::OpenOptions::new()
+            .append(true)
+            .open(&design)
+            .and_then(|mut f| f.write_all(checks.as_bytes()));
+        if let Err(e) = res {
+            eprintln!("Error: failed to write {}: {e}", design.display());
+            return Err(1);
         }
     }
-
-    // Drop the generator executor after the execution finishes to ensure that
-    // Calyx has access to the generated file.
+    if let Err(e) = fs::write(&tb_file, tb) {
+        eprintln!("Error: failed to write {}: {e}", tb_file.display());
+        return Err(1);
+    }
+    println!("{}: {} {}", test.name, design.display(), tb_file.display());
     drop(gen_exec);
     Ok(())
 }
 
-fn gen_verilog(mut ctx: calyx_ir::Context) -> Result<(), calyx_utils::Error> {
+/// Generate Verilog for the program and write its source map to the given
+/// path if requested
+fn gen_verilog(
+    ctx: calyx_ir::Context,
+    out: calyx_utils::OutputFile,
+    source_map: Option<(&ip::SourceMap, &Path)>,
+) -> Result<(), u64> {
+    let ctx = prepare_verilog(ctx, source_map)?;
+    calyx_backend::VerilogBackend.run(ctx, out).unwrap();
+    Ok(())
+}
+
+/// Prepare the program for the Verilog backend and write its source map to
+/// the given path if requested
+fn prepare_verilog(
+    mut ctx: calyx_ir::Context,
+    source_map: Option<(&ip::SourceMap, &Path)>,
+) -> Result<calyx_ir::Context, u64> {
+    verilog_passes(&mut ctx).unwrap();
+    // The map only contains the cells that remain after the Calyx passes
+    if let Some((map, path)) = source_map {
+        if let Err(e) = fs::write(path, map.to_json(&ctx) + "\n") {
+            eprintln!("Error: failed to write {}: {e}", path.display());
+            return Err(1);
+        }
+    }
+    Ok(ctx)
+}
+
+/// Run the Calyx passes required by the Verilog backend
+fn verilog_passes(
+    ctx: &mut calyx_ir::Context,
+) -> Result<(), calyx_utils::Error> {
     let pm = PassManager::default_passes()?;
     let backend_conf = calyx_ir::BackendConf {
         synthesis_mode: false,
@@ -103,19 +325,74 @@ fn gen_verilog(mut ctx: calyx_ir::Context) -> Result<(), calyx_utils::Error> {
         flat_assign: true,
     };
     ctx.bc = backend_conf;
+    // The toplevel keeps its name instead of being wrapped in `main'
     pm.execute_plan(
-        &mut ctx,
+        ctx,
         &["all".to_string()],
-        &["canonicalize".to_string()],
+        &["canonicalize".to_string(), "wrap-main".to_string()],
         false,
-    )?;
-    let backend = calyx_backend::VerilogBackend;
-    backend.run(ctx, calyx_utils::OutputFile::Stdout)
+    )
+}
+
+/// Compare two versions of a library. Returns the number of breaking changes.
+fn api_diff(opts: &cmdline::ApiDiffOpts) -> Result<u64, fil_utils::Error> {
+    init_logger(opts.log_level);
+
+    let old = ast::FilamentParser::parse_file(&opts.old)?;
+    let new = ast::FilamentParser::parse_file(&opts.new)?;
+    Ok(ApiDiff::diff(opts, &old, &new))
+}
+
+/// Parse the options of a subcommand. `args[0]` is the program name and
+/// `args[1]` the subcommand.
+fn subcommand_opts<T: FromArgs>(args: &[String]) -> T {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    T::from_args(&args[..2], &args[2..]).unwrap_or_else(|early| {
+        match early.status {
+            Ok(()) => {
+                println!("{}", early.output);
+                std::process::exit(0)
+            }
+            Err(()) => {
+                eprintln!("{}", early.output);
+                std::process::exit(1)
+            }
+        }
+    })
 }
 
 fn main() {
-    let opts: cmdline::Opts = argh::from_env();
-    match run(&opts) {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("api-diff") => {
+            let opts: cmdline::ApiDiffOpts = subcommand_opts(&args);
+            match api_diff(&opts) {
+                Ok(0) => return,
+                Ok(n) => eprintln!("Found {n} breaking change(s)."),
+                Err(e) => eprintln!("Error: {e:?}"),
+            }
+            std::process::exit(1)
+        }
+        Some("import-sv") => {
+            let opts: cmdline::ImportSvOpts = subcommand_opts(&args);
+            if ImportSv::print(&opts).is_err() {
+                std::process::exit(1)
+            }
+            return;
+        }
+        _ => (),
+    }
+
+    let (opts, res) = if args.get(1).map(String::as_str) == Some("test") {
+        let opts: cmdline::Opts = subcommand_opts(&args);
+        let res = test(&opts);
+        (opts, res)
+    } else {
+        let opts: cmdline::Opts = argh::from_env();
+        let res = run(&opts);
+        (opts, res)
+    };
+    match res {
         Ok(_) => (),
         Err(err) => {
             eprintln!("Compilation failed with {err} errors.");
@@ -90,9 +90,13 @@ impl HoistFacts {
                 ir::Command::Fact(fact) => {
                     if fact.is_assert() {
                         // Otherwise this is a checked assertion that needs to be hoisted.
+                        // Assertions under a false path condition are vacuous.
+                        let pc = self.path_cond(comp);
+                        if pc.is_false(comp) {
+                            continue;
+                        }
                         // Generate prop = path_cond -> fact.prop
-                        let cond =
-                            self.path_cond(comp).implies(fact.prop, comp);
+                        let cond = pc.implies(fact.prop, comp);
                         match comp.assert(cond, fact.reason) {
                             Some(ir::Command::Fact(f)) => self.facts.push(f),
                             None => (),
@@ -0,0 +1,199 @@
+use super::{Direction, Module, Parser, Port};
+use crate::cmdline;
+use fil_ast as ast;
+use fil_utils::{Diagnostics, Id};
+use itertools::Itertools;
+use std::fmt::Write;
+
+/// Names of ports that are passed to every component by the backend and do
+/// not need timing information.
+const UNANNOTATED: [&str; 2] = ["clk", "reset"];
+
+/// Generates skeleton `extern` blocks for the modules defined in Verilog
+/// files.
+///
+/// Parameters of the modules become parameters of the components and widths
+/// are translated into expressions over them. Every component gets a single
+/// event `'G` and all ports are assumed to be live for the first cycle of it.
+/// These placeholders must be updated to reflect the actual timing behavior
+/// of the modules.
+pub struct ImportSv {
+    out: String,
+}
+
+impl ImportSv {
+    /// Print the extern blocks for the files in `opts`
+    pub fn print(opts: &cmdline::ImportSvOpts) -> Result<(), u64> {
+        let mut diag = Diagnostics::default();
+        let mut imp = ImportSv { out: String::new() };
+        let mut found = false;
+        for path in &opts.files {
+            let modules = match Parser::parse_file(path, &mut diag) {
+                Ok(modules) => modules,
+                Err(err) => {
+                    diag.add_error(err);
+                    continue;
+                }
+            };
+            let modules = modules
+                .iter()
+                .filter(|m| {
+                    opts.modules.is_empty()
+                        || opts.modules.iter().any(|n| m.name.as_ref() == n)
+                })
+                .collect_vec();
+            if modules.is_empty() {
+                continue;
+            }
+            found = true;
+            imp.extern_block(&path.to_string_lossy(), &modules);
+        }
+        if let Some(errs) = diag.report_all() {
+            return Err(errs);
+        }
+        if !found {
+            eprintln!("No Verilog modules found.");
+            return Err(1);
+        }
+        print!("{}", imp.out);
+        Ok(())
+    }
+
+    fn extern_block(&mut self, path: &str, modules: &[&Module]) {
+        if !self.out.is_empty() {
+            writeln!(self.out).unwrap();
+        }
+        writeln!(self.out, "// Generated by `filament import-sv {path}`.")
+            .unwrap();
+        writeln!(
+            self.out,
+            "// The events and port availability intervals are placeholders.\n\
+             // Update them to match the timing behavior of each module."
+        )
+        .unwrap();
+        writeln!(self.out, "extern \"{path}\" {{").unwrap();
+        for (i, m) in modules.iter().enumerate() {
+            if i > 0 {
+                writeln!(self.out).unwrap();
+            }
+            self.component(m);
+        }
+        writeln!(self.out, "}}").unwrap();
+    }
+
+    fn component(&mut self, m: &Module) {
+        let sv_params = m.params.iter().filter(|p| !p.local).collect_vec();
+        let params = sv_params.iter().map(|p| p.name.inner()).collect_vec();
+
+        // The parameters after `required` are optional if all of them have
+        // defaults that only refer to the required parameters.
+        let required = (0..=params.len())
+            .find(|r| {
+                sv_params[*r..].iter().all(|p| {
+                    p.default
+                        .as_ref()
+                        .map_or(false, |d| Self::over_params(d, &params[..*r]))
+                })
+            })
+            .unwrap();
+        let sig_params = sv_params
+            .iter()
+            .enumerate()
+            .map(|(i, p)| match &p.default {
+                Some(d) if i >= required => format!("?{}={d}", p.name),
+                _ => p.name.to_string(),
+            })
+            .collect_vec();
+        let sig_params = if sig_params.is_empty() {
+            String::new()
+        } else {
+            format!("[{}]", sig_params.join(", "))
+        };
+
+        let (inputs, outputs): (Vec<_>, Vec<_>) =
+            m.ports.iter().partition(|p| p.dir != Direction::Output);
+        writeln!(self.out, "   comp {}{sig_params}<'G: 1>(", m.name).unwrap();
+        for p in inputs {
+            self.port(p, &params);
+        }
+        writeln!(self.out, "   ) -> (").unwrap();
+        for p in outputs {
+            self.port(p, &params);
+        }
+        let widths = sv_params
+            .iter()
+            .filter(|p| {
+                m.ports.iter().any(|port| {
+                    matches!(&port.width, Some(ast::Expr::Abstract(id)) if id.inner() == p.name.inner())
+                })
+            })
+            .map(|p| format!("{} > 0", p.name))
+            .collect_vec();
+        if widths.is_empty() {
+            writeln!(self.out, "   );").unwrap();
+        } else {
+            writeln!(self.out, "   ) where {};", widths.join(", ")).unwrap();
+        }
+    }
+
+    fn port(&mut self, p: &Port, params: &[&Id]) {
+        let width = p.width.as_ref().filter(|w| Self::over_params(w, params));
+        let unsupported = match (p.dir, width) {
+            (Direction::Inout, _) => Some("inout ports are not supported"),
+            _ if p.unpacked => Some("unpacked ports are not supported"),
+            (_, None) => Some("width could not be translated"),
+            _ => None,
+        };
+        if let Some(reason) = unsupported {
+            let typ = p.typ.inner().trim();
+            writeln!(
+                self.out,
+                "      // FIXME: {}: {reason}: `{}{}{}'",
+                p.name,
+                p.dir,
+                if typ.is_empty() { "" } else { " " },
+                typ
+            )
+            .unwrap();
+            return;
+        }
+        let width = width.unwrap();
+        if p.dir == Direction::Input
+            && UNANNOTATED.contains(&p.name.as_ref())
+            && width.is_const(1)
+        {
+            writeln!(self.out, "      {}: 1,", p.name).unwrap();
+        } else if matches!(
+            width,
+            ast::Expr::Concrete(_) | ast::Expr::Abstract(_)
+        ) {
+            writeln!(self.out, "      {}: ['G, 'G+1] {width},", p.name)
+                .unwrap();
+        } else {
+            // The backend can only instantiate external components whose
+            // port widths are parameters or constants.
+            writeln!(
+                self.out,
+                "      // FIXME: width is not a parameter or constant: {}: ['G, 'G+1] {width},",
+                p.name
+            )
+            .unwrap();
+        }
+    }
+
+    /// Is this expression only defined over the given parameters?
+    fn over_params(e: &ast::Expr, params: &[&Id]) -> bool {
+        match e {
+            ast::Expr::Concrete(_) => true,
+            ast::Expr::Abstract(id) => params.contains(&id.inner()),
+            ast::Expr::ParamAccess { .. } => false,
+            ast::Expr::App { args, .. } => {
+                args.iter().all(|a| Self::over_params(a, params))
+            }
+            ast::Expr::Op { left, right, .. } => {
+                Self::over_params(left, params)
+                    && Self::over_params(right, params)
+            }
+        }
+    }
+}
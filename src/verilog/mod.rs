@@ -0,0 +1,9 @@
+//! Support for reading the Verilog modules that Filament components wrap and
+//! writing the Verilog generated for a program.
+mod import;
+mod parser;
+mod split;
+
+pub use import::ImportSv;
+pub use parser::{Direction, Module, Param, Parser, Port};
+pub use split::SplitVerilog;
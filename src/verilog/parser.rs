@@ -0,0 +1,747 @@
+use fil_ast::{self as ast, Loc};
+use fil_utils::{
+    Diagnostics, Error, FileIdx, GPosIdx, GlobalPositionTable, Id,
+};
+use std::{fs, path::Path};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Input,
+    Output,
+    Inout,
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::Input => write!(f, "input"),
+            Direction::Output => write!(f, "output"),
+            Direction::Inout => write!(f, "inout"),
+        }
+    }
+}
+
+/// A parameter of a Verilog module
+pub struct Param {
+    pub name: Loc<Id>,
+    /// The default value if it can be represented as a Filament expression
+    pub default: Option<ast::Expr>,
+    /// Local parameters cannot be overridden by instances
+    pub local: bool,
+}
+
+/// A port of a Verilog module
+pub struct Port {
+    pub name: Loc<Id>,
+    pub dir: Direction,
+    /// The width of the port if it can be represented as a Filament
+    /// expression over the parameters of the module
+    pub width: Option<ast::Expr>,
+    /// Source text of the type of the port
+    pub typ: Loc<String>,
+    /// The port has unpacked dimensions (`logic [7:0] mem [4]`)
+    pub unpacked: bool,
+}
+
+/// The header of a Verilog module
+pub struct Module {
+    pub name: Loc<Id>,
+    pub params: Vec<Param>,
+    pub ports: Vec<Port>,
+}
+
+impl Module {
+    pub fn port(&self, name: &str) -> Option<&Port> {
+        self.ports.iter().find(|p| p.name.as_ref() == name)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Tok {
+    Ident(String),
+    Num(String),
+    Sym(&'static str),
+}
+
+const SYMS: [&str; 21] = [
+    "**", "<<", ">>", "::", "(", ")", "[", "]", "{", "}", ",", ";", "=", "#",
+    "+", "-", "*", "/", "%", ":", ".",
+];
+
+/// Keywords that can appear in the type of a port or parameter
+const TYPE_KWS: [&str; 12] = [
+    "wire", "reg", "logic", "bit", "var", "tri", "signed", "unsigned", "int",
+    "integer", "uwire", "byte",
+];
+
+/// Parses the headers of modules defined in Verilog files. Module bodies are
+/// skipped except for non-ANSI port and parameter declarations.
+pub struct Parser<'a> {
+    file: FileIdx,
+    src: &'a str,
+    toks: Vec<(Tok, usize, usize)>,
+    idx: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Parse all the module headers in the given file
+    pub fn parse_file(
+        path: &Path,
+        diag: &mut Diagnostics,
+    ) -> Result<Vec<Module>, Error> {
+        let content = fs::read_to_string(path).map_err(|err| {
+            Error::invalid_file(format!(
+                "Failed to read {}: {err}",
+                path.to_string_lossy(),
+            ))
+        })?;
+        let file = GlobalPositionTable::as_mut()
+            .add_file(path.to_string_lossy().to_string(), content);
+        let (_, src) = GlobalPositionTable::as_ref().get_file_data(file);
+        let mut parser = Parser {
+            file,
+            src,
+            toks: vec![],
+            idx: 0,
+        };
+        parser.lex(diag)?;
+        parser.modules(diag)
+    }
+
+    fn pos(&self, start: usize, end: usize) -> GPosIdx {
+        GPosIdx(GlobalPositionTable::as_mut().add_pos(self.file, start, end))
+    }
+
+    /// Error at the current token
+    fn error(&self, msg: &str, diag: &mut Diagnostics) -> Error {
+        let (start, end) = self
+            .toks
+            .get(self.idx)
+            .map_or((self.src.len(), self.src.len()), |(_, s, e)| (*s, *e));
+        Error::malformed(format!("failed to parse Verilog: {msg}"))
+            .add_note(diag.add_info(msg, self.pos(start, end)))
+    }
+
+    fn lex(&mut self, diag: &mut Diagnostics) -> Result<(), Error> {
+        let bytes = self.src.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i];
+            let rest = &self.src[i..];
+            if c.is_ascii_whitespace() {
+                i += 1;
+            } else if rest.starts_with("//") || c == b'`' {
+                // Comments and compiler directives end at the line
+                i += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*")
+                || (rest.starts_with("(*") && !rest.starts_with("(*)"))
+            {
+                let close = if c == b'/' { "*/" } else { "*)" };
+                let Some(end) = rest[2..].find(close) else {
+                    self.idx = self.toks.len();
+                    return Err(self.error("unterminated comment", diag));
+                };
+                i += end + 4;
+            } else if c == b'"' {
+                let end = rest[1..].find('"').map_or(rest.len(), |e| e + 2);
+                i += end;
+            } else if c.is_ascii_alphabetic() || c == b'_' || c == b'$' {
+                let len = rest
+                    .find(|c: char| {
+                        !(c.is_ascii_alphanumeric() || c == '_' || c == '$')
+                    })
+                    .unwrap_or(rest.len());
+                self.toks.push((Tok::Ident(rest[..len].into()), i, i + len));
+                i += len;
+            } else if c.is_ascii_digit() || c == b'\'' {
+                // Numbers with optional sizes and bases: 8'hFF, 'd3, 10
+                let len = rest
+                    .find(|c: char| {
+                        !(c.is_ascii_alphanumeric() || c == '\'' || c == '_')
+                    })
+                    .unwrap_or(rest.len());
+                self.toks.push((Tok::Num(rest[..len].into()), i, i + len));
+                i += len;
+            } else if let Some(sym) =
+                SYMS.iter().find(|s| rest.starts_with(**s))
+            {
+                self.toks.push((Tok::Sym(sym), i, i + sym.len()));
+                i += sym.len();
+            } else {
+                // Other operators only occur in module bodies and in
+                // expressions we do not translate.
+                let len = rest.chars().next().unwrap().len_utf8();
+                self.toks.push((Tok::Sym("?"), i, i + len));
+                i += len;
+            }
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&Tok> {
+        self.toks.get(self.idx).map(|(t, _, _)| t)
+    }
+
+    fn is_ident(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Ident(s)) if s == kw)
+    }
+
+    fn is_sym(&self, sym: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Sym(s)) if *s == sym)
+    }
+
+    fn expect_sym(
+        &mut self,
+        sym: &str,
+        diag: &mut Diagnostics,
+    ) -> Result<(), Error> {
+        if self.is_sym(sym) {
+            self.idx += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{sym}'"), diag))
+        }
+    }
+
+    fn ident(&mut self, diag: &mut Diagnostics) -> Result<Loc<Id>, Error> {
+        match self.toks.get(self.idx) {
+            Some((Tok::Ident(name), start, end)) => {
+                let pos = self.pos(*start, *end);
+                let name = Id::new(name);
+                self.idx += 1;
+                Ok(Loc::new(name, pos))
+            }
+            _ => Err(self.error("expected an identifier", diag)),
+        }
+    }
+
+    /// Index of the token that closes the bracket at the current token
+    fn matching(&self) -> usize {
+        self.close_of(self.idx)
+    }
+
+    /// Split the tokens in `start..end` at commas that are not nested in
+    /// brackets.
+    fn split_commas(&self, start: usize, end: usize) -> Vec<(usize, usize)> {
+        let mut items = vec![];
+        let (mut depth, mut item) = (0, start);
+        for i in start..end {
+            match self.toks[i].0 {
+                Tok::Sym("(" | "[" | "{") => depth += 1,
+                Tok::Sym(")" | "]" | "}") => depth -= 1,
+                Tok::Sym(",") if depth == 0 => {
+                    items.push((item, i));
+                    item = i + 1;
+                }
+                _ => (),
+            }
+        }
+        if item < end {
+            items.push((item, end));
+        }
+        items
+    }
+
+    /// Source text spanned by the tokens `start..end`
+    fn text(&self, start: usize, end: usize) -> Loc<String> {
+        if start >= end {
+            let pos = self.toks.get(start).map_or(0, |t| t.1);
+            return Loc::new(String::new(), self.pos(pos, pos));
+        }
+        let (s, e) = (self.toks[start].1, self.toks[end - 1].2);
+        Loc::new(self.src[s..e].to_string(), self.pos(s, e))
+    }
+
+    fn modules(
+        &mut self,
+        diag: &mut Diagnostics,
+    ) -> Result<Vec<Module>, Error> {
+        let mut modules = vec![];
+        while self.idx < self.toks.len() {
+            if self.is_ident("module") || self.is_ident("macromodule") {
+                self.idx += 1;
+                modules.push(self.module(diag)?);
+            } else {
+                self.idx += 1;
+            }
+        }
+        Ok(modules)
+    }
+
+    fn module(&mut self, diag: &mut Diagnostics) -> Result<Module, Error> {
+        if self.is_ident("automatic") || self.is_ident("static") {
+            self.idx += 1;
+        }
+        let name = self.ident(diag)?;
+        // Package imports in the header
+        while self.is_ident("import") {
+            while !self.is_sym(";") && self.peek().is_some() {
+                self.idx += 1;
+            }
+            self.idx += 1;
+        }
+
+        let mut params = vec![];
+        if self.is_sym("#") {
+            self.idx += 1;
+            if !self.is_sym("(") {
+                return Err(self.error("expected `(' after `#'", diag));
+            }
+            let end = self.matching();
+            let mut local = false;
+            for (s, e) in self.split_commas(self.idx + 1, end) {
+                if let Some(param) = self.param(s, e, &mut local) {
+                    params.push(param);
+                }
+            }
+            self.idx = end + 1;
+        }
+
+        let mut ports = vec![];
+        // Names of ports declared in the body of a non-ANSI header
+        let mut names = vec![];
+        if self.is_sym("(") {
+            let end = self.matching();
+            let mut prev: Option<(Direction, usize, usize)> = None;
+            for (s, e) in self.split_commas(self.idx + 1, end) {
+                match self.port(s, e, &mut prev) {
+                    Some(port) => ports.push(port),
+                    None => {
+                        let (_, start, end) = &self.toks[e - 1];
+                        let name = self.src[*start..*end].to_string();
+                        names.push((name, self.pos(*start, *end)))
+                    }
+                }
+            }
+            self.idx = end + 1;
+        }
+        self.expect_sym(";", diag)?;
+
+        self.body(&mut params, &mut ports, diag)?;
+        // Order the ports declared in the body like the header
+        if !names.is_empty() {
+            for (name, pos) in &names {
+                if !ports.iter().any(|p| p.name.as_ref() == name.as_str()) {
+                    return Err(Error::malformed(format!(
+                        "failed to parse Verilog: port `{name}' has no direction"
+                    ))
+                    .add_note(diag.add_info("port declared here", *pos)));
+                }
+            }
+            ports.sort_by_key(|p| {
+                names
+                    .iter()
+                    .position(|(n, _)| p.name.as_ref() == n.as_str())
+            });
+        }
+
+        Ok(Module {
+            name,
+            params,
+            ports,
+        })
+    }
+
+    /// Parse a parameter declaration in `start..end`. Returns `None` for type
+    /// parameters.
+    fn param(
+        &self,
+        start: usize,
+        end: usize,
+        local: &mut bool,
+    ) -> Option<Param> {
+        let toks = &self.toks[start..end];
+        match &toks.first()?.0 {
+            Tok::Ident(kw) if kw == "parameter" => *local = false,
+            Tok::Ident(kw) if kw == "localparam" => *local = true,
+            _ => (),
+        }
+        let eq = toks
+            .iter()
+            .position(|(t, _, _)| *t == Tok::Sym("="))
+            .unwrap_or(toks.len());
+        let is_type =
+            toks.iter().any(|(t, _, _)| *t == Tok::Ident("type".into()));
+        if is_type || eq == 0 {
+            return None;
+        }
+        let (Tok::Ident(name), s, e) = &toks[eq - 1] else {
+            return None;
+        };
+        let default = (eq < toks.len())
+            .then(|| self.expr(start + eq + 1, end))
+            .flatten();
+        Some(Param {
+            name: Loc::new(Id::new(name), self.pos(*s, *e)),
+            default,
+            local: *local,
+        })
+    }
+
+    /// Parse a port declaration in `start..end` of an ANSI-style header.
+    /// `prev` is the direction and type of the previous port which is reused
+    /// by declarations that only have a name. Returns `None` if this is a
+    /// non-ANSI header that only names the port.
+    fn port(
+        &self,
+        start: usize,
+        end: usize,
+        prev: &mut Option<(Direction, usize, usize)>,
+    ) -> Option<Port> {
+        let dir = match &self.toks[start].0 {
+            Tok::Ident(kw) if kw == "input" => Some(Direction::Input),
+            Tok::Ident(kw) if kw == "output" => Some(Direction::Output),
+            Tok::Ident(kw) if kw == "inout" => Some(Direction::Inout),
+            _ => None,
+        };
+        // The name is the last identifier before unpacked dimensions or a
+        // default value.
+        let default = (start..end)
+            .find(|i| {
+                self.toks[*i].0 == Tok::Sym("=") && self.depth(start, *i) == 0
+            })
+            .unwrap_or(end);
+        let name = (start..default).rev().find(|i| {
+            matches!(self.toks[*i].0, Tok::Ident(_))
+                && self.depth(start, *i) == 0
+        })?;
+        let (dir, typ_start, typ_end) = match dir {
+            Some(dir) => (dir, start + 1, name),
+            None if name == start => {
+                let (dir, s, e) = (*prev)?;
+                (dir, s, e)
+            }
+            // A port with a type but without a direction
+            None => (prev.map_or(Direction::Inout, |p| p.0), start, name),
+        };
+        *prev = Some((dir, typ_start, typ_end));
+        Some(self.port_decl(dir, typ_start, typ_end, name, end))
+    }
+
+    /// Bracket depth of the token at `i` relative to `start`
+    fn depth(&self, start: usize, i: usize) -> i32 {
+        self.toks[start..i].iter().fold(0, |d, (t, _, _)| match t {
+            Tok::Sym("(" | "[" | "{") => d + 1,
+            Tok::Sym(")" | "]" | "}") => d - 1,
+            _ => d,
+        })
+    }
+
+    /// A port with the type in `typ_start..typ_end`, the name at `name`, and
+    /// unpacked dimensions until `end`.
+    fn port_decl(
+        &self,
+        dir: Direction,
+        typ_start: usize,
+        typ_end: usize,
+        name: usize,
+        end: usize,
+    ) -> Port {
+        let (_, s, e) = &self.toks[name];
+        Port {
+            name: Loc::new(Id::new(&self.src[*s..*e]), self.pos(*s, *e)),
+            dir,
+            width: self.width(typ_start, typ_end),
+            typ: self.text(typ_start, typ_end),
+            unpacked: self.toks[name + 1..end]
+                .iter()
+                .any(|(t, _, _)| *t == Tok::Sym("[")),
+        }
+    }
+
+    /// The width of the type in `start..end`
+    fn width(&self, start: usize, end: usize) -> Option<ast::Expr> {
+        let mut width = ast::Expr::concrete(1);
+        let mut i = start;
+        while i < end {
+            match &self.toks[i].0 {
+                Tok::Ident(kw) if kw == "integer" || kw == "int" => {
+                    width = width * ast::Expr::concrete(32)
+                }
+                Tok::Ident(kw) if kw == "byte" => {
+                    width = width * ast::Expr::concrete(8)
+                }
+                Tok::Ident(kw) if TYPE_KWS.contains(&kw.as_str()) => (),
+                // User-defined types and interfaces
+                Tok::Ident(_) => return None,
+                Tok::Sym("[") => {
+                    let close = self.close_of(i);
+                    let colon = (i + 1..close).find(|j| {
+                        self.toks[*j].0 == Tok::Sym(":")
+                            && self.depth(i + 1, *j) == 0
+                    })?;
+                    let msb = self.expr(i + 1, colon)?;
+                    let lsb = self.expr(colon + 1, close)?;
+                    width = width * Self::range_width(msb, lsb);
+                    i = close;
+                }
+                _ => return None,
+            }
+            i += 1;
+        }
+        Some(width)
+    }
+
+    /// Index of the bracket closing the one at `i`
+    fn close_of(&self, i: usize) -> usize {
+        let mut depth = 0;
+        for (j, (tok, _, _)) in self.toks.iter().enumerate().skip(i) {
+            match tok {
+                Tok::Sym("(" | "[" | "{") => depth += 1,
+                Tok::Sym(")" | "]" | "}") => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return j;
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.toks.len()
+    }
+
+    /// Width of the range `[msb:lsb]`
+    fn range_width(msb: ast::Expr, lsb: ast::Expr) -> ast::Expr {
+        // Ranges are usually written as `[W-1:0]` or `[0:W-1]`
+        let (hi, lo) = if msb.is_const(0) && !lsb.is_const(0) {
+            (lsb, msb)
+        } else {
+            (msb, lsb)
+        };
+        match (hi, lo) {
+            (
+                ast::Expr::Op {
+                    op: ast::Op::Sub,
+                    left,
+                    right,
+                },
+                lo,
+            ) if right.is_const(1) && lo.is_const(0) => *left,
+            (hi, lo) => hi - lo + ast::Expr::concrete(1),
+        }
+    }
+
+    /// Translate the expression in `start..end` into a Filament expression
+    fn expr(&self, start: usize, end: usize) -> Option<ast::Expr> {
+        let mut p = ExprParser {
+            parser: self,
+            idx: start,
+            end,
+        };
+        let e = p.sum()?;
+        (p.idx == end).then_some(e)
+    }
+
+    /// Parse the body of a module for non-ANSI port and parameter
+    /// declarations.
+    fn body(
+        &mut self,
+        params: &mut Vec<Param>,
+        ports: &mut Vec<Port>,
+        diag: &mut Diagnostics,
+    ) -> Result<(), Error> {
+        let header_params = !params.is_empty();
+        loop {
+            let Some(tok) = self.peek().cloned() else {
+                return Err(self.error("expected `endmodule'", diag));
+            };
+            let Tok::Ident(kw) = tok else {
+                self.idx += 1;
+                continue;
+            };
+            match kw.as_str() {
+                "endmodule" => {
+                    self.idx += 1;
+                    return Ok(());
+                }
+                // Declarations in functions and tasks are not ports
+                "function" | "task" => {
+                    let end = format!("end{kw}");
+                    while self.peek().is_some() && !self.is_ident(&end) {
+                        self.idx += 1;
+                    }
+                }
+                "input" | "output" | "inout" => {
+                    let dir = match kw.as_str() {
+                        "input" => Direction::Input,
+                        "output" => Direction::Output,
+                        _ => Direction::Inout,
+                    };
+                    let end = self.statement_end();
+                    let items = self.split_commas(self.idx + 1, end);
+                    // The type is shared by all the names in the declaration
+                    let typ_end = items.first().map_or(end, |(_, e)| {
+                        (self.idx + 1..*e)
+                            .rev()
+                            .find(|i| {
+                                matches!(self.toks[*i].0, Tok::Ident(_))
+                                    && self.depth(self.idx + 1, *i) == 0
+                            })
+                            .unwrap_or(*e)
+                    });
+                    for (n, (s, e)) in items.iter().enumerate() {
+                        let name = if n == 0 { typ_end } else { *s };
+                        if matches!(
+                            self.toks.get(name),
+                            Some((Tok::Ident(_), _, _))
+                        ) {
+                            ports.push(self.port_decl(
+                                dir,
+                                self.idx + 1,
+                                typ_end,
+                                name,
+                                *e,
+                            ));
+                        }
+                    }
+                    self.idx = end;
+                }
+                "localparam" => self.body_params(params),
+                "parameter" if !header_params => self.body_params(params),
+                _ => self.idx += 1,
+            }
+        }
+    }
+
+    /// Parse a parameter declaration statement in the body
+    fn body_params(&mut self, params: &mut Vec<Param>) {
+        let end = self.statement_end();
+        let mut local = false;
+        for (s, e) in self.split_commas(self.idx, end) {
+            if let Some(p) = self.param(s, e, &mut local) {
+                params.push(p);
+            }
+        }
+        self.idx = end;
+    }
+
+    /// Index of the `;` ending the current statement
+    fn statement_end(&self) -> usize {
+        (self.idx..self.toks.len())
+            .find(|i| self.toks[*i].0 == Tok::Sym(";"))
+            .unwrap_or(self.toks.len())
+    }
+}
+
+/// Recursive descent parser for the subset of Verilog expressions that can be
+/// represented in Filament.
+struct ExprParser<'p, 'a> {
+    parser: &'p Parser<'a>,
+    idx: usize,
+    end: usize,
+}
+
+impl ExprParser<'_, '_> {
+    fn peek(&self) -> Option<&Tok> {
+        (self.idx < self.end).then(|| &self.parser.toks[self.idx].0)
+    }
+
+    fn eat(&mut self, sym: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Sym(s)) if *s == sym) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Shifts have the lowest precedence among the supported operators
+    fn sum(&mut self) -> Option<ast::Expr> {
+        let mut l = self.additive()?;
+        loop {
+            if self.eat("<<") {
+                let r = self.additive()?;
+                l = l * ast::Expr::func(ast::Fn::Pow2, vec![r]);
+            } else if self.eat(">>") {
+                let r = self.additive()?;
+                l = l / ast::Expr::func(ast::Fn::Pow2, vec![r]);
+            } else {
+                return Some(l);
+            }
+        }
+    }
+
+    fn additive(&mut self) -> Option<ast::Expr> {
+        let mut l = self.term()?;
+        loop {
+            if self.eat("+") {
+                l += self.term()?;
+            } else if self.eat("-") {
+                l = l - self.term()?;
+            } else {
+                return Some(l);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Option<ast::Expr> {
+        let mut l = self.power()?;
+        loop {
+            if self.eat("*") {
+                l = l * self.power()?;
+            } else if self.eat("/") {
+                l = l / self.power()?;
+            } else if self.eat("%") {
+                l = l % self.power()?;
+            } else {
+                return Some(l);
+            }
+        }
+    }
+
+    fn power(&mut self) -> Option<ast::Expr> {
+        let base = self.atom()?;
+        if !self.eat("**") {
+            return Some(base);
+        }
+        // Only powers of two can be represented
+        let exp = self.atom()?;
+        base.is_const(2)
+            .then(|| ast::Expr::func(ast::Fn::Pow2, vec![exp]))
+    }
+
+    fn atom(&mut self) -> Option<ast::Expr> {
+        let (tok, s, e) = self.parser.toks.get(self.idx)?.clone();
+        if self.idx >= self.end {
+            return None;
+        }
+        self.idx += 1;
+        match tok {
+            Tok::Num(n) => Self::number(&n).map(ast::Expr::concrete),
+            Tok::Ident(f) if f == "$clog2" => {
+                if !self.eat("(") {
+                    return None;
+                }
+                let arg = self.sum()?;
+                self.eat(")")
+                    .then(|| ast::Expr::func(ast::Fn::Log2, vec![arg]))
+            }
+            Tok::Ident(name) if !name.starts_with('$') => Some(ast::Expr::abs(
+                Loc::new(Id::new(name), self.parser.pos(s, e)),
+            )),
+            Tok::Sym("(") => {
+                let e = self.sum()?;
+                self.eat(")").then_some(e)
+            }
+            Tok::Sym("+") => self.atom(),
+            _ => None,
+        }
+    }
+
+    /// Value of a Verilog number literal like `8'hFF` or `32`
+    fn number(n: &str) -> Option<u64> {
+        let n = n.replace('_', "");
+        let Some((_, val)) = n.split_once('\'') else {
+            return n.parse().ok();
+        };
+        let val = val.trim_start_matches(['s', 'S']);
+        let (radix, digits) = match val.chars().next()? {
+            'h' | 'H' => (16, &val[1..]),
+            'd' | 'D' => (10, &val[1..]),
+            'o' | 'O' => (8, &val[1..]),
+            'b' | 'B' => (2, &val[1..]),
+            _ => return None,
+        };
+        u64::from_str_radix(digits, radix).ok()
+    }
+}
@@ -0,0 +1,152 @@
+use calyx_backend::{Backend, VerilogBackend};
+use calyx_frontend::LibrarySignatures;
+use calyx_ir as calyx;
+use calyx_utils::OutputFile;
+use fil_utils::{Diagnostics, Error};
+use itertools::Itertools;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Directory, relative to the output directory, that external sources are
+/// copied to.
+const EXTERN_DIR: &str = "extern";
+
+/// Writes the Verilog for a Calyx program as one file per module.
+///
+/// Every component, including the FSMs generated by the backend, is written
+/// to `<dir>/<module>.sv`. The Verilog files that implement external
+/// components are copied to `<dir>/extern/` and the primitives built into
+/// the Calyx backend are written to `<dir>/extern/calyx.sv`. Finally, a file
+/// list `<dir>/<toplevel>.f` names all the files for simulators and synthesis
+/// tools. File names only depend on module names so that repeated runs
+/// update the same files.
+pub struct SplitVerilog {
+    dir: PathBuf,
+    /// Files written so far
+    files: Vec<PathBuf>,
+    diag: Diagnostics,
+}
+
+impl SplitVerilog {
+    /// Write the program `ctx`, which must have been prepared for the Verilog
+    /// backend, to `dir`. `extra` contains additional files, given by their
+    /// name and contents, that are written after the modules.
+    pub fn write(
+        mut ctx: calyx::Context,
+        dir: &Path,
+        extra: &[(String, String)],
+    ) -> Result<(), u64> {
+        let mut split = SplitVerilog {
+            dir: dir.to_path_buf(),
+            files: vec![],
+            diag: Diagnostics::default(),
+        };
+        if let Err(err) = VerilogBackend::validate(&ctx) {
+            split.diag.add_error(Error::misc(format!(
+                "Invalid Calyx program: {err:?}"
+            )));
+            return Err(split.diag.report_all().unwrap());
+        }
+        if let Err(err) = fs::create_dir_all(dir.join(EXTERN_DIR)) {
+            split.error(dir, err);
+            return Err(split.diag.report_all().unwrap());
+        }
+
+        split.externs(&mut ctx);
+        split.modules(&mut ctx);
+        for (name, contents) in extra {
+            let path = dir.join(name);
+            match fs::write(&path, contents) {
+                Ok(()) => split.files.push(path),
+                Err(err) => split.error(&path, err),
+            }
+        }
+        split.file_list(&ctx.entrypoint);
+
+        match split.diag.report_all() {
+            Some(errs) => Err(errs),
+            None => Ok(()),
+        }
+    }
+
+    fn error(&mut self, path: &Path, err: impl std::fmt::Display) {
+        self.diag.add_error(Error::invalid_file(format!(
+            "Failed to write {}: {err}",
+            path.display()
+        )));
+    }
+
+    /// Copy the sources of external components and write the primitives of
+    /// the Calyx backend
+    fn externs(&mut self, ctx: &mut calyx::Context) {
+        let mut names = HashSet::new();
+        for src in ctx.lib.extern_paths().into_iter().unique().sorted() {
+            // Files with the same name from different directories are
+            // disambiguated using a suffix.
+            let stem = src.file_stem().unwrap_or_default().to_string_lossy();
+            let ext = src.extension().unwrap_or_default().to_string_lossy();
+            let name = (0..)
+                .map(|i| match i {
+                    0 => format!("{stem}.{ext}"),
+                    i => format!("{stem}_{i}.{ext}"),
+                })
+                .find(|n| n != "calyx.sv" && names.insert(n.clone()))
+                .unwrap();
+            let dst = self.dir.join(EXTERN_DIR).join(name);
+            if let Err(err) = fs::copy(src, &dst) {
+                self.error(&dst, err);
+                continue;
+            }
+            self.files.push(dst);
+        }
+
+        // The backend emits all primitives that are defined inline. The
+        // library is temporarily replaced so that the external sources,
+        // which have already been copied, are not emitted again.
+        let mut inline = LibrarySignatures::default();
+        for (prim, _) in ctx.lib.prim_inlines() {
+            inline.add_inline_primitive(prim.clone());
+        }
+        let lib = std::mem::replace(&mut ctx.lib, inline);
+        let dst = self.dir.join(EXTERN_DIR).join("calyx.sv");
+        let res = VerilogBackend::link_externs(
+            ctx,
+            &mut OutputFile::File(dst.clone()),
+        );
+        ctx.lib = lib;
+        match res {
+            Ok(()) => self.files.push(dst),
+            Err(err) => self.error(&dst, format!("{err:?}")),
+        }
+    }
+
+    /// Write each component to its own file
+    fn modules(&mut self, ctx: &mut calyx::Context) {
+        let comps = std::mem::take(&mut ctx.components);
+        for comp in comps.into_iter().sorted_by_key(|c| c.name.to_string()) {
+            let dst = self.dir.join(format!("{}.sv", comp.name));
+            ctx.components = vec![comp];
+            match VerilogBackend::emit(ctx, &mut OutputFile::File(dst.clone()))
+            {
+                Ok(()) => self.files.push(dst),
+                Err(err) => self.error(&dst, format!("{err:?}")),
+            }
+        }
+    }
+
+    /// Write the list of files in the order they should be read
+    fn file_list(&mut self, toplevel: &calyx::Id) {
+        let dst = self.dir.join(format!("{toplevel}.f"));
+        let list = self
+            .files
+            .iter()
+            .map(|f| format!("{}\n", f.display()))
+            .collect::<String>();
+        if let Err(err) = fs::write(&dst, list) {
+            self.error(&dst, err);
+        }
+    }
+}
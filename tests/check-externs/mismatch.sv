@@ -0,0 +1,20 @@
+module Scale #(
+  parameter WIDTH = 32,
+  parameter SHIFT = 1
+) (
+  input wire clk,
+  input wire logic valid,
+  input wire logic [WIDTH-1:0] in,
+  input wire logic [WIDTH:0] bias,
+  output logic [WIDTH-1:0] out,
+  input wire logic [2*WIDTH-1:0] wide
+);
+endmodule
+
+module Acc #(parameter W) (
+  input wire clk,
+  input wire reset,
+  input wire [W-1:0] in,
+  output wire [W-1:0] out
+);
+endmodule
@@ -0,0 +1,4 @@
+module Broken #(parameter W = 1)
+  input [W-1:0] a
+);
+endmodule
@@ -0,0 +1,40 @@
+// A FIFO with a parameterized width and depth
+module Fifo #(
+  parameter WIDTH = 32,
+  parameter DEPTH = 4,
+  localparam ADDR = $clog2(DEPTH)
+) (
+  input wire clk,
+  input wire reset,
+  (* keep *) input logic push,
+  input logic [WIDTH-1:0] in,
+  input logic pop,
+  output logic [WIDTH-1:0] out,
+  output logic [$clog2(DEPTH):0] count,
+  output logic full, empty
+);
+  always_ff @(posedge clk) begin
+    /* body is ignored */
+  end
+endmodule
+
+// Non-ANSI header with parameters in the body
+module Mult(clk, left, right, out, bus);
+  parameter W = 8;
+  parameter OUT_W = 2*W;
+  input clk;
+  input [W-1:0] left, right;
+  output reg [OUT_W-1:0] out;
+  inout [3:0] bus;
+  function [W-1:0] id(input [W-1:0] x);
+    id = x;
+  endfunction
+  always @(*) out = left * right;
+endmodule
+
+module Mem #(parameter int unsigned N = 2**3, parameter type T = logic) (
+  input logic [7:0] data [N],
+  input T val,
+  output logic [N*8-1:0] flat
+);
+endmodule